@@ -0,0 +1,268 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, LitStr, Token};
+
+/// Derives `Persist` for a struct by inserting its fields into a table.
+///
+/// The entity's `Manifest::Context` must implement `SqlConnection`, and `SqlValue` must be
+/// convertible from a reference to each column, e.g. `impl From<&Isbn> for SqlValue`.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::{persist, Associations, CapturingContext, Manifest, Persist};
+/// #[derive(Persist)]
+/// #[malignius(table = "movie")]
+/// struct Movie {
+///     title: String,
+///     #[malignius(column = "release_year")]
+///     year: u32,
+///     #[malignius(skip)]
+///     rating: Option<u8>,
+/// }
+/// # impl Manifest for Movie {
+/// #     type Context = CapturingContext;
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<CapturingContext>) {
+/// #         let movie = Movie { title: "Arrival".into(), year: 2016, rating: None };
+/// #         (movie, Associations::new())
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(CapturingContext::new());
+/// # let _: Movie = persist(ctx.clone()).await?;
+/// # assert_eq!(
+/// #     ctx.statements()[0].sql,
+/// #     "insert into movie (title, release_year) values ($1, $2)"
+/// # );
+/// # Ok(())
+/// # }
+/// ```
+#[proc_macro_derive(Persist, attributes(malignius))]
+pub fn derive_persist(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_persist(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_persist(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut table = None;
+    for attr in &input.attrs {
+        if !attr.path().is_ident("malignius") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `malignius` attribute"))
+            }
+        })?;
+    }
+
+    let table = table.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "`#[derive(Persist)]` requires a `#[malignius(table = \"...\")]` attribute",
+        )
+    })?;
+
+    let fields = named_fields(&input, "Persist")?;
+
+    let mut columns = Vec::new();
+    let mut values = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+
+        let mut column = ident.to_string();
+        let mut skip = false;
+        for attr in &field.attrs {
+            if !attr.path().is_ident("malignius") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("column") {
+                    column = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported `malignius` attribute"))
+                }
+            })?;
+        }
+
+        if skip {
+            continue;
+        }
+
+        columns.push(column);
+        values.push(quote! {
+            ::malignius::SqlValue::from(&entity.#ident)
+        });
+    }
+
+    let placeholders = (1..=columns.len())
+        .map(|n| format!("${n}"))
+        .collect::<Vec<_>>();
+    let sql = format!(
+        "insert into {table} ({}) values ({})",
+        columns.join(", "),
+        placeholders.join(", ")
+    );
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::malignius::Persist for #ident #ty_generics #where_clause {
+            type Err = <<Self as ::malignius::Manifest>::Context as ::malignius::SqlConnection>::Err;
+
+            async fn persist(
+                ctx: &<Self as ::malignius::Manifest>::Context,
+                entity: Self,
+            ) -> ::std::result::Result<Self, Self::Err> {
+                let rows = ::malignius::SqlConnection::execute(ctx, #sql, vec![#(#values),*]).await?;
+                ::malignius::record_rows_affected::<Self>(rows);
+
+                Ok(entity)
+            }
+        }
+    })
+}
+
+/// Derives `StrictOverrides` for overrides made up of `Option` fields, reporting every field that
+/// is `None` as unset.
+///
+/// This can be added to the builders generated by `derive_builder`, so that fields added to the
+/// entity later are checked too:
+///
+/// ```
+/// # use derive_builder::Builder;
+/// # use malignius::StrictOverrides;
+/// #[derive(Builder)]
+/// #[builder(derive(StrictOverrides))]
+/// struct Movie {
+///     title: String,
+///     year: u32,
+/// }
+/// # let mut movie = MovieBuilder::default();
+/// # movie.title("Arrival".into());
+/// # assert_eq!(movie.unset_fields(), ["year"]);
+/// ```
+#[proc_macro_derive(StrictOverrides)]
+pub fn derive_strict_overrides(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_strict_overrides(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_strict_overrides(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = named_fields(&input, "StrictOverrides")?
+        .iter()
+        .map(|field| field.ident.as_ref().unwrap());
+    let names = fields.clone().map(|ident| ident.to_string());
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::malignius::StrictOverrides for #ident #ty_generics #where_clause {
+            fn unset_fields(&self) -> ::std::vec::Vec<&'static str> {
+                let mut unset_fields = ::std::vec::Vec::new();
+                #(
+                    if self.#fields.is_none() {
+                        unset_fields.push(#names);
+                    }
+                )*
+
+                unset_fields
+            }
+        }
+    })
+}
+
+/// Derives `OverrideProvenance` for overrides made up of `Option` fields, reporting every field
+/// that is `Some` as overridden and every other field as defaulted.
+///
+/// ```
+/// # use malignius::{OverrideProvenance, Source};
+/// #[derive(Default, OverrideProvenance)]
+/// struct MovieOverrides {
+///     title: Option<String>,
+///     year: Option<u32>,
+/// }
+/// # let overrides = MovieOverrides {
+/// #     title: Some("Arrival".into()),
+/// #     ..Default::default()
+/// # };
+/// # assert_eq!(
+/// #     overrides.field_sources(),
+/// #     [("title", Source::Override), ("year", Source::Default)]
+/// # );
+/// ```
+#[proc_macro_derive(OverrideProvenance)]
+pub fn derive_override_provenance(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_override_provenance(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_override_provenance(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = named_fields(&input, "OverrideProvenance")?
+        .iter()
+        .map(|field| field.ident.as_ref().unwrap());
+    let names = fields.clone().map(|ident| ident.to_string());
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::malignius::OverrideProvenance for #ident #ty_generics #where_clause {
+            fn field_sources(&self) -> ::std::vec::Vec<(&'static str, ::malignius::Source)> {
+                ::std::vec![
+                    #(
+                        (
+                            #names,
+                            match self.#fields {
+                                ::std::option::Option::Some(_) => ::malignius::Source::Override,
+                                ::std::option::Option::None => ::malignius::Source::Default,
+                            },
+                        ),
+                    )*
+                ]
+            }
+        }
+    })
+}
+
+/// Returns the fields of a struct with named fields, or an error naming the derive otherwise.
+fn named_fields<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a Punctuated<Field, Token![,]>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                format!("`#[derive({derive})]` only supports structs with named fields"),
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("`#[derive({derive})]` only supports structs"),
+        )),
+    }
+}
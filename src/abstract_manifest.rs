@@ -0,0 +1,152 @@
+use crate::Manifest;
+
+/// A [`Manifest`] that is only a building block for other factories and is never persisted.
+///
+/// Abstract types can be manifested and [embedded](crate::embed), but since they never implement
+/// [`Persist`](crate::Persist) they can't be persisted or used as an association. Implement this
+/// with [`abstract_manifest!`](crate::abstract_manifest), which fails to compile if the type also
+/// implements `Persist`.
+///
+/// Persisting an abstract type is a compile error:
+///
+/// ```compile_fail
+/// use std::sync::Arc;
+///
+/// use malignius::{abstract_manifest, persist, Associations, Manifest};
+///
+/// struct Address {
+///     pub city: String,
+/// }
+///
+/// impl Manifest for Address {
+///     type Context = ();
+///     type Overrides = ();
+///
+///     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+///         (Self { city: "Lisbon".into() }, Associations::new())
+///     }
+/// }
+///
+/// abstract_manifest!(Address);
+///
+/// async fn persist_address() {
+///     let _: Address = persist(Arc::new(())).await.unwrap();
+/// }
+/// ```
+///
+/// As is marking a type that implements `Persist` as abstract:
+///
+/// ```compile_fail
+/// use malignius::{abstract_manifest, Associations, Manifest, Persist};
+///
+/// struct Address {
+///     pub city: String,
+/// }
+///
+/// impl Manifest for Address {
+///     type Context = ();
+///     type Overrides = ();
+///
+///     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+///         (Self { city: "Lisbon".into() }, Associations::new())
+///     }
+/// }
+///
+/// impl Persist for Address {
+///     type Err = std::convert::Infallible;
+///
+///     async fn persist(_ctx: &Self::Context, address: Self) -> Result<Self, Self::Err> {
+///         Ok(address)
+///     }
+/// }
+///
+/// abstract_manifest!(Address);
+/// ```
+pub trait AbstractManifest: Manifest {}
+
+/// Implements [`AbstractManifest`] for a type, checking that it does not implement `Persist`.
+///
+/// ```
+/// # use malignius::{Associations, Manifest};
+/// # struct Address;
+/// # impl Manifest for Address {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         (Self, Associations::new())
+/// #     }
+/// # }
+/// malignius::abstract_manifest!(Address);
+/// ```
+#[macro_export]
+macro_rules! abstract_manifest {
+    ($ty:ty) => {
+        impl $crate::AbstractManifest for $ty {}
+
+        const _: fn() = || {
+            // This is ambiguous, and therefore fails to compile, if the type implements `Persist`.
+            let _ = <$ty as $crate::AmbiguousIfPersist<_>>::check;
+        };
+    };
+}
+
+/// Used by [`abstract_manifest!`](crate::abstract_manifest) to detect `Persist` implementations.
+#[doc(hidden)]
+pub trait AmbiguousIfPersist<A> {
+    fn check() {}
+}
+
+impl<T: ?Sized> AmbiguousIfPersist<()> for T {}
+
+#[doc(hidden)]
+pub struct IsPersist;
+
+impl<T: ?Sized + crate::Persist> AmbiguousIfPersist<IsPersist> for T {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{embed, manifest, Associations};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Address {
+        pub city: String,
+    }
+
+    impl Manifest for Address {
+        type Context = ();
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    city: "Lisbon".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    crate::abstract_manifest!(Address);
+
+    fn assert_abstract<T: AbstractManifest>() {}
+
+    #[test]
+    fn abstract_types_can_be_manifested_and_embedded() {
+        assert_abstract::<Address>();
+
+        assert_eq!(
+            manifest::<Address>(),
+            Address {
+                city: "Lisbon".into()
+            }
+        );
+        assert_eq!(
+            embed::<Address>(),
+            Address {
+                city: "Lisbon".into()
+            }
+        );
+    }
+}
@@ -1,16 +1,167 @@
 use std::any::{Any, TypeId};
+use std::fmt;
 use std::future::Future;
+use std::marker::PhantomData;
 use std::pin::Pin;
-use std::sync::Arc;
 
-use crate::{manifest, persist, Persist};
+use crate::{
+    configured_options, graph_path, persist_in_with, persist_manifested, sequences, GraphShape,
+    InitialOverrides, MaligniusError, Manifest, Persist,
+};
 
-pub fn association<T: Persist + 'static>(associations: &mut Associations<T::Context>) -> T {
-    let entity = manifest::<T>();
+/// Manifests an entity of type `T` and registers it to be persisted as an association.
+///
+/// Associations are persisted before the entity that registered them, grouped by type and in
+/// the order they were registered within each type (see [`Associations`]). Since registration
+/// happens as soon as this is called, factories should only call it when the association is
+/// actually needed (see [`lazy_association`]).
+pub fn association<T>(associations: &mut Associations<T::Context>) -> T
+where
+    T: Persist + 'static,
+    T::Err: std::error::Error + 'static,
+{
+    register_association(associations, T::Overrides::initial)
+}
+
+/// Manifests an entity of type `T` with the given overrides and registers it to be persisted as
+/// an association.
+///
+/// The returned entity reflects the overrides, so the parent can read fields derived from them.
+///
+/// ```
+/// # use malignius::*;
+/// # #[derive(Clone, Default)]
+/// # struct AuthorOverrides { name: Option<String> }
+/// # #[derive(Clone)]
+/// # struct Author { id: u64, name: String }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = AuthorOverrides;
+/// #     fn manifest(overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let name = overrides.name.unwrap_or_else(|| "N. K. Jemisin".into());
+/// #         (Self { id: 1, name }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # let mut associations = Associations::new();
+/// let author: Author = association_with(&mut associations, AuthorOverrides {
+///     name: Some("Ted Chiang".into()),
+///     ..Default::default()
+/// });
+/// # assert_eq!(author.name, "Ted Chiang");
+/// ```
+pub fn association_with<T>(
+    associations: &mut Associations<T::Context>,
+    overrides: T::Overrides,
+) -> T
+where
+    T: Persist + 'static,
+    T::Overrides: Clone,
+    T::Err: std::error::Error + 'static,
+{
+    register_association(associations, move || overrides.clone())
+}
+
+/// Manifests `n` entities of type `T` and registers each of them to be persisted as an
+/// association, for has-many relationships.
+///
+/// Unlike [`association`], each entity is persisted exactly as it was manifested rather than
+/// being manifested again, so values drawn from sequences, such as ids, are the same in the
+/// returned entities as in the persisted ones.
+///
+/// ```
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Tag { id: u64 }
+/// # impl Manifest for Tag {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let id = sequences::next_named("tag", |n| n as u64);
+/// #         (Self { id }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Tag {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, tag: Self) -> Result<Self, Self::Err> {
+/// #         Ok(tag)
+/// #     }
+/// # }
+/// # let mut associations = Associations::new();
+/// let tags: Vec<Tag> = association_many(&mut associations, 3);
+/// let tag_ids = tags.iter().map(|tag| tag.id).collect();
+/// # let _: Vec<u64> = tag_ids;
+/// # assert_eq!(associations.len(), 3);
+/// ```
+pub fn association_many<T>(associations: &mut Associations<T::Context>, n: usize) -> Vec<T>
+where
+    T: Persist + Clone + 'static,
+    T::Err: std::error::Error + 'static,
+{
+    (0..n)
+        .map(|_| {
+            let path = graph_path().map(|path| path.child::<T>(associations.associations.len()));
+            let (entity, children) = match path.clone() {
+                Some(path) => graph_path::with_path(path, || T::manifest(T::Overrides::initial())),
+                None => T::manifest(T::Overrides::initial()),
+            };
+
+            let manifested = entity.clone();
+            let shape = GraphShape::manifested::<T, _>(&children);
+            associations.persist::<T, _>(shape, move |ctx| {
+                Box::pin(async move {
+                    let mut persisted = graph_path::in_path(
+                        path,
+                        persist_manifested(
+                            ctx,
+                            vec![(manifested, children)],
+                            &configured_options::<T>(),
+                        ),
+                    )
+                    .await
+                    .map_err(MaligniusError::into_association_error::<T>)?;
+
+                    Ok(persisted.remove(0))
+                })
+            });
+
+            entity
+        })
+        .collect()
+}
 
-    associations.persist::<T, _>(move |conn| {
+fn register_association<T>(
+    associations: &mut Associations<T::Context>,
+    overrides: impl Fn() -> T::Overrides + 'static,
+) -> T
+where
+    T: Persist + 'static,
+    T::Err: std::error::Error + 'static,
+{
+    // The association is manifested again when it is persisted, so both need the same path,
+    // overrides and `per_persist` counters for their `graph_id`s and fields to match.
+    let path = graph_path().map(|path| path.child::<T>(associations.associations.len()));
+    let checkpoint = sequences::per_persist_checkpoint();
+    let manifest = || graph_path::with_root::<T, _>(|| T::manifest(overrides()));
+    let (entity, children) = match path.clone() {
+        Some(path) => graph_path::with_path(path, manifest),
+        None => manifest(),
+    };
+    let shape = GraphShape::manifested::<T, _>(&children);
+
+    associations.persist::<T, _>(shape, move |ctx| {
         Box::pin(async move {
-            let entity = persist::<T>(conn).await.map_err(|_| "failed to persist")?;
+            let entity = sequences::replaying_per_persist(
+                checkpoint,
+                graph_path::in_path(path, persist_in_with::<T>(ctx, overrides())),
+            )
+            .await
+            .map_err(MaligniusError::into_association_error::<T>)?;
 
             Ok(entity)
         })
@@ -19,17 +170,231 @@ pub fn association<T: Persist + 'static>(associations: &mut Associations<T::Cont
     entity
 }
 
+/// Returns the overridden key if present, otherwise registers an association and returns its key.
+///
+/// The association is only manifested and registered when no key was supplied, so overriding a
+/// foreign key never results in an unused entity being persisted.
+///
+/// ```
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Post { id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 2 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # struct CommentOverrides { post_id: Option<u64> }
+/// # let overrides = CommentOverrides { post_id: Some(7) };
+/// # let mut associations = Associations::new();
+/// let post_id = lazy_association::<Post, _>(&mut associations, overrides.post_id, |post| post.id);
+/// # assert_eq!(post_id, 7);
+/// # assert!(associations.is_empty());
+/// ```
+pub fn lazy_association<T, Key>(
+    associations: &mut Associations<T::Context>,
+    key_override: Option<Key>,
+    key: impl FnOnce(&T) -> Key,
+) -> Key
+where
+    T: Persist + 'static,
+    T::Err: std::error::Error + 'static,
+{
+    match key_override {
+        Some(key) => key,
+        None => key(&association::<T>(associations)),
+    }
+}
+
+/// Manifests an entity of type `T` and registers it to be persisted as an association, but only
+/// if `condition` holds.
+///
+/// When `condition` is false nothing is registered and `None` is returned, so a factory can skip
+/// a dependency that the caller has already provided, such as an existing author.
+///
+/// ```
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 1 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # struct PostOverrides { author_id: Option<u64> }
+/// # let overrides = PostOverrides { author_id: None };
+/// # let mut associations = Associations::new();
+/// let author: Option<Author> =
+///     optional_association(&mut associations, overrides.author_id.is_none());
+/// # assert!(author.is_some());
+/// ```
+pub fn optional_association<T>(
+    associations: &mut Associations<T::Context>,
+    condition: bool,
+) -> Option<T>
+where
+    T: Persist + 'static,
+    T::Err: std::error::Error + 'static,
+{
+    condition.then(|| association::<T>(associations))
+}
+
+/// An error that occurred while persisting an association.
+///
+/// The underlying error is available through [`std::error::Error::source`].
+#[derive(Debug)]
+pub struct AssociationError {
+    entity_type: &'static str,
+    source: Box<dyn std::error::Error>,
+}
+
+impl AssociationError {
+    pub(crate) fn new<T>(source: Box<dyn std::error::Error>) -> Self {
+        Self {
+            entity_type: std::any::type_name::<T>(),
+            source,
+        }
+    }
+
+    /// Returns the type name of the association that failed to persist.
+    pub fn entity_type(&self) -> &'static str {
+        self.entity_type
+    }
+}
+
+impl fmt::Display for AssociationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to persist association `{}`", self.entity_type)
+    }
+}
+
+impl std::error::Error for AssociationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// A typed relationship from a `Parent` entity to the `Child` entity it references.
+///
+/// The edge captures how to extract the key the parent stores from the child.
+pub struct Edge<Parent, Child, Key> {
+    key: Box<dyn FnOnce(&Child) -> Key>,
+    _parent: PhantomData<Parent>,
+}
+
+impl<Parent, Child, Key> Edge<Parent, Child, Key>
+where
+    Parent: Manifest<Context = Child::Context>,
+    Child: Persist + 'static,
+    Child::Err: std::error::Error + 'static,
+{
+    pub fn new(key: impl FnOnce(&Child) -> Key + 'static) -> Self {
+        Self {
+            key: Box::new(key),
+            _parent: PhantomData,
+        }
+    }
+
+    /// Registers the child as an association and returns its key.
+    pub fn associate(self, associations: &mut Associations<Child::Context>) -> Key {
+        let child = association::<Child>(associations);
+
+        (self.key)(&child)
+    }
+}
+
+/// Registers `Child` as an association of `Parent` and returns the key extracted from it.
+///
+/// ```
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 1 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Post { id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 2 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # let mut associations = Associations::new();
+/// let author_id = edge::<Post, Author, _>(&mut associations, |author| author.id);
+/// # assert_eq!(author_id, 1);
+/// ```
+pub fn edge<Parent, Child, Key>(
+    associations: &mut Associations<Child::Context>,
+    key: impl FnOnce(&Child) -> Key + 'static,
+) -> Key
+where
+    Parent: Manifest<Context = Child::Context>,
+    Child: Persist + 'static,
+    Child::Err: std::error::Error + 'static,
+{
+    Edge::<Parent, Child, Key>::new(key).associate(associations)
+}
+
+type PersistFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, Box<dyn std::error::Error>>> + 'a>>;
+
+type PersistFn<Context> = Box<dyn for<'a> FnOnce(&'a Context) -> PersistFuture<'a, Box<dyn Any>>>;
+
 pub(crate) struct AnyAssociation<Context> {
-    entity_type: TypeId,
-    pub(crate) persist: Box<
-        dyn FnOnce(
-            Arc<Context>,
-        ) -> Pin<
-            Box<dyn Future<Output = Result<Box<dyn Any>, Box<dyn std::error::Error>>>>,
-        >,
-    >,
+    pub(crate) entity_type: TypeId,
+    pub(crate) entity_type_name: &'static str,
+    index: usize,
+    /// The types of the associations that have to be persisted before this one.
+    depends_on: Vec<TypeId>,
+    /// The shape of the association's graph, as it was manifested when it was registered.
+    pub(crate) shape: GraphShape,
+    pub(crate) persist: PersistFn<Context>,
 }
 
+/// The associations registered while manifesting an entity.
+///
+/// Associations are persisted in a deterministic order: sorted by type, then by the order they
+/// were registered. This keeps the order in which types are persisted stable even when a factory
+/// registers associations while iterating over an unordered collection such as a `HashMap`.
+/// Associations of the same type keep their registration order.
+///
+/// When one association refers to another, such as a `Post` association that needs an `Author`
+/// association to exist first, the dependency can be declared with
+/// [`Associations::depends_on`]. Dependencies take precedence over the order above.
 pub struct Associations<Context> {
     pub(crate) associations: Vec<AnyAssociation<Context>>,
 }
@@ -41,29 +406,459 @@ impl<Context: 'static> Associations<Context> {
         }
     }
 
-    pub(crate) fn persist<
-        T: 'static,
-        F: FnOnce(
-                Arc<Context>,
-            )
-                -> Pin<Box<dyn Future<Output = Result<T, Box<dyn std::error::Error>>>>>
-            + 'static,
-    >(
-        &mut self,
-        persist: F,
-    ) {
+    /// Returns the number of associations registered.
+    pub fn len(&self) -> usize {
+        self.associations.len()
+    }
+
+    /// Returns whether no associations have been registered.
+    pub fn is_empty(&self) -> bool {
+        self.associations.is_empty()
+    }
+
+    /// Returns the type of each association, in the order they were registered.
+    ///
+    /// This only inspects the associations; none of them are persisted.
+    pub fn entity_types(&self) -> Vec<TypeId> {
+        self.associations
+            .iter()
+            .map(|association| association.entity_type)
+            .collect()
+    }
+
+    pub(crate) fn persist<T, F>(&mut self, shape: GraphShape, persist: F)
+    where
+        T: Manifest + 'static,
+        F: for<'a> FnOnce(&'a Context) -> PersistFuture<'a, T> + 'static,
+    {
         self.associations.push(AnyAssociation {
             entity_type: TypeId::of::<T>(),
-            persist: Box::new(|conn| {
+            entity_type_name: std::any::type_name::<T>(),
+            index: self.associations.len(),
+            depends_on: Vec::new(),
+            shape,
+            persist: Box::new(|ctx| {
                 Box::pin(async move {
-                    let value = persist(conn).await?;
+                    let value = persist(ctx).await?;
 
                     Ok(Box::new(value) as Box<dyn Any>)
                 })
-                    as Pin<
-                        Box<dyn Future<Output = Result<Box<dyn Any>, Box<dyn std::error::Error>>>>,
-                    >
             }),
         });
     }
+
+    /// Declares that the association registered last depends on the associations of type
+    /// `Dependency`, so that it is persisted after all of them.
+    ///
+    /// Dependencies on types that have no associations registered are ignored. The order is
+    /// only guaranteed when associations are persisted one at a time, which is the default.
+    ///
+    /// ```
+    /// # use malignius::*;
+    /// # #[derive(Clone)]
+    /// # struct Author { id: u64 }
+    /// # impl Manifest for Author {
+    /// #     type Context = ();
+    /// #     type Overrides = ();
+    /// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+    /// #         (Self { id: 1 }, Associations::new())
+    /// #     }
+    /// # }
+    /// # impl Persist for Author {
+    /// #     type Err = std::convert::Infallible;
+    /// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+    /// #         Ok(author)
+    /// #     }
+    /// # }
+    /// # #[derive(Clone)]
+    /// # struct Post { id: u64 }
+    /// # impl Manifest for Post {
+    /// #     type Context = ();
+    /// #     type Overrides = ();
+    /// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+    /// #         (Self { id: 2 }, Associations::new())
+    /// #     }
+    /// # }
+    /// # impl Persist for Post {
+    /// #     type Err = std::convert::Infallible;
+    /// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+    /// #         Ok(post)
+    /// #     }
+    /// # }
+    /// # let mut associations = Associations::new();
+    /// let author: Author = association(&mut associations);
+    /// let post: Post = association(&mut associations);
+    /// associations.depends_on::<Author>();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if no association has been registered yet.
+    pub fn depends_on<Dependency: 'static>(&mut self) -> &mut Self {
+        self.associations
+            .last_mut()
+            .expect("`depends_on` must be called after registering an association")
+            .depends_on
+            .push(TypeId::of::<Dependency>());
+
+        self
+    }
+
+    /// Returns the associations in the order they should be persisted, or an error if their
+    /// dependencies form a cycle.
+    pub(crate) fn into_ordered(mut self) -> Result<Vec<AnyAssociation<Context>>, DependencyCycle> {
+        self.associations
+            .sort_by_key(|association| (association.entity_type, association.index));
+
+        // Repeatedly take the first association whose dependencies have all been taken, so
+        // associations without dependencies keep the order above.
+        let mut remaining = self.associations;
+        let mut ordered = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let ready = remaining.iter().position(|association| {
+                association.depends_on.iter().all(|dependency| {
+                    *dependency == association.entity_type
+                        || remaining
+                            .iter()
+                            .all(|other| other.entity_type != *dependency)
+                })
+            });
+
+            match ready {
+                Some(index) => ordered.push(remaining.remove(index)),
+                None => {
+                    let mut entity_types = remaining
+                        .iter()
+                        .map(|association| association.entity_type_name)
+                        .collect::<Vec<_>>();
+                    entity_types.dedup();
+
+                    return Err(DependencyCycle { entity_types });
+                }
+            }
+        }
+
+        Ok(ordered)
+    }
+}
+
+impl<Context: 'static> Default for Associations<Context> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An error returned when the dependencies declared with [`Associations::depends_on`] form a
+/// cycle, so the associations cannot be put in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyCycle {
+    entity_types: Vec<&'static str>,
+}
+
+impl DependencyCycle {
+    /// Returns the type names of the associations that could not be put in order.
+    pub fn entity_types(&self) -> &[&'static str] {
+        &self.entity_types
+    }
+}
+
+impl fmt::Display for DependencyCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "associations have a dependency cycle between ")?;
+
+        for (index, entity_type) in self.entity_types.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "`{entity_type}`")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for DependencyCycle {}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use crate::test_support::{shared, TestContext};
+    use crate::{persist, Persist};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingContext {
+        pub persisted: RefCell<Vec<&'static str>>,
+    }
+
+    macro_rules! recorded_entity {
+        ($name:ident, $overrides:ident) => {
+            #[derive(Debug, Default)]
+            struct $overrides {}
+
+            #[derive(Debug)]
+            struct $name {}
+
+            impl Manifest for $name {
+                type Context = RecordingContext;
+                type Overrides = $overrides;
+
+                fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+                    (Self {}, Associations::new())
+                }
+            }
+
+            impl Persist for $name {
+                type Err = std::convert::Infallible;
+
+                async fn persist(ctx: &Self::Context, entity: Self) -> Result<Self, Self::Err> {
+                    ctx.persisted.borrow_mut().push(stringify!($name));
+                    Ok(entity)
+                }
+            }
+        };
+    }
+
+    recorded_entity!(Author, AuthorOverrides);
+    recorded_entity!(Tag, TagOverrides);
+    recorded_entity!(Category, CategoryOverrides);
+
+    #[derive(Debug, Default)]
+    struct PostOverrides {}
+
+    #[derive(Debug)]
+    struct Post {}
+
+    impl Manifest for Post {
+        type Context = RecordingContext;
+        type Overrides = PostOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+
+            // A fresh `HashMap` is seeded randomly, so its iteration order varies between calls.
+            let related = HashMap::from([(1, "author"), (2, "tag"), (3, "category")]);
+            for kind in related.values() {
+                match *kind {
+                    "author" => {
+                        association::<Author>(&mut associations);
+                    }
+                    "tag" => {
+                        association::<Tag>(&mut associations);
+                    }
+                    _ => {
+                        association::<Category>(&mut associations);
+                    }
+                }
+            }
+
+            (Self {}, associations)
+        }
+    }
+
+    impl Persist for Post {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            Ok(post)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Review {
+        pub author_id: u32,
+    }
+
+    impl Manifest for Review {
+        type Context = RecordingContext;
+        type Overrides = Option<u32>;
+
+        fn manifest(author_id: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author: Option<Author> =
+                optional_association(&mut associations, author_id.is_none());
+
+            (
+                Self {
+                    author_id: author_id.or(author.map(|_| 1)).unwrap(),
+                },
+                associations,
+            )
+        }
+    }
+
+    #[test]
+    fn optional_associations_are_only_registered_when_needed() {
+        let (review, associations) = Review::manifest(Some(7));
+        assert_eq!(review.author_id, 7);
+        assert!(associations.is_empty());
+
+        let (review, associations) = Review::manifest(None);
+        assert_eq!(review.author_id, 1);
+        assert_eq!(associations.entity_types(), vec![TypeId::of::<Author>()]);
+    }
+
+    #[test]
+    fn default_associations_are_empty() {
+        assert!(Associations::<()>::default().is_empty());
+    }
+
+    #[tokio::test]
+    async fn associations_from_an_unordered_source_persist_in_a_stable_order(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut orders = Vec::new();
+        for _ in 0..20 {
+            let ctx = shared(RecordingContext::default());
+            let _: Post = persist(ctx.clone()).await?;
+
+            orders.push(ctx.persisted.take());
+        }
+
+        assert_eq!(orders[0].len(), 3);
+        assert!(orders.iter().all(|order| order == &orders[0]));
+
+        Ok(())
+    }
+
+    struct Chain;
+
+    impl Manifest for Chain {
+        type Context = RecordingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+
+            // Registered in the opposite order to the one they have to be persisted in.
+            association::<Tag>(&mut associations);
+            associations.depends_on::<Category>();
+            association::<Category>(&mut associations);
+            associations.depends_on::<Author>();
+            association::<Author>(&mut associations);
+
+            (Self, associations)
+        }
+    }
+
+    impl Persist for Chain {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, chain: Self) -> Result<Self, Self::Err> {
+            Ok(chain)
+        }
+    }
+
+    #[tokio::test]
+    async fn associations_are_persisted_after_their_dependencies(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = shared(RecordingContext::default());
+
+        let _: Chain = persist(ctx.clone()).await?;
+
+        assert_eq!(ctx.persisted.take(), ["Author", "Category", "Tag"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dependency_cycles_are_reported() {
+        let mut associations = Associations::<RecordingContext>::new();
+        association::<Author>(&mut associations);
+        associations.depends_on::<Tag>();
+        association::<Tag>(&mut associations);
+        associations.depends_on::<Author>();
+        association::<Category>(&mut associations);
+
+        let err = associations.into_ordered().err().unwrap();
+
+        assert_eq!(err.entity_types().len(), 2);
+        assert!(err
+            .entity_types()
+            .contains(&std::any::type_name::<Author>()));
+        assert!(err.entity_types().contains(&std::any::type_name::<Tag>()));
+    }
+
+    #[derive(Debug, Clone)]
+    struct Comment {
+        pub id: usize,
+    }
+
+    impl Manifest for Comment {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: crate::sequences::namespace("association_many").next("comment", |n| n),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Comment {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+            ctx.conn
+                .execute("insert into comment (id) values ($1)", [comment.id])?;
+
+            Ok(comment)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Thread {
+        pub comment_ids: Vec<usize>,
+    }
+
+    impl Manifest for Thread {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let comments: Vec<Comment> = association_many(&mut associations, 3);
+
+            (
+                Self {
+                    comment_ids: comments.iter().map(|comment| comment.id).collect(),
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Thread {
+        type Err = rusqlite::Error;
+
+        async fn persist(_ctx: &Self::Context, thread: Self) -> Result<Self, Self::Err> {
+            Ok(thread)
+        }
+    }
+
+    #[tokio::test]
+    async fn association_many_persists_every_entity() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared("create table comment (id integer primary key)")?;
+
+        let thread: Thread = persist(ctx.clone()).await?;
+
+        let comment_ids = {
+            let mut stmt = ctx.conn.prepare("select id from comment order by id")?;
+            let comment_ids = stmt
+                .query_map([], |row| row.get::<_, usize>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            comment_ids
+        };
+
+        assert_eq!(thread.comment_ids.len(), 3);
+        assert_eq!(comment_ids, thread.comment_ids);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use crate::Sequence;
+
+/// A [`Sequence`] that runs out after producing a fixed number of values.
+///
+/// This is useful for values drawn from a finite pool, such as the seats on a flight, where
+/// producing more than the pool holds is a bug in the test.
+///
+/// ```
+/// # use malignius::BoundedSequence;
+/// let mut seats = BoundedSequence::new(180, |n| format!("seat-{n}"));
+/// let seat = seats.try_next()?;
+/// # assert_eq!(seat, "seat-1");
+/// # assert_eq!(seats.remaining(), 179);
+/// # Ok::<(), malignius::SequenceExhausted>(())
+/// ```
+pub struct BoundedSequence<T> {
+    sequence: Sequence<T>,
+    limit: usize,
+    produced: usize,
+}
+
+impl<T> BoundedSequence<T> {
+    /// Creates a sequence that produces at most `limit` values.
+    pub fn new(limit: usize, produce: impl Fn(usize) -> T + 'static) -> Self {
+        Self {
+            sequence: Sequence::new(produce),
+            limit,
+            produced: 0,
+        }
+    }
+
+    /// Returns the next value in the sequence, or `None` once it is exhausted.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<T> {
+        self.try_next().ok()
+    }
+
+    /// Returns the next value in the sequence, or an error once it is exhausted.
+    pub fn try_next(&mut self) -> Result<T, SequenceExhausted> {
+        if self.produced == self.limit {
+            return Err(SequenceExhausted {
+                produced: self.produced,
+                limit: self.limit,
+            });
+        }
+
+        self.produced += 1;
+
+        Ok(self.sequence.next())
+    }
+
+    /// Returns how many more values the sequence can produce.
+    pub fn remaining(&self) -> usize {
+        self.limit - self.produced
+    }
+}
+
+/// The error returned by [`BoundedSequence::try_next`] once the sequence is exhausted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceExhausted {
+    /// The number of values the sequence produced before it was exhausted.
+    pub produced: usize,
+    /// The maximum number of values the sequence was configured to produce.
+    pub limit: usize,
+}
+
+impl fmt::Display for SequenceExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sequence exhausted after producing {} of at most {} values",
+            self.produced, self.limit
+        )
+    }
+}
+
+impl std::error::Error for SequenceExhausted {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_returns_none_once_exhausted() {
+        let mut ids = BoundedSequence::new(2, |n| n);
+
+        assert_eq!(ids.next(), Some(1));
+        assert_eq!(ids.next(), Some(2));
+        assert_eq!(ids.next(), None);
+        assert_eq!(ids.remaining(), 0);
+    }
+
+    #[test]
+    fn try_next_reports_the_produced_count_and_limit() {
+        let mut seats = BoundedSequence::new(3, |n| format!("seat-{n}"));
+
+        for _ in 0..3 {
+            assert!(seats.try_next().is_ok());
+        }
+
+        let err = seats.try_next().unwrap_err();
+        assert_eq!(
+            err,
+            SequenceExhausted {
+                produced: 3,
+                limit: 3
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "sequence exhausted after producing 3 of at most 3 values"
+        );
+    }
+}
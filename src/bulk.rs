@@ -0,0 +1,446 @@
+use std::sync::Arc;
+
+use crate::{configured_options, persist_roots_at, InitialOverrides, MaligniusError, Persist};
+
+/// The entities persisted by [`persist_bulk_hierarchy`], level by level.
+///
+/// Each level is in the order its entities were persisted, so the children of the `n`th parent
+/// are `children[n * children_per_parent..(n + 1) * children_per_parent]`, and likewise for the
+/// grandchildren.
+#[derive(Debug)]
+pub struct BulkHierarchy<Parent, Child, Grandchild> {
+    pub parents: Vec<Parent>,
+    pub children: Vec<Child>,
+    pub grandchildren: Vec<Grandchild>,
+}
+
+/// Persists a three-level hierarchy of entities, such as comments spread across posts spread
+/// across authors.
+///
+/// `parents` root entities are persisted first, then `children_per_parent` children for each of
+/// them, then `grandchildren_per_child` grandchildren for each child. Each level is persisted in
+/// one go, so levels whose type is configured as [`batched`](crate::PersistConfig::batched) are
+/// inserted in batches. The foreign keys are wired up by `child_overrides` and
+/// `grandchild_overrides`, which receive the persisted entity one level up and return the
+/// overrides to manifest each of its children with.
+///
+/// Every entity is the root of its own graph, the same as with
+/// [`persist_many_with`](crate::persist_many_with), so its associations are persisted the same
+/// way. When a child or grandchild fails to persist, the error is returned as an
+/// [`AssociationError`](crate::AssociationError) naming its type.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Debug)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: sequences::next_named("author", |n| n as u64) }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Default)]
+/// # struct PostOverrides { author_id: Option<u64> }
+/// # #[derive(Debug)]
+/// # struct Post { id: u64, author_id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = PostOverrides;
+/// #     fn manifest(overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let id = sequences::next_named("post", |n| n as u64);
+/// #         (Self { id, author_id: overrides.author_id.unwrap_or(0) }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[derive(Default)]
+/// # struct CommentOverrides { post_id: Option<u64> }
+/// # #[derive(Debug)]
+/// # struct Comment { id: u64, post_id: u64 }
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = CommentOverrides;
+/// #     fn manifest(overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let id = sequences::next_named("comment", |n| n as u64);
+/// #         (Self { id, post_id: overrides.post_id.unwrap_or(0) }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), comment: Self) -> Result<Self, Self::Err> {
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let hierarchy = persist_bulk_hierarchy::<Author, Post, Comment>(
+///     ctx,
+///     10,
+///     10,
+///     10,
+///     |author| PostOverrides { author_id: Some(author.id), ..Default::default() },
+///     |post| CommentOverrides { post_id: Some(post.id), ..Default::default() },
+/// )
+/// .await?;
+///
+/// assert_eq!(hierarchy.grandchildren.len(), 1000);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_bulk_hierarchy<Parent, Child, Grandchild>(
+    ctx: Arc<Parent::Context>,
+    parents: usize,
+    children_per_parent: usize,
+    grandchildren_per_child: usize,
+    child_overrides: impl FnMut(&Parent) -> Child::Overrides,
+    grandchild_overrides: impl FnMut(&Child) -> Grandchild::Overrides,
+) -> Result<BulkHierarchy<Parent, Child, Grandchild>, MaligniusError<Parent::Err>>
+where
+    Parent: Persist + 'static,
+    Child: Persist<Context = Parent::Context> + 'static,
+    Child::Err: std::error::Error + 'static,
+    Grandchild: Persist<Context = Parent::Context> + 'static,
+    Grandchild::Err: std::error::Error + 'static,
+{
+    let parents =
+        persist_level::<(), Parent>(&ctx, &[()], parents, 0, |_| Parent::Overrides::initial())
+            .await?;
+    let children =
+        persist_level::<Parent, Child>(&ctx, &parents, children_per_parent, 0, child_overrides)
+            .await
+            .map_err(level_error::<Child, _>)?;
+    let grandchildren = persist_level::<Child, Grandchild>(
+        &ctx,
+        &children,
+        grandchildren_per_child,
+        0,
+        grandchild_overrides,
+    )
+    .await
+    .map_err(level_error::<Grandchild, _>)?;
+
+    Ok(BulkHierarchy {
+        parents,
+        children,
+        grandchildren,
+    })
+}
+
+/// Converts the error from persisting a level of `T`s below the root level into an
+/// [`AssociationError`](crate::AssociationError) naming `T`.
+fn level_error<T, E>(err: MaligniusError<T::Err>) -> MaligniusError<E>
+where
+    T: Persist,
+    T::Err: std::error::Error + 'static,
+{
+    MaligniusError::Association(Box::new(err.into_association_error::<T>()))
+}
+
+/// Manifests `per_parent` children for each of the parents and persists them in one go.
+///
+/// The children are the roots of their own graphs, numbered from `first_index`, and are
+/// returned in the order of their parents.
+pub(crate) async fn persist_level<Parent, Child>(
+    ctx: &Child::Context,
+    parents: &[Parent],
+    per_parent: usize,
+    first_index: usize,
+    mut overrides: impl FnMut(&Parent) -> Child::Overrides,
+) -> Result<Vec<Child>, MaligniusError<Child::Err>>
+where
+    Child: Persist + 'static,
+{
+    persist_roots_at::<Child>(
+        ctx,
+        first_index,
+        parents.len() * per_parent,
+        |offset| Child::manifest(overrides(&parents[offset / per_parent])),
+        configured_options::<Child>(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{params, params_from_iter};
+
+    use crate::test_support::TestContext;
+    use crate::{
+        association, configure, record_rows_affected, sequences, Associations, Manifest,
+        PersistConfig, PersistStatementCounter,
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Author {
+        pub id: i64,
+        pub name: String,
+    }
+
+    impl Manifest for Author {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: 0,
+                    name: "Octavia E. Butler".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Author {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            let rows = ctx.conn.execute(
+                "insert into author (name) values ($1)",
+                params![author.name],
+            )?;
+            record_rows_affected::<Self>(rows);
+
+            Ok(Self {
+                id: ctx.conn.last_insert_rowid(),
+                ..author
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct Post {
+        pub id: i64,
+        pub author_id: i64,
+    }
+
+    impl Manifest for Post {
+        type Context = TestContext;
+        type Overrides = i64;
+
+        fn manifest(author_id: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { id: 0, author_id }, Associations::new())
+        }
+    }
+
+    impl Persist for Post {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            Ok(Self::persist_batch(ctx, vec![post]).await?.remove(0))
+        }
+
+        async fn persist_batch(
+            ctx: &Self::Context,
+            posts: Vec<Self>,
+        ) -> Result<Vec<Self>, Self::Err> {
+            let values = vec!["(?)"; posts.len()].join(", ");
+            let rows = ctx.conn.execute(
+                &format!("insert into post (author_id) values {values}"),
+                params_from_iter(posts.iter().map(|post| post.author_id)),
+            )?;
+            record_rows_affected::<Self>(rows);
+
+            // The rows are given consecutive ids, ending with the last one inserted.
+            let first_id = ctx.conn.last_insert_rowid() - posts.len() as i64 + 1;
+
+            Ok(posts
+                .into_iter()
+                .zip(first_id..)
+                .map(|(post, id)| Self { id, ..post })
+                .collect())
+        }
+    }
+
+    #[derive(Debug)]
+    struct Comment {
+        pub post_id: i64,
+    }
+
+    impl Manifest for Comment {
+        type Context = TestContext;
+        type Overrides = i64;
+
+        fn manifest(post_id: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { post_id }, Associations::new())
+        }
+    }
+
+    impl Persist for Comment {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+            Ok(Self::persist_batch(ctx, vec![comment]).await?.remove(0))
+        }
+
+        async fn persist_batch(
+            ctx: &Self::Context,
+            comments: Vec<Self>,
+        ) -> Result<Vec<Self>, Self::Err> {
+            let values = vec!["(?)"; comments.len()].join(", ");
+            let rows = ctx.conn.execute(
+                &format!("insert into comment (post_id) values {values}"),
+                params_from_iter(comments.iter().map(|comment| comment.post_id)),
+            )?;
+            record_rows_affected::<Self>(rows);
+
+            Ok(comments)
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_bulk_hierarchy_wires_each_level_to_its_parents(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                pragma foreign_keys = on;
+                create table author (id integer primary key, name text not null);
+                create table post (
+                    id integer primary key,
+                    author_id integer not null references author (id)
+                );
+                create table comment (
+                    id integer primary key,
+                    post_id integer not null references post (id)
+                );
+            "#,
+        )?;
+
+        configure::<Post>(PersistConfig {
+            batched: true,
+            ..Default::default()
+        });
+        configure::<Comment>(PersistConfig {
+            batched: true,
+            ..Default::default()
+        });
+
+        let statements = PersistStatementCounter::start();
+        let hierarchy = persist_bulk_hierarchy::<Author, Post, Comment>(
+            ctx.clone(),
+            10,
+            10,
+            10,
+            |author| author.id,
+            |post| post.id,
+        )
+        .await?;
+
+        // One insert per author, then the posts and comments in batches of at most 100.
+        assert_eq!(statements.total(), 10 + 1 + 10);
+
+        assert_eq!(hierarchy.parents.len(), 10);
+        assert_eq!(hierarchy.children.len(), 100);
+        assert_eq!(hierarchy.grandchildren.len(), 1000);
+
+        assert_eq!(ctx.count("select count(*) from author")?, 10);
+        assert_eq!(ctx.count("select count(*) from post")?, 100);
+        assert_eq!(ctx.count("select count(*) from comment")?, 1000);
+
+        assert_eq!(
+            ctx.count("select count(distinct author_id) from post")?,
+            10,
+            "every author should have posts"
+        );
+        assert_eq!(
+            ctx.count(
+                "select count(*) from (select post_id from comment group by post_id having count(*) = 10)"
+            )?,
+            100,
+            "every post should have ten comments"
+        );
+
+        for (index, post) in hierarchy.children.iter().enumerate() {
+            assert_eq!(post.author_id, hierarchy.parents[index / 10].id);
+        }
+
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct Label {
+        pub number: usize,
+    }
+
+    impl Manifest for Label {
+        type Context = ();
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    number: sequences::per_persist("label", |n| n),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Label {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, label: Self) -> Result<Self, Self::Err> {
+            Ok(label)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Ticket {
+        pub label_numbers: Vec<usize>,
+    }
+
+    impl Manifest for Ticket {
+        type Context = ();
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let label_numbers = (0..2)
+                .map(|_| association::<Label>(&mut associations).number)
+                .collect();
+
+            (Self { label_numbers }, associations)
+        }
+    }
+
+    impl Persist for Ticket {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, ticket: Self) -> Result<Self, Self::Err> {
+            Ok(ticket)
+        }
+    }
+
+    #[tokio::test]
+    async fn batched_levels_give_each_entity_its_own_graph(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        configure::<Ticket>(PersistConfig {
+            batched: true,
+            ..Default::default()
+        });
+
+        let tickets = persist_level::<(), Ticket>(&(), &[()], 3, 0, |_| ()).await?;
+
+        assert_eq!(tickets.len(), 3);
+        for ticket in tickets {
+            assert_eq!(ticket.label_numbers, vec![1, 2]);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::Mutex;
+
+use crate::SqlValue;
+
+/// A statement recorded by a [`CapturingContext`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedStatement {
+    pub sql: String,
+    pub params: Vec<SqlValue>,
+}
+
+/// A context that records the SQL it is asked to execute instead of running it.
+///
+/// This allows the SQL generated by a factory to be asserted on in isolation.
+#[derive(Debug, Default)]
+pub struct CapturingContext {
+    statements: Mutex<Vec<CapturedStatement>>,
+}
+
+impl CapturingContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the statement and its parameters.
+    pub fn execute(&self, sql: impl Into<String>, params: impl IntoIterator<Item = SqlValue>) {
+        self.statements.lock().unwrap().push(CapturedStatement {
+            sql: sql.into(),
+            params: params.into_iter().collect(),
+        });
+    }
+
+    /// Returns the statements that have been recorded, in the order they were executed.
+    pub fn statements(&self) -> Vec<CapturedStatement> {
+        self.statements.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    use crate::{persist, Associations, Manifest, Persist};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct MovieOverrides {
+        pub title: Option<String>,
+        pub year: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Movie {
+        pub title: String,
+        pub year: u32,
+    }
+
+    impl Manifest for Movie {
+        type Context = CapturingContext;
+        type Overrides = MovieOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    title: overrides.title.unwrap_or("Inception".into()),
+                    year: overrides.year.unwrap_or(2010),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Movie {
+        type Err = Infallible;
+
+        async fn persist(ctx: &Self::Context, movie: Self) -> Result<Self, Self::Err> {
+            ctx.execute(
+                "insert into movie (title, year) values ($1, $2)",
+                [movie.title.as_str().into(), movie.year.into()],
+            );
+
+            Ok(movie)
+        }
+    }
+
+    #[tokio::test]
+    async fn capturing_context_records_executed_statements(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = Arc::new(CapturingContext::new());
+
+        let _: Movie = persist(ctx.clone()).await?;
+
+        assert_eq!(
+            ctx.statements(),
+            vec![CapturedStatement {
+                sql: "insert into movie (title, year) values ($1, $2)".into(),
+                params: vec![SqlValue::Text("Inception".into()), SqlValue::Integer(2010)],
+            }]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use crate::Manifest;
+
+thread_local! {
+    static CLOCK: RefCell<Option<Rc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+/// A source of the current time for timestamp defaults.
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+}
+
+/// The real system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that stays at a fixed time until it is changed.
+///
+/// Clones share the same time, so a clone can be handed to [`manifest_with_clock`] while the
+/// original is used to move the time along.
+#[derive(Debug, Clone)]
+pub struct FixedClock {
+    now: Rc<Cell<SystemTime>>,
+}
+
+impl FixedClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Rc::new(Cell::new(now)),
+        }
+    }
+
+    /// Sets the current time.
+    pub fn set(&self, now: SystemTime) {
+        self.now.set(now);
+    }
+
+    /// Moves the current time forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.now.get()
+    }
+}
+
+/// Returns the current time according to the clock in use on this thread.
+///
+/// Factories should use this instead of [`SystemTime::now`] for timestamp defaults, so that tests
+/// can control the time with [`manifest_with_clock`] or [`with_clock`]. Without a clock
+/// override this is the [`SystemClock`].
+///
+/// ```
+/// # use std::time::SystemTime;
+/// # struct PostOverrides { created_at: Option<SystemTime> }
+/// # struct Post { created_at: SystemTime }
+/// # let overrides = PostOverrides { created_at: None };
+/// # let _ = Post {
+/// created_at: overrides.created_at.unwrap_or_else(malignius::now),
+/// # };
+/// ```
+pub fn now() -> SystemTime {
+    CLOCK
+        .with_borrow(|clock| clock.clone())
+        .map_or_else(SystemTime::now, |clock| clock.now())
+}
+
+/// Runs `f` with `clock` as the clock used by [`now`] on this thread.
+pub fn with_clock<R>(clock: impl Clock + 'static, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Rc<dyn Clock>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CLOCK.set(self.0.take());
+        }
+    }
+
+    let _restore = Restore(CLOCK.replace(Some(Rc::new(clock))));
+
+    f()
+}
+
+/// Manifests an entity, using `clock` for any timestamp defaults.
+pub fn manifest_with_clock<T: Manifest>(clock: impl Clock + 'static, overrides: T::Overrides) -> T {
+    with_clock(clock, || {
+        crate::graph_path::with_root::<T, _>(|| T::manifest(overrides)).0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Associations;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct EventOverrides {
+        pub created_at: Option<SystemTime>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Event {
+        pub created_at: SystemTime,
+    }
+
+    impl Manifest for Event {
+        type Context = ();
+        type Overrides = EventOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    created_at: overrides.created_at.unwrap_or_else(now),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[test]
+    fn timestamp_defaults_use_the_given_clock() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = FixedClock::new(start);
+
+        let event: Event = manifest_with_clock(clock.clone(), EventOverrides::default());
+        assert_eq!(event.created_at, start);
+
+        clock.advance(Duration::from_secs(60));
+
+        let event: Event = manifest_with_clock(clock.clone(), EventOverrides::default());
+        assert_eq!(event.created_at, start + Duration::from_secs(60));
+    }
+
+    #[test]
+    fn the_clock_is_restored_afterwards() {
+        let start = SystemTime::UNIX_EPOCH;
+
+        with_clock(FixedClock::new(start), || assert_eq!(now(), start));
+
+        assert!(now() > start);
+    }
+}
@@ -0,0 +1,452 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+static PERSIST_CONFIGS: Mutex<Option<HashMap<TypeId, PersistConfig>>> = Mutex::new(None);
+
+/// The number of entities persisted by each call to
+/// [`Persist::persist_batch`](crate::Persist::persist_batch) when no
+/// [`batch_size`](PersistConfig::batch_size) is configured.
+///
+/// This keeps a multi-row insert of entities with up to nine columns under SQLite's default
+/// limit of 999 parameters per statement.
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// How entities of a particular type are persisted.
+///
+/// Register one with [`configure`] to avoid passing options to every persist call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PersistConfig {
+    /// Persist the entity through [`Persist::persist_batch`](crate::Persist::persist_batch)
+    /// instead of [`Persist::persist`](crate::Persist::persist).
+    pub batched: bool,
+
+    /// The maximum number of entities passed to each call to
+    /// [`Persist::persist_batch`](crate::Persist::persist_batch) when `batched` is set.
+    ///
+    /// Larger sets of entities are split into several batches. When `None`,
+    /// [`DEFAULT_BATCH_SIZE`] is used.
+    pub batch_size: Option<usize>,
+
+    /// The maximum number of associations that may be persisted at the same time.
+    ///
+    /// This is used in place of the default
+    /// [`PersistOptions::max_concurrency`](crate::PersistOptions::max_concurrency) when
+    /// persisting without explicit options. When `None`, the default is kept.
+    pub max_concurrency: Option<usize>,
+
+    /// Persist the entity and its associations in a single transaction, begun with
+    /// [`Persist::begin_transaction`](crate::Persist::begin_transaction).
+    ///
+    /// The transaction is committed once the entity has been persisted, and rolled back if it or
+    /// any of its associations fails, so a failed persist leaves nothing behind. Associations that
+    /// are themselves configured as transactional begin a transaction of their own, so their
+    /// `Persist` implementations should use savepoints if the database does not support nested
+    /// transactions.
+    pub transactional: bool,
+}
+
+/// An error returned when [`Persist::persist_batch`](crate::Persist::persist_batch) returns a
+/// different number of entities than it was given.
+///
+/// It is returned as a [`MaligniusError::Association`](crate::MaligniusError::Association).
+#[derive(Debug)]
+pub struct BatchSizeMismatch {
+    pub(crate) entity_type: &'static str,
+    pub(crate) expected: usize,
+    pub(crate) returned: usize,
+}
+
+impl BatchSizeMismatch {
+    /// Returns the type name of the entity whose batch was persisted.
+    pub fn entity_type(&self) -> &'static str {
+        self.entity_type
+    }
+
+    /// Returns the number of entities the batch was given.
+    pub fn expected(&self) -> usize {
+        self.expected
+    }
+
+    /// Returns the number of entities the batch returned.
+    pub fn returned(&self) -> usize {
+        self.returned
+    }
+}
+
+impl fmt::Display for BatchSizeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`persist_batch` for `{}` returned {} of the {} entities it was given",
+            self.entity_type, self.returned, self.expected
+        )
+    }
+}
+
+impl std::error::Error for BatchSizeMismatch {}
+
+/// Registers the [`PersistConfig`] for entities of type `T`, replacing any previous one.
+///
+/// The configuration applies process-wide, including when `T` is persisted as an association.
+///
+/// ```
+/// # use malignius::{configure, PersistConfig};
+/// # struct Comment;
+/// configure::<Comment>(PersistConfig {
+///     batched: true,
+///     ..Default::default()
+/// });
+/// ```
+pub fn configure<T: 'static>(config: PersistConfig) {
+    PERSIST_CONFIGS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(TypeId::of::<T>(), config);
+}
+
+/// Returns the [`PersistConfig`] registered for `T`, or the default one.
+pub(crate) fn config_for<T: 'static>() -> PersistConfig {
+    PERSIST_CONFIGS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|configs| configs.get(&TypeId::of::<T>()).cloned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use rusqlite::{params_from_iter, Connection};
+
+    use crate::test_support::shared;
+    use crate::{
+        association, persist, persist_manifested, Associations, MaligniusError, Manifest, Persist,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingContext {
+        pub single_persists: AtomicUsize,
+        pub batch_persists: AtomicUsize,
+    }
+
+    #[derive(Debug, Default)]
+    struct PostOverrides {}
+
+    #[derive(Debug)]
+    struct Post {}
+
+    impl Manifest for Post {
+        type Context = RecordingContext;
+        type Overrides = PostOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self {}, Associations::new())
+        }
+    }
+
+    impl Persist for Post {
+        type Err = std::convert::Infallible;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.single_persists.fetch_add(1, Ordering::SeqCst);
+            Ok(post)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct CommentOverrides {}
+
+    #[derive(Debug)]
+    struct Comment {}
+
+    impl Manifest for Comment {
+        type Context = RecordingContext;
+        type Overrides = CommentOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let _post: Post = association(&mut associations);
+
+            (Self {}, associations)
+        }
+    }
+
+    impl Persist for Comment {
+        type Err = std::convert::Infallible;
+
+        async fn persist(ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+            ctx.single_persists.fetch_add(1, Ordering::SeqCst);
+            Ok(comment)
+        }
+
+        async fn persist_batch(
+            ctx: &Self::Context,
+            comments: Vec<Self>,
+        ) -> Result<Vec<Self>, Self::Err> {
+            ctx.batch_persists.fetch_add(1, Ordering::SeqCst);
+            Ok(comments)
+        }
+    }
+
+    struct TagContext {
+        pub conn: Connection,
+        pub statements: AtomicUsize,
+    }
+
+    #[derive(Debug, Default)]
+    struct TagOverrides {}
+
+    #[derive(Debug)]
+    struct Tag {
+        pub name: String,
+    }
+
+    impl Manifest for Tag {
+        type Context = TagContext;
+        type Overrides = TagOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    name: "rust".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Tag {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, tag: Self) -> Result<Self, Self::Err> {
+            Ok(Self::persist_batch(ctx, vec![tag]).await?.remove(0))
+        }
+
+        async fn persist_batch(
+            ctx: &Self::Context,
+            tags: Vec<Self>,
+        ) -> Result<Vec<Self>, Self::Err> {
+            let values = vec!["(?)"; tags.len()].join(", ");
+            ctx.conn.execute(
+                &format!("insert into tag (name) values {values}"),
+                params_from_iter(tags.iter().map(|tag| &tag.name)),
+            )?;
+            ctx.statements.fetch_add(1, Ordering::SeqCst);
+
+            Ok(tags)
+        }
+    }
+
+    #[test]
+    fn config_for_defaults_when_nothing_is_registered() {
+        struct Unconfigured;
+
+        assert_eq!(config_for::<Unconfigured>(), PersistConfig::default());
+    }
+
+    #[tokio::test]
+    async fn batched_config_persists_through_the_batch_path(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        configure::<Comment>(PersistConfig {
+            batched: true,
+            ..Default::default()
+        });
+
+        let ctx = Arc::new(RecordingContext::default());
+
+        let _: Comment = persist(ctx.clone()).await?;
+
+        // Only the comment is batched; its post is persisted as usual.
+        assert_eq!(ctx.batch_persists.load(Ordering::SeqCst), 1);
+        assert_eq!(ctx.single_persists.load(Ordering::SeqCst), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn batches_are_split_by_the_batch_size() -> Result<(), Box<dyn std::error::Error>> {
+        configure::<Tag>(PersistConfig {
+            batched: true,
+            batch_size: Some(100),
+            ..Default::default()
+        });
+
+        let conn = Connection::open(":memory:")?;
+        conn.execute("create table tag (name text not null)", ())?;
+
+        let ctx = TagContext {
+            conn,
+            statements: AtomicUsize::new(0),
+        };
+
+        let manifested = (0..250)
+            .map(|_| Tag::manifest(TagOverrides::default()))
+            .collect();
+        let tags = persist_manifested::<Tag>(&ctx, manifested, &Default::default()).await?;
+
+        assert_eq!(tags.len(), 250);
+        assert_eq!(ctx.statements.load(Ordering::SeqCst), 3);
+
+        let rows: usize = ctx
+            .conn
+            .query_row("select count(*) from tag", [], |row| row.get(0))?;
+        assert_eq!(rows, 250);
+
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct Lossy;
+
+    impl Manifest for Lossy {
+        type Context = RecordingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self, Associations::new())
+        }
+    }
+
+    impl Persist for Lossy {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, lossy: Self) -> Result<Self, Self::Err> {
+            Ok(lossy)
+        }
+
+        async fn persist_batch(
+            _ctx: &Self::Context,
+            _entities: Vec<Self>,
+        ) -> Result<Vec<Self>, Self::Err> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_that_lose_entities_are_an_error() {
+        configure::<Lossy>(PersistConfig {
+            batched: true,
+            ..Default::default()
+        });
+
+        let result = persist::<Lossy>(Arc::new(RecordingContext::default())).await;
+
+        let Err(MaligniusError::Association(err)) = result else {
+            panic!("expected a batch size mismatch, got {result:?}");
+        };
+        let err = err.downcast_ref::<BatchSizeMismatch>().unwrap();
+        assert_eq!(err.expected(), 1);
+        assert_eq!(err.returned(), 0);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "`persist_batch` for `{}` returned 0 of the 1 entities it was given",
+                std::any::type_name::<Lossy>()
+            )
+        );
+    }
+
+    struct BillingContext {
+        pub conn: Connection,
+    }
+
+    #[derive(Debug)]
+    struct Customer;
+
+    impl Manifest for Customer {
+        type Context = BillingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self, Associations::new())
+        }
+    }
+
+    impl Persist for Customer {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, customer: Self) -> Result<Self, Self::Err> {
+            ctx.conn
+                .execute("insert into customer default values", ())?;
+
+            Ok(customer)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Invoice;
+
+    impl Manifest for Invoice {
+        type Context = BillingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            association::<Customer>(&mut associations);
+
+            (Self, associations)
+        }
+    }
+
+    impl Persist for Invoice {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, invoice: Self) -> Result<Self, Self::Err> {
+            // There is no invoice table, so the customer has to be rolled back.
+            ctx.conn.execute("insert into invoice default values", ())?;
+
+            Ok(invoice)
+        }
+
+        async fn begin_transaction(ctx: &Self::Context) -> Result<(), Self::Err> {
+            ctx.conn.execute_batch("begin")
+        }
+
+        async fn commit_transaction(ctx: &Self::Context) -> Result<(), Self::Err> {
+            ctx.conn.execute_batch("commit")
+        }
+
+        async fn rollback_transaction(ctx: &Self::Context) -> Result<(), Self::Err> {
+            ctx.conn.execute_batch("rollback")
+        }
+    }
+
+    #[tokio::test]
+    async fn transactional_config_rolls_back_the_associations_of_a_failed_persist(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        configure::<Invoice>(PersistConfig {
+            transactional: true,
+            ..Default::default()
+        });
+
+        let conn = Connection::open(":memory:")?;
+        conn.execute("create table customer (id integer primary key)", ())?;
+
+        let ctx = shared(BillingContext { conn });
+
+        let result = persist::<Invoice>(ctx.clone()).await;
+        assert!(matches!(result, Err(MaligniusError::Persist(_))));
+
+        let customers: usize = ctx
+            .conn
+            .query_row("select count(*) from customer", [], |row| row.get(0))?;
+        assert_eq!(customers, 0);
+        assert!(ctx.conn.is_autocommit());
+
+        let _: Customer = persist(ctx.clone()).await?;
+        let customers: usize = ctx
+            .conn
+            .query_row("select count(*) from customer", [], |row| row.get(0))?;
+        assert_eq!(customers, 1);
+
+        Ok(())
+    }
+}
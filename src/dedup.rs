@@ -0,0 +1,309 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use crate::scope::Scoped;
+use crate::{
+    graph_path, persist_in, Associations, GraphPath, GraphShape, InitialOverrides, MaligniusError,
+    Persist,
+};
+
+type PersistedSlot = Rc<futures::lock::Mutex<Option<Box<dyn Any>>>>;
+
+/// The keyed associations persisted so far, by type and then by key.
+type PersistedKeys = Rc<RefCell<HashMap<TypeId, Box<dyn Any>>>>;
+
+thread_local! {
+    static PERSISTED_KEYS: RefCell<Option<PersistedKeys>> = const { RefCell::new(None) };
+}
+
+/// The identity of an entity, used to persist associations that refer to the same entity only
+/// once.
+///
+/// See [`keyed_association`].
+///
+/// ```
+/// # use malignius::AssociationKey;
+/// # struct Author {
+/// #     email: String,
+/// # }
+/// impl AssociationKey for Author {
+///     type Key = String;
+///
+///     fn association_key(&self) -> Self::Key {
+///         self.email.clone()
+///     }
+/// }
+/// ```
+pub trait AssociationKey {
+    type Key: Hash + Eq + 'static;
+
+    /// Returns the key identifying the entity.
+    fn association_key(&self) -> Self::Key;
+}
+
+/// Manifests an entity of type `T` and registers it to be persisted as an association, unless
+/// an entity with the same [`AssociationKey`] has already been persisted.
+///
+/// This is useful for diamond-shaped graphs, where several entities in the graph refer to the
+/// same entity, such as a `Comment` whose `Post` and `Profile` both belong to the same `Author`.
+/// With [`association`](crate::association) the `Author` would be inserted twice. With
+/// `keyed_association`, the first association to be persisted inserts it, and the others are
+/// given the persisted entity instead.
+///
+/// Keys are remembered for the rest of the outermost persist call, across every level of the
+/// graph. Outside of a persist call this behaves like [`association`](crate::association).
+pub fn keyed_association<T>(associations: &mut Associations<T::Context>) -> T
+where
+    T: Persist + AssociationKey + Clone + 'static,
+    T::Err: std::error::Error + 'static,
+{
+    let path = graph_path().map(|path| path.child::<T>(associations.associations.len()));
+    let manifest = || graph_path::with_root::<T, _>(|| T::manifest(T::Overrides::initial()));
+    let (entity, children) = match path.clone() {
+        Some(path) => graph_path::with_path(path, manifest),
+        None => manifest(),
+    };
+    let key = entity.association_key();
+
+    associations.persist::<T, _>(GraphShape::manifested::<T, _>(&children), move |ctx| {
+        Box::pin(async move {
+            let Some(slot) = persisted_slot::<T>(key) else {
+                return persist_keyed::<T>(ctx, path).await;
+            };
+
+            let mut slot = slot.lock().await;
+            if let Some(entity) = slot.as_ref() {
+                return Ok(entity.downcast_ref::<T>().unwrap().clone());
+            }
+
+            let entity = persist_keyed::<T>(ctx, path).await?;
+            *slot = Some(Box::new(entity.clone()));
+
+            Ok(entity)
+        })
+    });
+
+    entity
+}
+
+async fn persist_keyed<T>(
+    ctx: &T::Context,
+    path: Option<GraphPath>,
+) -> Result<T, Box<dyn std::error::Error>>
+where
+    T: Persist + 'static,
+    T::Err: std::error::Error + 'static,
+{
+    let entity = graph_path::in_path(path, persist_in::<T>(ctx))
+        .await
+        .map_err(MaligniusError::into_association_error::<T>)?;
+
+    Ok(entity)
+}
+
+/// Returns the slot for the entity of type `T` with the given key, or `None` outside of a
+/// persist call.
+fn persisted_slot<T: AssociationKey + 'static>(key: T::Key) -> Option<PersistedSlot> {
+    PERSISTED_KEYS.with_borrow(|persisted| {
+        let mut persisted = persisted.as_ref()?.borrow_mut();
+        let slots = persisted
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(HashMap::<T::Key, PersistedSlot>::new()))
+            .downcast_mut::<HashMap<T::Key, PersistedSlot>>()
+            .unwrap();
+
+        Some(slots.entry(key).or_default().clone())
+    })
+}
+
+/// Runs a persist call, starting a fresh set of [`keyed_association`] keys unless it is nested
+/// in another.
+pub(crate) async fn in_dedup_scope<F: Future>(future: F) -> F::Output {
+    if PERSISTED_KEYS.with_borrow(|persisted| persisted.is_some()) {
+        return future.await;
+    }
+
+    Scoped::new(&PERSISTED_KEYS, Rc::default(), future).await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rusqlite::params;
+
+    use crate::test_support::TestContext;
+    use crate::{association, persist, Manifest};
+
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct Author {
+        pub email: String,
+    }
+
+    impl Manifest for Author {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    email: "ursula@example.com".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Author {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into author (email) values ($1)",
+                params![author.email],
+            )?;
+
+            Ok(author)
+        }
+    }
+
+    impl AssociationKey for Author {
+        type Key = String;
+
+        fn association_key(&self) -> Self::Key {
+            self.email.clone()
+        }
+    }
+
+    #[derive(Debug)]
+    struct Post {
+        pub author_email: String,
+    }
+
+    impl Manifest for Post {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author: Author = keyed_association(&mut associations);
+
+            (
+                Self {
+                    author_email: author.email,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Post {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into post (author_email) values ($1)",
+                params![post.author_email],
+            )?;
+
+            Ok(post)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Profile {
+        pub author_email: String,
+    }
+
+    impl Manifest for Profile {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author: Author = keyed_association(&mut associations);
+
+            (
+                Self {
+                    author_email: author.email,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Profile {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, profile: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into profile (author_email) values ($1)",
+                params![profile.author_email],
+            )?;
+
+            Ok(profile)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Comment;
+
+    impl Manifest for Comment {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            association::<Post>(&mut associations);
+            association::<Profile>(&mut associations);
+
+            (Self, associations)
+        }
+    }
+
+    impl Persist for Comment {
+        type Err = rusqlite::Error;
+
+        async fn persist(_ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+            Ok(comment)
+        }
+    }
+
+    const SCHEMA: &str = r#"
+                create table author (email text primary key);
+                create table post (author_email text not null references author (email));
+                create table profile (author_email text not null references author (email));
+            "#;
+
+    #[tokio::test]
+    async fn shared_associations_are_persisted_once() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let _comment: Comment = persist(ctx.clone()).await?;
+
+        assert_eq!(ctx.count("select count(*) from author")?, 1);
+        assert_eq!(ctx.count("select count(*) from post")?, 1);
+        assert_eq!(ctx.count("select count(*) from profile")?, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keys_are_forgotten_between_persist_calls() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let _post: Post = persist(ctx.clone()).await?;
+        let result = persist::<Post>(ctx.clone()).await;
+
+        // The second call inserts the author again, which violates its primary key.
+        assert!(matches!(result, Err(MaligniusError::Association(_))));
+        assert_eq!(ctx.count("select count(*) from author")?, 1);
+
+        Ok(())
+    }
+}
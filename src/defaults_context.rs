@@ -0,0 +1,189 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::scope::Scoped;
+use crate::{persist_with, MaligniusError, Manifest, Persist};
+
+thread_local! {
+    static DEFAULTS: RefCell<Option<Rc<DefaultsContext>>> = const { RefCell::new(None) };
+}
+
+/// String key-values that factories can consult for their defaults, like environment variables.
+///
+/// This allows the same tests to produce different data depending on where they are run.
+///
+/// ```
+/// # struct ServerOverrides { region: Option<String> }
+/// # struct Server { region: String }
+/// # let overrides = ServerOverrides { region: None };
+/// # let server = Server {
+/// region: overrides
+///     .region
+///     .or_else(|| malignius::default_value("DEFAULT_REGION"))
+///     .unwrap_or("us-east-1".into()),
+/// # };
+/// # assert_eq!(server.region, "us-east-1");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DefaultsContext {
+    values: HashMap<String, String>,
+}
+
+impl DefaultsContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a context from the environment variables of the current process.
+    pub fn from_env() -> Self {
+        Self {
+            values: std::env::vars().collect(),
+        }
+    }
+
+    /// Sets the value for `key`, replacing any existing value.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns the value for `key`, if there is one.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+}
+
+/// Returns the value for `key` from the defaults context in use on this thread.
+///
+/// Without a defaults context this is always `None`.
+pub fn default_value(key: &str) -> Option<String> {
+    DEFAULTS.with_borrow(|defaults| {
+        defaults
+            .as_ref()
+            .and_then(|defaults| defaults.get(key))
+            .map(str::to_owned)
+    })
+}
+
+/// Runs `f` with `defaults` as the defaults context used by [`default_value`] on this thread.
+pub fn with_defaults<R>(defaults: DefaultsContext, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Rc<DefaultsContext>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            DEFAULTS.set(self.0.take());
+        }
+    }
+
+    let _restore = Restore(DEFAULTS.replace(Some(Rc::new(defaults))));
+
+    f()
+}
+
+/// Manifests an entity, using `defaults` for any defaults that consult [`default_value`].
+pub fn manifest_with_defaults<T: Manifest>(
+    defaults: DefaultsContext,
+    overrides: T::Overrides,
+) -> T {
+    with_defaults(defaults, || {
+        crate::graph_path::with_root::<T, _>(|| T::manifest(overrides)).0
+    })
+}
+
+/// Persists an entity, using `defaults` for any defaults that consult [`default_value`].
+///
+/// The defaults context applies to the entity and all of its associations.
+pub async fn persist_with_defaults<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    defaults: DefaultsContext,
+    overrides: T::Overrides,
+) -> Result<T, MaligniusError<T::Err>> {
+    Scoped::new(
+        &DEFAULTS,
+        Rc::new(defaults),
+        persist_with::<T>(ctx, overrides),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Associations;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct ServerOverrides {
+        pub region: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Server {
+        pub region: String,
+    }
+
+    impl Manifest for Server {
+        type Context = ();
+        type Overrides = ServerOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    region: overrides
+                        .region
+                        .or_else(|| default_value("DEFAULT_REGION"))
+                        .unwrap_or("us-east-1".into()),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Server {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, server: Self) -> Result<Self, Self::Err> {
+            Ok(server)
+        }
+    }
+
+    fn eu_defaults() -> DefaultsContext {
+        let mut defaults = DefaultsContext::new();
+        defaults.set("DEFAULT_REGION", "eu-west-1");
+        defaults
+    }
+
+    #[test]
+    fn defaults_come_from_the_defaults_context() {
+        let server: Server = manifest_with_defaults(eu_defaults(), ServerOverrides::default());
+        assert_eq!(server.region, "eu-west-1");
+
+        let server: Server = crate::manifest();
+        assert_eq!(server.region, "us-east-1");
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_the_defaults_context() {
+        let server: Server = manifest_with_defaults(
+            eu_defaults(),
+            ServerOverrides {
+                region: Some("ap-south-1".into()),
+            },
+        );
+
+        assert_eq!(server.region, "ap-south-1");
+    }
+
+    #[tokio::test]
+    async fn persist_with_defaults_uses_the_defaults_context(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let server: Server =
+            persist_with_defaults(Arc::new(()), eu_defaults(), ServerOverrides::default()).await?;
+
+        assert_eq!(server.region, "eu-west-1");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+use crate::AssociationError;
+
+/// An error that occurred while persisting an entity or one of its associations.
+#[derive(Debug)]
+pub enum MaligniusError<E> {
+    /// An association of the entity failed to persist, so the entity itself was not persisted.
+    ///
+    /// This is usually an [`AssociationError`] naming the association that failed. It is also
+    /// used for errors malignius detects itself, such as a
+    /// [`BatchSizeMismatch`](crate::BatchSizeMismatch).
+    Association(Box<dyn std::error::Error>),
+    /// The entity itself failed to persist.
+    Persist(E),
+}
+
+impl<E> MaligniusError<E> {
+    /// Returns the error from persisting the entity itself, if that is what failed.
+    pub fn persist_error(&self) -> Option<&E> {
+        match self {
+            Self::Association(_) => None,
+            Self::Persist(err) => Some(err),
+        }
+    }
+
+    /// Returns the error from persisting the entity itself, if that is what failed.
+    pub fn into_persist_error(self) -> Option<E> {
+        match self {
+            Self::Association(_) => None,
+            Self::Persist(err) => Some(err),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> MaligniusError<E> {
+    /// Converts the error from persisting an association of type `T` into an
+    /// [`AssociationError`] for the entity that registered it.
+    ///
+    /// When one of `T`'s own associations failed, its error is kept as the source, so the chain
+    /// of errors follows the path down to the entity that actually failed.
+    pub(crate) fn into_association_error<T>(self) -> AssociationError {
+        match self {
+            Self::Association(err) => AssociationError::new::<T>(err),
+            Self::Persist(err) => AssociationError::new::<T>(Box::new(err)),
+        }
+    }
+}
+
+impl<E> From<E> for MaligniusError<E> {
+    fn from(err: E) -> Self {
+        Self::Persist(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for MaligniusError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Association(err) => err.fmt(f),
+            Self::Persist(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for MaligniusError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Association(err) => err.source(),
+            Self::Persist(err) => err.source(),
+        }
+    }
+}
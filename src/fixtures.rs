@@ -0,0 +1,508 @@
+use std::any::{type_name, Any};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{persist_with_associations, Persist};
+
+/// A set of persisted entities that can be exported with [`export_fixtures`] and persisted again
+/// later with [`load_fixtures`], without running their factories.
+///
+/// The graph of an entity and every association persisted for it is captured by
+/// [`persist_fixture_graph`]. Entities are identified by their full type name, as returned by
+/// [`std::any::type_name`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FixtureGraph {
+    entities: Vec<FixtureEntity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FixtureEntity {
+    #[serde(rename = "type")]
+    entity_type: String,
+    data: serde_json::Value,
+}
+
+impl FixtureGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entity to the graph.
+    ///
+    /// Entities are loaded in the order they were added, so an entity should be added after the
+    /// entities it refers to.
+    pub fn add<T: Serialize>(&mut self, entity: &T) -> Result<&mut Self, FixtureError> {
+        self.entities.push(FixtureEntity {
+            entity_type: type_name::<T>().to_owned(),
+            data: serde_json::to_value(entity).map_err(FixtureError::Json)?,
+        });
+
+        Ok(self)
+    }
+}
+
+/// Exports the entities in the graph as JSON.
+pub fn export_fixtures(graph: &FixtureGraph) -> String {
+    serde_json::to_string_pretty(graph).expect("fixture graphs are always serializable")
+}
+
+/// Persists an entity along with its associations, returning it along with a [`FixtureGraph`] of
+/// every entity that was persisted.
+///
+/// The graph holds the associations in the order they were persisted, followed by the entity
+/// itself, so loading it persists every entity after the ones it refers to. `Types` lists the
+/// types of every entity in the graph, the same as for [`load_fixtures`].
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Serialize, Deserialize)]
+/// # struct Author {
+/// #     name: String,
+/// # }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         (Self { name: "Ted Chiang".into() }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Serialize, Deserialize)]
+/// # struct Post {
+/// #     author_name: String,
+/// # }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let author: Author = association(&mut associations);
+/// #         (Self { author_name: author.name }, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let (_post, graph) =
+///     persist_fixture_graph::<Post, (Author, Post)>(ctx, Default::default()).await?;
+/// let fixtures = export_fixtures(&graph);
+/// # assert!(fixtures.contains("Ted Chiang"));
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_fixture_graph<T, Types>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+) -> Result<(T, FixtureGraph), FixtureError>
+where
+    T: Persist + 'static,
+    T::Err: Error + 'static,
+    Types: FixtureTypes,
+{
+    let (entity, associations) = persist_with_associations::<T>(ctx, overrides)
+        .await
+        .map_err(|err| FixtureError::Persist(Box::new(err)))?;
+
+    let mut graph = FixtureGraph::new();
+    for (entity_type, persisted) in associations
+        .iter()
+        .chain([(type_name::<T>(), &entity as &dyn Any)])
+    {
+        let data = Types::export_entity(persisted)
+            .ok_or_else(|| FixtureError::UnknownType(entity_type.to_owned()))??;
+
+        graph.entities.push(FixtureEntity {
+            entity_type: entity_type.to_owned(),
+            data,
+        });
+    }
+
+    Ok((entity, graph))
+}
+
+/// The entity types that [`load_fixtures`] can load and [`persist_fixture_graph`] can export.
+///
+/// This is implemented for tuples of up to eight entities that share the same context.
+pub trait FixtureTypes {
+    type Context;
+
+    /// Serializes an entity, returning `None` if it is not one of the types.
+    fn export_entity(entity: &dyn Any) -> Option<Result<serde_json::Value, FixtureError>>;
+
+    /// Persists an entity of the given type, returning `None` if it is not one of the types.
+    #[allow(async_fn_in_trait)]
+    async fn load_entity(
+        ctx: &Self::Context,
+        entity_type: &str,
+        data: serde_json::Value,
+    ) -> Option<Result<(), FixtureError>>;
+}
+
+macro_rules! impl_fixture_types {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<$first, $($rest),*> FixtureTypes for ($first, $($rest,)*)
+        where
+            $first: Persist + Serialize + DeserializeOwned + 'static,
+            <$first as Persist>::Err: Error + 'static,
+            $(
+                $rest: Persist<Context = <$first as crate::Manifest>::Context>
+                    + Serialize
+                    + DeserializeOwned
+                    + 'static,
+                <$rest as Persist>::Err: Error + 'static,
+            )*
+        {
+            type Context = <$first as crate::Manifest>::Context;
+
+            fn export_entity(entity: &dyn Any) -> Option<Result<serde_json::Value, FixtureError>> {
+                if let Some(entity) = entity.downcast_ref::<$first>() {
+                    return Some(serde_json::to_value(entity).map_err(FixtureError::Json));
+                }
+
+                $(
+                    if let Some(entity) = entity.downcast_ref::<$rest>() {
+                        return Some(serde_json::to_value(entity).map_err(FixtureError::Json));
+                    }
+                )*
+
+                None
+            }
+
+            async fn load_entity(
+                ctx: &Self::Context,
+                entity_type: &str,
+                data: serde_json::Value,
+            ) -> Option<Result<(), FixtureError>> {
+                if entity_type == type_name::<$first>() {
+                    return Some(load_entity::<$first>(ctx, data).await);
+                }
+
+                $(
+                    if entity_type == type_name::<$rest>() {
+                        return Some(load_entity::<$rest>(ctx, data).await);
+                    }
+                )*
+
+                None
+            }
+        }
+    };
+}
+
+impl_fixture_types!(A);
+impl_fixture_types!(A, B);
+impl_fixture_types!(A, B, C);
+impl_fixture_types!(A, B, C, D);
+impl_fixture_types!(A, B, C, D, E);
+impl_fixture_types!(A, B, C, D, E, F);
+impl_fixture_types!(A, B, C, D, E, F, G);
+impl_fixture_types!(A, B, C, D, E, F, G, H);
+
+async fn load_entity<T: Persist + DeserializeOwned>(
+    ctx: &T::Context,
+    data: serde_json::Value,
+) -> Result<(), FixtureError>
+where
+    T::Err: Error + 'static,
+{
+    let entity = serde_json::from_value::<T>(data).map_err(FixtureError::Json)?;
+
+    T::persist(ctx, entity)
+        .await
+        .map_err(|err| FixtureError::Persist(Box::new(err)))?;
+
+    Ok(())
+}
+
+/// Persists the entities exported by [`export_fixtures`], in the order they were added.
+///
+/// The entities are persisted as-is with [`Persist::persist`], so their factories are not run and
+/// their associations are not persisted again.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Serialize, Deserialize)]
+/// # struct Author {
+/// #     name: String,
+/// # }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         (Self { name: "Ted Chiang".into() }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Serialize, Deserialize)]
+/// # struct Post {
+/// #     author_name: String,
+/// # }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let author: Author = association(&mut associations);
+/// #         (Self { author_name: author.name }, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// # let (_, graph) = persist_fixture_graph::<Post, (Author, Post)>(ctx.clone(), ()).await?;
+/// # let fixtures = export_fixtures(&graph);
+/// load_fixtures::<(Author, Post)>(ctx, &fixtures).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn load_fixtures<T: FixtureTypes>(
+    ctx: Arc<T::Context>,
+    data: &str,
+) -> Result<(), FixtureError> {
+    let graph = serde_json::from_str::<FixtureGraph>(data).map_err(FixtureError::Json)?;
+
+    for entity in graph.entities {
+        T::load_entity(&ctx, &entity.entity_type, entity.data)
+            .await
+            .ok_or(FixtureError::UnknownType(entity.entity_type))??;
+    }
+
+    Ok(())
+}
+
+/// An error that occurred while exporting or loading fixtures.
+#[derive(Debug)]
+pub enum FixtureError {
+    /// An entity could not be serialized or deserialized.
+    Json(serde_json::Error),
+    /// The fixtures contain an entity whose type was not one of the types being exported or
+    /// loaded.
+    UnknownType(String),
+    /// An entity could not be persisted.
+    Persist(Box<dyn Error>),
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "invalid fixtures: {err}"),
+            Self::UnknownType(entity_type) => {
+                write!(f, "fixtures contain an unknown type `{entity_type}`")
+            }
+            Self::Persist(err) => write!(f, "failed to persist fixture: {err}"),
+        }
+    }
+}
+
+impl Error for FixtureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Json(err) => Some(err),
+            Self::UnknownType(_) => None,
+            Self::Persist(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{params, Connection};
+
+    use crate::test_support::TestContext;
+    use crate::{association, Associations, Manifest};
+
+    use super::*;
+
+    const SCHEMA: &str = r#"
+                    pragma foreign_keys = on;
+
+                    create table author (
+                        id integer primary key,
+                        name text not null
+                    );
+
+                    create table post (
+                        id integer primary key,
+                        author_id integer not null references author (id),
+                        title text not null
+                    );
+                "#;
+
+    #[derive(Debug, Default)]
+    struct AuthorOverrides {}
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+    struct Author {
+        pub id: i64,
+        pub name: String,
+    }
+
+    impl Manifest for Author {
+        type Context = TestContext;
+        type Overrides = AuthorOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: 7,
+                    name: "Octavia E. Butler".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Author {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into author (id, name) values ($1, $2)",
+                params![author.id, author.name],
+            )?;
+
+            Ok(author)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct PostOverrides {
+        pub author_id: Option<i64>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+    struct Post {
+        pub id: i64,
+        pub author_id: i64,
+        pub title: String,
+    }
+
+    impl Manifest for Post {
+        type Context = TestContext;
+        type Overrides = PostOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author_id = overrides
+                .author_id
+                .unwrap_or_else(|| association::<Author>(&mut associations).id);
+
+            (
+                Self {
+                    id: 42,
+                    author_id,
+                    title: "Bloodchild".into(),
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Post {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into post (id, author_id, title) values ($1, $2, $3)",
+                params![post.id, post.author_id, post.title],
+            )?;
+
+            Ok(post)
+        }
+    }
+
+    fn rows(conn: &Connection) -> rusqlite::Result<Vec<(i64, String, String)>> {
+        conn.prepare(
+            "select post.id, author.name, post.title from post join author on author.id = post.author_id",
+        )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn exported_fixtures_can_be_loaded_into_a_fresh_database(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let (_post, graph) =
+            persist_fixture_graph::<Post, (Author, Post)>(ctx.clone(), Default::default()).await?;
+
+        let fixtures = export_fixtures(&graph);
+
+        let fresh = TestContext::shared(SCHEMA)?;
+        load_fixtures::<(Author, Post)>(fresh.clone(), &fixtures).await?;
+
+        assert_eq!(rows(&fresh.conn)?, rows(&ctx.conn)?);
+        assert_eq!(
+            rows(&fresh.conn)?,
+            vec![(42, "Octavia E. Butler".into(), "Bloodchild".into())]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn exporting_fails_for_unknown_types() -> Result<(), Box<dyn std::error::Error>> {
+        let result = persist_fixture_graph::<Post, (Post,)>(
+            TestContext::shared(SCHEMA)?,
+            Default::default(),
+        )
+        .await;
+
+        assert!(
+            matches!(result, Err(FixtureError::UnknownType(entity_type)) if entity_type == type_name::<Author>())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn loading_fails_for_unknown_types() -> Result<(), Box<dyn std::error::Error>> {
+        let mut graph = FixtureGraph::new();
+        graph.add(&Post {
+            id: 1,
+            author_id: 1,
+            title: "Kindred".into(),
+        })?;
+
+        let result =
+            load_fixtures::<(Author,)>(TestContext::shared(SCHEMA)?, &export_fixtures(&graph))
+                .await;
+
+        assert!(
+            matches!(result, Err(FixtureError::UnknownType(entity_type)) if entity_type == type_name::<Post>())
+        );
+
+        Ok(())
+    }
+}
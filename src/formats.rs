@@ -0,0 +1,129 @@
+//! Producers of format-valid values for use with [`Sequence`](crate::Sequence).
+//!
+//! Each producer maps a sequence number to a distinct value that passes the usual validation for
+//! its format, which is useful for exercising input validation with realistic data.
+//!
+//! ```
+//! # use malignius::{formats, Sequence};
+//! let mut card_numbers = Sequence::new(formats::card_number);
+//! # assert_ne!(card_numbers.next(), card_numbers.next());
+//! ```
+
+/// Returns an email address such as `user1@example.com`.
+///
+/// The addresses use the `example.com` domain, which is reserved for documentation and testing.
+pub fn email(n: usize) -> String {
+    format!("user{n}@example.com")
+}
+
+/// Returns a phone number in E.164 format, such as `+15550000001`.
+///
+/// The numbers use the `555` area code, which is reserved for fictional use. They are distinct for
+/// the first ten million sequence numbers.
+pub fn phone_number(n: usize) -> String {
+    format!("+1555{:07}", n % 10_000_000)
+}
+
+/// Returns a 16-digit card number with a valid Luhn check digit, such as `4000000000000010`.
+///
+/// The numbers use the Visa prefix `4`, so they pass the format checks of most payment forms
+/// without belonging to real cards.
+pub fn card_number(n: usize) -> String {
+    let payload = format!("4{:014}", n % 100_000_000_000_000);
+
+    format!("{payload}{}", luhn_check_digit(&payload))
+}
+
+/// Returns the digit that makes `payload` followed by it pass the Luhn check.
+fn luhn_check_digit(payload: &str) -> u32 {
+    let sum = payload
+        .chars()
+        .rev()
+        .map(|char| char.to_digit(10).unwrap())
+        .enumerate()
+        .map(|(index, digit)| match index % 2 {
+            0 if digit * 2 > 9 => digit * 2 - 9,
+            0 => digit * 2,
+            _ => digit,
+        })
+        .sum::<u32>();
+
+    (10 - sum % 10) % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Sequence;
+
+    use super::*;
+
+    fn is_luhn_valid(number: &str) -> bool {
+        let sum = number
+            .chars()
+            .rev()
+            .map(|char| char.to_digit(10).unwrap())
+            .enumerate()
+            .map(|(index, digit)| match index % 2 {
+                1 if digit * 2 > 9 => digit * 2 - 9,
+                1 => digit * 2,
+                _ => digit,
+            })
+            .sum::<u32>();
+
+        sum % 10 == 0
+    }
+
+    #[test]
+    fn card_numbers_pass_the_luhn_check() {
+        let mut card_numbers = Sequence::new(card_number);
+
+        for card_number in card_numbers.take_vec(100) {
+            assert_eq!(card_number.len(), 16);
+            assert!(
+                is_luhn_valid(&card_number),
+                "{card_number} is not Luhn-valid"
+            );
+        }
+
+        // A well-known Visa test number.
+        assert!(is_luhn_valid("4111111111111111"));
+        assert!(!is_luhn_valid("4111111111111112"));
+    }
+
+    #[test]
+    fn phone_numbers_are_in_e164_format() {
+        let mut phone_numbers = Sequence::new(phone_number);
+
+        assert_eq!(phone_numbers.next(), "+15550000001");
+
+        for phone_number in phone_numbers.take_vec(100) {
+            let digits = phone_number.strip_prefix('+').unwrap();
+
+            assert!(digits.len() <= 15);
+            assert!(digits.chars().all(|char| char.is_ascii_digit()));
+            assert!(!digits.starts_with('0'));
+        }
+    }
+
+    #[test]
+    fn emails_have_a_local_part_and_a_domain() {
+        let mut emails = Sequence::new(email);
+
+        for email in emails.take_vec(100) {
+            let (local_part, domain) = email.split_once('@').unwrap();
+
+            assert!(!local_part.is_empty() && local_part.len() <= 64);
+            assert!(local_part.chars().all(|char| char.is_ascii_alphanumeric()));
+            assert_eq!(domain, "example.com");
+        }
+    }
+
+    #[test]
+    fn values_are_distinct() {
+        let mut card_numbers = Sequence::new(card_number).take_vec(1_000);
+        card_numbers.sort();
+        card_numbers.dedup();
+
+        assert_eq!(card_numbers.len(), 1_000);
+    }
+}
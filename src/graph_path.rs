@@ -0,0 +1,274 @@
+use std::any::type_name;
+use std::cell::RefCell;
+use std::future::Future;
+
+use crate::scope::Scoped;
+
+thread_local! {
+    static GRAPH_PATH: RefCell<Option<GraphPath>> = const { RefCell::new(None) };
+}
+
+/// The position of an entity in the graph being manifested or persisted.
+///
+/// The path starts at the entity being persisted and has a segment for each level of
+/// associations, made up of the association's type name and the order it was registered in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GraphPath {
+    segments: Vec<(&'static str, usize)>,
+}
+
+impl GraphPath {
+    fn root<T: ?Sized>(index: usize) -> Self {
+        Self {
+            segments: vec![(type_name::<T>(), index)],
+        }
+    }
+
+    pub(crate) fn child<T: ?Sized>(&self, index: usize) -> Self {
+        let mut segments = self.segments.clone();
+        segments.push((type_name::<T>(), index));
+
+        Self { segments }
+    }
+
+    /// Returns the type name and registration index of each entity along the path.
+    pub fn segments(&self) -> &[(&'static str, usize)] {
+        &self.segments
+    }
+
+    /// Returns an id derived from the path.
+    ///
+    /// The id is the 64-bit FNV-1a hash of the path, so it is the same on every run and with
+    /// every build.
+    pub fn id(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        let mut hash = OFFSET_BASIS;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(PRIME);
+            }
+        };
+
+        for (entity_type, index) in &self.segments {
+            write(entity_type.as_bytes());
+            write(&(*index as u64).to_le_bytes());
+        }
+
+        hash
+    }
+}
+
+/// Returns the path of the entity currently being manifested.
+///
+/// This is `None` outside of [`manifest`](crate::manifest) and [`persist`](crate::persist).
+pub fn graph_path() -> Option<GraphPath> {
+    GRAPH_PATH.with_borrow(|path| path.clone())
+}
+
+/// Returns an id derived from the position of the entity currently being manifested in its
+/// graph.
+///
+/// Using this as the default id in factories makes ids deterministic: persisting the same
+/// factory always yields the same ids, which keeps snapshots of whole graphs stable. Entities in
+/// different positions get different ids.
+///
+/// ```
+/// # use malignius::{Associations, Manifest};
+/// # #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// # struct AuthorId(u64);
+/// # struct Author { id: AuthorId }
+/// # #[derive(Default)]
+/// # struct AuthorOverrides { id: Option<AuthorId> }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = AuthorOverrides;
+/// #     fn manifest(overrides: AuthorOverrides) -> (Self, Associations<()>) {
+/// #         let author = Author {
+/// id: overrides.id.unwrap_or_else(|| AuthorId(malignius::graph_id())),
+/// #         };
+/// #         (author, Associations::new())
+/// #     }
+/// # }
+/// # assert_eq!(malignius::manifest::<Author>().id, malignius::manifest::<Author>().id);
+/// ```
+///
+/// # Panics
+///
+/// Panics when called outside of a graph, such as when a factory's [`Manifest::manifest`] is
+/// called directly instead of through [`manifest`](crate::manifest) or
+/// [`persist`](crate::persist).
+///
+/// [`Manifest::manifest`]: crate::Manifest::manifest
+pub fn graph_id() -> u64 {
+    graph_path()
+        .expect("`graph_id` can only be called while manifesting an entity")
+        .id()
+}
+
+/// Runs `f` with `T` at the root of the graph, unless a graph is already in progress.
+pub(crate) fn with_root<T: ?Sized, R>(f: impl FnOnce() -> R) -> R {
+    with_root_at::<T, R>(0, f)
+}
+
+/// Runs `f` with `T` at the root of the graph as the `index`th of several roots, unless a graph
+/// is already in progress.
+pub(crate) fn with_root_at<T: ?Sized, R>(index: usize, f: impl FnOnce() -> R) -> R {
+    if GRAPH_PATH.with_borrow(|path| path.is_some()) {
+        return f();
+    }
+
+    with_path(GraphPath::root::<T>(index), f)
+}
+
+/// Runs `f` with the given path as the current path.
+pub(crate) fn with_path<R>(path: GraphPath, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<GraphPath>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            GRAPH_PATH.set(self.0.take());
+        }
+    }
+
+    let _restore = Restore(GRAPH_PATH.replace(Some(path)));
+
+    f()
+}
+
+/// Runs the future with `T` at the root of the graph as the `index`th of several roots, unless
+/// a graph is already in progress.
+pub(crate) async fn in_root_at<T: ?Sized, F: Future>(index: usize, future: F) -> F::Output {
+    if GRAPH_PATH.with_borrow(|path| path.is_some()) {
+        return future.await;
+    }
+
+    in_path(Some(GraphPath::root::<T>(index)), future).await
+}
+
+/// Runs the future with the given path as the current path.
+///
+/// When `path` is `None` the current path is left as-is.
+pub(crate) async fn in_path<F: Future>(path: Option<GraphPath>, future: F) -> F::Output {
+    match path {
+        Some(path) => Scoped::new(&GRAPH_PATH, path, future).await,
+        None => future.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::test_support::shared;
+    use crate::{association, persist, Associations, Manifest, Persist};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingContext {
+        pub persisted: RefCell<Vec<(&'static str, u64)>>,
+    }
+
+    #[derive(Debug, Default)]
+    struct AuthorOverrides {}
+
+    #[derive(Debug)]
+    struct Author {
+        pub id: u64,
+    }
+
+    impl Manifest for Author {
+        type Context = RecordingContext;
+        type Overrides = AuthorOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { id: graph_id() }, Associations::new())
+        }
+    }
+
+    impl Persist for Author {
+        type Err = std::convert::Infallible;
+
+        async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            ctx.persisted.borrow_mut().push(("author", author.id));
+            Ok(author)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct PostOverrides {}
+
+    #[derive(Debug)]
+    struct Post {
+        pub id: u64,
+        pub author_id: u64,
+        pub editor_id: u64,
+    }
+
+    impl Manifest for Post {
+        type Context = RecordingContext;
+        type Overrides = PostOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author: Author = association(&mut associations);
+            let editor: Author = association(&mut associations);
+
+            (
+                Self {
+                    id: graph_id(),
+                    author_id: author.id,
+                    editor_id: editor.id,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Post {
+        type Err = std::convert::Infallible;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.persisted.borrow_mut().push(("post", post.id));
+            Ok(post)
+        }
+    }
+
+    #[tokio::test]
+    async fn graph_ids_are_the_same_for_the_same_graph() -> Result<(), Box<dyn std::error::Error>> {
+        let first = shared(RecordingContext::default());
+        let second = shared(RecordingContext::default());
+
+        let first_post: Post = persist(first.clone()).await?;
+        let second_post: Post = persist(second.clone()).await?;
+
+        assert_eq!(*first.persisted.borrow(), *second.persisted.borrow());
+        assert_eq!(first_post.id, second_post.id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn graph_ids_match_between_manifesting_and_persisting(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = shared(RecordingContext::default());
+
+        let post: Post = persist(ctx.clone()).await?;
+
+        assert_eq!(
+            *ctx.persisted.borrow(),
+            vec![
+                ("author", post.author_id),
+                ("author", post.editor_id),
+                ("post", post.id),
+            ]
+        );
+        assert_ne!(post.author_id, post.editor_id);
+        assert_eq!(crate::manifest::<Post>().id, post.id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::{Associations, InitialOverrides, Manifest};
+
+/// The entity types a factory produces and how many of each, including the entity itself.
+///
+/// Types are identified by their name without the module path, e.g. `Comment`.
+///
+/// ```
+/// # use malignius::GraphShape;
+/// let shape = GraphShape::from([("Comment", 1), ("Post", 1), ("Author", 1)]);
+/// # assert_eq!(shape, GraphShape::new().with("Author", 1).with("Post", 1).with("Comment", 1));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphShape {
+    counts: BTreeMap<String, usize>,
+}
+
+impl GraphShape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shape of the graph manifested for `T` with the default overrides.
+    ///
+    /// The entity and its associations are manifested, but nothing is persisted. Note that
+    /// manifesting advances any sequences the factories use.
+    ///
+    /// The associations are counted as they were registered, with the overrides each factory
+    /// manifested them with, so nested associations are not manifested again.
+    pub fn of<T: Manifest + 'static>() -> Self {
+        let (_, associations) =
+            crate::graph_path::with_root::<T, _>(|| T::manifest(T::Overrides::initial()));
+
+        Self::manifested::<T, _>(&associations)
+    }
+
+    /// Returns the shape of an entity of type `T` that registered `associations`.
+    pub(crate) fn manifested<T: ?Sized, Context>(associations: &Associations<Context>) -> Self {
+        let mut shape = Self::new().with(short_type_name(type_name::<T>()), 1);
+        for association in &associations.associations {
+            shape.merge(association.shape.clone());
+        }
+
+        shape
+    }
+
+    /// Adds `count` entities of the given type to the shape.
+    pub fn with(mut self, entity_type: impl Into<String>, count: usize) -> Self {
+        *self.counts.entry(entity_type.into()).or_default() += count;
+        self
+    }
+
+    /// Returns the number of entities of the given type in the shape.
+    pub fn count(&self, entity_type: &str) -> usize {
+        self.counts.get(entity_type).copied().unwrap_or_default()
+    }
+
+    fn merge(&mut self, other: GraphShape) {
+        for (entity_type, count) in other.counts {
+            *self.counts.entry(entity_type).or_default() += count;
+        }
+    }
+}
+
+impl<S: Into<String>, const N: usize> From<[(S, usize); N]> for GraphShape {
+    fn from(counts: [(S, usize); N]) -> Self {
+        counts
+            .into_iter()
+            .fold(Self::new(), |shape, (entity_type, count)| {
+                shape.with(entity_type, count)
+            })
+    }
+}
+
+impl fmt::Display for GraphShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (entity_type, count) in &self.counts {
+            writeln!(f, "{entity_type}: {count}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts that the graph manifested for `T` has the expected shape.
+///
+/// This is useful for guarding against accidental changes to how a factory wires up its
+/// associations.
+///
+/// # Panics
+///
+/// Panics with the types whose counts differ if the shape does not match.
+///
+/// ```
+/// # use malignius::assert_graph_shape;
+/// # use malignius::{association, Associations, Manifest};
+/// # #[derive(Clone)]
+/// # struct Author;
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         (Self, Associations::new())
+/// #     }
+/// # }
+/// # impl malignius::Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Post;
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Author = association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl malignius::Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # struct Comment;
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Post = association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// assert_graph_shape::<Comment>([("Comment", 1), ("Post", 1), ("Author", 1)]);
+/// ```
+pub fn assert_graph_shape<T: Manifest + 'static>(expected: impl Into<GraphShape>) {
+    let expected = expected.into();
+    let actual = GraphShape::of::<T>();
+
+    if actual == expected {
+        return;
+    }
+
+    let mut entity_types = expected.counts.keys().collect::<Vec<_>>();
+    entity_types.extend(actual.counts.keys());
+    entity_types.sort();
+    entity_types.dedup();
+
+    let differences = entity_types
+        .into_iter()
+        .filter(|entity_type| expected.count(entity_type) != actual.count(entity_type))
+        .map(|entity_type| {
+            format!(
+                "  {entity_type}: expected {}, found {}",
+                expected.count(entity_type),
+                actual.count(entity_type)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    panic!(
+        "unexpected graph shape for `{}`:\n{}",
+        short_type_name(type_name::<T>()),
+        differences.join("\n")
+    );
+}
+
+/// Strips the module paths from a type name, e.g. `Vec<alloc::string::String>` becomes
+/// `Vec<String>`.
+pub(crate) fn short_type_name(name: &str) -> String {
+    let mut short = String::new();
+    let mut segment = String::new();
+
+    let mut chars = name.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if char.is_alphanumeric() || char == '_' {
+            segment.push(char);
+        } else {
+            short.push_str(&segment);
+            segment.clear();
+            short.push(char);
+        }
+    }
+    short.push_str(&segment);
+
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{association, association_with, lazy_association, Associations, Persist};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct AuthorOverrides {}
+
+    #[derive(Debug)]
+    struct Author {}
+
+    impl Manifest for Author {
+        type Context = ();
+        type Overrides = AuthorOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self {}, Associations::new())
+        }
+    }
+
+    impl Persist for Author {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            Ok(author)
+        }
+    }
+
+    #[derive(Debug, Default, Clone)]
+    struct PostOverrides {
+        pub author_id: Option<i64>,
+    }
+
+    #[derive(Debug)]
+    struct Post {}
+
+    impl Manifest for Post {
+        type Context = ();
+        type Overrides = PostOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let _author_id =
+                lazy_association::<Author, _>(&mut associations, overrides.author_id, |_| 1);
+
+            (Self {}, associations)
+        }
+    }
+
+    impl Persist for Post {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            Ok(post)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct CommentOverrides {}
+
+    #[derive(Debug)]
+    struct Comment {}
+
+    impl Manifest for Comment {
+        type Context = ();
+        type Overrides = CommentOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let _post: Post = association(&mut associations);
+            let _author: Author = association(&mut associations);
+
+            (Self {}, associations)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Reply {}
+
+    impl Manifest for Reply {
+        type Context = ();
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let _post: Post =
+                association_with(&mut associations, PostOverrides { author_id: Some(1) });
+
+            (Self {}, associations)
+        }
+    }
+
+    #[test]
+    fn graph_shape_counts_nested_associations() {
+        assert_graph_shape::<Comment>([("Comment", 1), ("Post", 1), ("Author", 2)]);
+    }
+
+    #[test]
+    fn graph_shape_counts_associations_as_they_were_registered() {
+        // The `Post` is manifested with an author already given, so it registers no `Author`.
+        assert_graph_shape::<Reply>([("Reply", 1), ("Post", 1)]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "unexpected graph shape for `Comment`:\n  Author: expected 1, found 2\n  Tag: expected 1, found 0"
+    )]
+    fn graph_shape_mismatches_describe_the_differences() {
+        assert_graph_shape::<Comment>([("Comment", 1), ("Post", 1), ("Author", 1), ("Tag", 1)]);
+    }
+
+    #[test]
+    fn short_type_name_strips_module_paths() {
+        assert_eq!(short_type_name(type_name::<Comment>()), "Comment");
+        assert_eq!(
+            short_type_name(type_name::<Vec<Option<String>>>()),
+            "Vec<Option<String>>"
+        );
+    }
+}
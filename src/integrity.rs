@@ -0,0 +1,178 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+type CheckFuture<'a> = Pin<Box<dyn Future<Output = bool> + 'a>>;
+
+type IntegrityCheckFn<Context> = Box<dyn for<'a> Fn(&'a Context) -> CheckFuture<'a>>;
+
+/// A named assertion about the data in a context, run by [`verify_graph`].
+///
+/// ```
+/// # use malignius::IntegrityCheck;
+/// # struct TestContext;
+/// # impl TestContext {
+/// #     async fn orphaned_posts(&self) -> usize {
+/// #         0
+/// #     }
+/// # }
+/// let check = IntegrityCheck::new("every post has an author", |ctx: &TestContext| {
+///     Box::pin(async move { ctx.orphaned_posts().await == 0 })
+/// });
+/// ```
+pub struct IntegrityCheck<Context> {
+    name: String,
+    check: IntegrityCheckFn<Context>,
+}
+
+impl<Context> IntegrityCheck<Context> {
+    /// Creates a check that passes when `check` returns `true`.
+    pub fn new(
+        name: impl Into<String>,
+        check: impl for<'a> Fn(&'a Context) -> CheckFuture<'a> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            check: Box::new(check),
+        }
+    }
+
+    /// Returns the name of the check.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<Context> fmt::Debug for IntegrityCheck<Context> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntegrityCheck")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The outcome of the checks run by [`verify_graph`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    passed: Vec<String>,
+    failed: Vec<String>,
+}
+
+impl IntegrityReport {
+    /// Returns the names of the checks that passed, in the order they were run.
+    pub fn passed(&self) -> &[String] {
+        &self.passed
+    }
+
+    /// Returns the names of the checks that failed, in the order they were run.
+    pub fn failed(&self) -> &[String] {
+        &self.failed
+    }
+
+    /// Returns whether every check passed.
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in &self.passed {
+            writeln!(f, "passed: {name}")?;
+        }
+
+        for name in &self.failed {
+            writeln!(f, "failed: {name}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs integrity checks against a context, typically after persisting a graph.
+///
+/// The checks are run one at a time, in order, and every check is run even if an earlier one
+/// failed.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # struct TestContext;
+/// # impl TestContext {
+/// #     async fn orphaned_posts(&self) -> usize {
+/// #         0
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(TestContext);
+/// # let checks = [IntegrityCheck::new("every post has an author", |ctx: &TestContext| {
+/// #     Box::pin(async move { ctx.orphaned_posts().await == 0 })
+/// # })];
+/// let report = verify_graph(ctx, &checks).await;
+/// assert!(report.is_ok(), "{report}");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn verify_graph<Context>(
+    ctx: Arc<Context>,
+    checks: &[IntegrityCheck<Context>],
+) -> IntegrityReport {
+    let mut report = IntegrityReport::default();
+
+    for check in checks {
+        if (check.check)(&ctx).await {
+            report.passed.push(check.name.clone());
+        } else {
+            report.failed.push(check.name.clone());
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::persist;
+    use crate::test_support::{Post, TestContext, SCHEMA};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn verify_graph_reports_passed_and_failed_checks(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let _post: Post = persist(ctx.clone()).await?;
+
+        let checks = [
+            IntegrityCheck::new("every post has a valid author", |ctx: &TestContext| {
+                Box::pin(async move {
+                    ctx.count(
+                        r#"
+                            select count(*) from post
+                            left join author on author.name = post.author_name
+                            where author.id is null
+                        "#,
+                    )
+                    .is_ok_and(|count| count == 0)
+                })
+            }),
+            IntegrityCheck::new("there are two posts", |ctx: &TestContext| {
+                Box::pin(async move {
+                    ctx.count("select count(*) from post")
+                        .is_ok_and(|count| count == 2)
+                })
+            }),
+        ];
+
+        let report = verify_graph(ctx, &checks).await;
+
+        assert!(!report.is_ok());
+        assert_eq!(report.passed(), ["every post has a valid author"]);
+        assert_eq!(report.failed(), ["there are two posts"]);
+
+        Ok(())
+    }
+}
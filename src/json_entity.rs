@@ -0,0 +1,294 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde_json::{Map, Value};
+
+use crate::{Associations, Manifest, Persist};
+
+/// A kind of JSON document, for stores that don't have a fixed schema.
+///
+/// The document's fields are defined by [`JsonDocument::defaults`] at runtime, rather than by a
+/// struct. Documents are manifested and persisted as a [`JsonEntity`].
+///
+/// ```
+/// # use std::cell::RefCell;
+/// # use malignius::{manifest_with, Associations, JsonDocument, JsonEntity, JsonPatch};
+/// # use serde_json::{json, Value};
+/// # #[derive(Debug)]
+/// # struct StoreError;
+/// # #[derive(Default)]
+/// # struct DocumentStore {
+/// #     documents: RefCell<Vec<(&'static str, Value)>>,
+/// # }
+/// # impl DocumentStore {
+/// #     async fn insert(&self, table: &'static str, doc: Value) -> Result<Value, StoreError> {
+/// #         self.documents.borrow_mut().push((table, doc.clone()));
+/// #         Ok(doc)
+/// #     }
+/// # }
+/// struct UserDocument;
+///
+/// impl JsonDocument for UserDocument {
+///     type Context = DocumentStore;
+///     type Err = StoreError;
+///
+///     fn defaults(_associations: &mut Associations<Self::Context>) -> Value {
+///         json!({ "name": "Jane Smith", "roles": ["member"] })
+///     }
+///
+///     async fn persist(ctx: &Self::Context, document: Value) -> Result<Value, Self::Err> {
+///         ctx.insert("users", document).await
+///     }
+/// }
+///
+/// let user: JsonEntity<UserDocument> =
+///     manifest_with(JsonPatch::try_from(json!({ "name": "Ada" }))?);
+/// # assert_eq!(user.document, json!({ "name": "Ada", "roles": ["member"] }));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub trait JsonDocument {
+    type Context;
+    type Err;
+
+    /// Returns the default document, registering any associations it needs.
+    fn defaults(associations: &mut Associations<Self::Context>) -> Value;
+
+    /// Persists the document, returning the persisted document.
+    #[allow(async_fn_in_trait)]
+    async fn persist(ctx: &Self::Context, document: Value) -> Result<Value, Self::Err>;
+}
+
+/// A JSON document of kind `D` that can be manifested and persisted like any other entity.
+///
+/// Its overrides are a [`JsonPatch`] that is merged into the default document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonEntity<D> {
+    pub document: Value,
+    _kind: PhantomData<D>,
+}
+
+impl<D> JsonEntity<D> {
+    pub fn new(document: Value) -> Self {
+        Self {
+            document,
+            _kind: PhantomData,
+        }
+    }
+}
+
+/// Changes to a JSON document, applied as a JSON merge patch ([RFC 7396]).
+///
+/// Each field in the patch replaces the field in the document, except that objects are merged
+/// recursively and `null` removes the field.
+///
+/// [RFC 7396]: https://www.rfc-editor.org/rfc/rfc7396
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonPatch(pub Map<String, Value>);
+
+impl From<Map<String, Value>> for JsonPatch {
+    fn from(fields: Map<String, Value>) -> Self {
+        Self(fields)
+    }
+}
+
+impl TryFrom<Value> for JsonPatch {
+    type Error = InvalidJsonPatch;
+
+    /// Converts a JSON object into a patch, failing if the value is not an object.
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Object(fields) => Ok(Self(fields)),
+            value => Err(InvalidJsonPatch(value)),
+        }
+    }
+}
+
+/// The error returned when converting a JSON value that is not an object into a [`JsonPatch`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidJsonPatch(pub Value);
+
+impl fmt::Display for InvalidJsonPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a JSON patch must be an object, but got `{}`", self.0)
+    }
+}
+
+impl std::error::Error for InvalidJsonPatch {}
+
+impl JsonPatch {
+    /// Applies the patch to the document.
+    pub fn apply(self, document: &mut Value) {
+        merge(document, Value::Object(self.0));
+    }
+}
+
+fn merge(document: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *document = patch;
+        return;
+    };
+
+    if !document.is_object() {
+        *document = Value::Object(Map::new());
+    }
+
+    let fields = document.as_object_mut().unwrap();
+
+    for (key, value) in patch {
+        if value.is_null() {
+            fields.remove(&key);
+        } else {
+            merge(fields.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+impl<D> Manifest for JsonEntity<D>
+where
+    D: JsonDocument,
+    D::Context: 'static,
+{
+    type Context = D::Context;
+    type Overrides = JsonPatch;
+
+    fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+        let mut associations = Associations::new();
+
+        let mut document = D::defaults(&mut associations);
+        overrides.apply(&mut document);
+
+        (Self::new(document), associations)
+    }
+}
+
+impl<D> Persist for JsonEntity<D>
+where
+    D: JsonDocument,
+    D::Context: 'static,
+{
+    type Err = D::Err;
+
+    async fn persist(ctx: &Self::Context, entity: Self) -> Result<Self, Self::Err> {
+        Ok(Self::new(D::persist(ctx, entity.document).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use serde_json::json;
+
+    use crate::test_support::shared;
+    use crate::{association, manifest_with, persist_with};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct DocumentStore {
+        pub documents: RefCell<Vec<Value>>,
+    }
+
+    impl DocumentStore {
+        fn insert(&self, mut document: Value) -> Value {
+            let mut documents = self.documents.borrow_mut();
+            document["_id"] = json!(documents.len() + 1);
+            documents.push(document.clone());
+
+            document
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Team;
+
+    impl JsonDocument for Team {
+        type Context = DocumentStore;
+        type Err = std::convert::Infallible;
+
+        fn defaults(_associations: &mut Associations<Self::Context>) -> Value {
+            json!({ "name": "Platform" })
+        }
+
+        async fn persist(ctx: &Self::Context, document: Value) -> Result<Value, Self::Err> {
+            Ok(ctx.insert(document))
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct User;
+
+    impl JsonDocument for User {
+        type Context = DocumentStore;
+        type Err = std::convert::Infallible;
+
+        fn defaults(associations: &mut Associations<Self::Context>) -> Value {
+            let team: JsonEntity<Team> = association(associations);
+
+            json!({
+                "name": "Jane Smith",
+                "team": team.document["name"],
+                "settings": { "theme": "light", "notifications": true },
+            })
+        }
+
+        async fn persist(ctx: &Self::Context, document: Value) -> Result<Value, Self::Err> {
+            Ok(ctx.insert(document))
+        }
+    }
+
+    #[test]
+    fn overrides_are_merged_into_the_defaults() -> Result<(), InvalidJsonPatch> {
+        let user: JsonEntity<User> = manifest_with(JsonPatch::try_from(json!({
+            "name": "Ada Lovelace",
+            "settings": { "theme": "dark", "notifications": null },
+            "admin": true,
+        }))?);
+
+        assert_eq!(
+            user.document,
+            json!({
+                "name": "Ada Lovelace",
+                "team": "Platform",
+                "settings": { "theme": "dark" },
+                "admin": true,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn patches_must_be_objects() {
+        let err = JsonPatch::try_from(json!(["admin"])).unwrap_err();
+
+        assert_eq!(err, InvalidJsonPatch(json!(["admin"])));
+        assert_eq!(
+            err.to_string(),
+            r#"a JSON patch must be an object, but got `["admin"]`"#
+        );
+    }
+
+    #[tokio::test]
+    async fn json_entities_are_persisted_with_their_associations(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = shared(DocumentStore::default());
+
+        let user: JsonEntity<User> =
+            persist_with(ctx.clone(), JsonPatch::try_from(json!({ "admin": true }))?).await?;
+
+        assert_eq!(user.document["_id"], 2);
+        assert_eq!(user.document["name"], "Jane Smith");
+        assert_eq!(user.document["admin"], true);
+
+        assert_eq!(
+            *ctx.documents.borrow(),
+            vec![
+                json!({ "_id": 1, "name": "Platform" }),
+                user.document.clone(),
+            ]
+        );
+
+        Ok(())
+    }
+}
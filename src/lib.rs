@@ -1,22 +1,278 @@
 #![doc = include_str!("../README.md")]
 
+// Allows the code generated by `malignius-macros` to refer to `::malignius` in our own tests.
+#[cfg(test)]
+extern crate self as malignius;
+
+mod abstract_manifest;
 mod associations;
+mod bounded_sequence;
+mod bulk;
+mod capture;
+mod clock;
+mod config;
+mod dedup;
+mod defaults_context;
+mod error;
+#[cfg(feature = "fixtures")]
+mod fixtures;
+#[cfg(feature = "formats")]
+pub mod formats;
+mod graph_path;
+mod graph_shape;
+mod integrity;
+#[cfg(feature = "json")]
+mod json_entity;
+#[cfg(feature = "memory-store")]
+mod memory_store;
+mod names;
+mod observers;
+mod once;
+mod options;
+mod orphans;
+mod panic;
+mod persisted_associations;
+#[cfg(feature = "presets")]
+mod presets;
+mod provenance;
+mod retry;
+#[cfg(feature = "rand")]
+mod rng;
+mod rows_affected;
+mod scenario;
+mod scope;
+mod scoped_sequence;
 mod sequence;
-
+pub mod sequences;
+mod services;
+mod session;
+mod sharded_context;
+mod shared_sequence;
+mod sql_connection;
+mod sql_value;
+#[cfg(feature = "sqlx")]
+mod sqlx_transaction;
+mod strict;
+mod tenant;
+#[cfg(test)]
+mod test_support;
+mod timeseries;
+mod tree;
+mod try_manifest;
+mod try_sequence;
+mod tuple;
+mod unique;
+mod variants;
+mod verify;
+mod versioned;
+
+use std::any::Any;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Instant;
 
+use futures::{future, stream, StreamExt, TryStreamExt};
+use futures_timer::Delay;
+
+pub use abstract_manifest::*;
 pub use associations::*;
+pub use bounded_sequence::*;
+pub use bulk::*;
+pub use capture::*;
+pub use clock::*;
+pub use config::*;
+pub use dedup::*;
+pub use defaults_context::*;
+pub use error::*;
+#[cfg(feature = "fixtures")]
+pub use fixtures::*;
+pub use graph_path::*;
+pub use graph_shape::*;
+pub use integrity::*;
+#[cfg(feature = "json")]
+pub use json_entity::*;
+#[cfg(feature = "derive")]
+pub use malignius_macros::{OverrideProvenance, Persist, StrictOverrides};
+#[cfg(feature = "memory-store")]
+pub use memory_store::*;
+pub use names::*;
+pub use observers::*;
+pub use once::*;
+pub use options::*;
+pub use orphans::*;
+pub use panic::PanicError;
+pub use persisted_associations::*;
+#[cfg(feature = "presets")]
+pub use presets::*;
+pub use provenance::*;
+pub use retry::*;
+#[cfg(feature = "rand")]
+pub use rng::*;
+pub use rows_affected::*;
+pub use scenario::*;
+pub use scoped_sequence::*;
 pub use sequence::*;
-
+pub use services::*;
+pub use session::*;
+pub use sharded_context::*;
+pub use shared_sequence::*;
+pub use sql_connection::*;
+pub use sql_value::*;
+#[cfg(feature = "sqlx")]
+pub use sqlx_transaction::*;
+pub use strict::*;
+pub use tenant::*;
+pub use timeseries::*;
+pub use tree::*;
+pub use try_manifest::*;
+pub use try_sequence::*;
+pub use tuple::*;
+pub use unique::*;
+pub use variants::*;
+pub use verify::*;
+pub use versioned::*;
+
+/// An entity that can be manifested from a set of overrides.
+///
+/// # Borrowed defaults
+///
+/// Entities with many string fields can avoid allocating a copy of every default on every
+/// manifest by using `Cow<'static, str>` fields, borrowing defaults from string literals and only
+/// owning the values that were overridden:
+///
+/// ```
+/// # use std::borrow::Cow;
+/// # use malignius::{Associations, Manifest};
+/// # #[derive(Default)]
+/// # struct TagBuilder {
+/// #     name: Option<Cow<'static, str>>,
+/// # }
+/// struct Tag {
+///     pub name: Cow<'static, str>,
+/// }
+///
+/// impl Manifest for Tag {
+///     type Context = ();
+///     type Overrides = TagBuilder;
+///
+///     fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+///         let name = overrides.name.unwrap_or(Cow::Borrowed("rust"));
+///
+///         (Self { name }, Associations::new())
+///     }
+/// }
+/// # assert_eq!(malignius::manifest::<Tag>().name, "rust");
+/// ```
+///
+/// Such entities work with every function in this crate, including the ones that require
+/// `T: 'static`, since `Cow<'static, str>` only borrows data that lives forever. Defaults that
+/// are computed, such as ones that include a sequence number, still have to be owned.
 pub trait Manifest {
     type Context;
-    type Overrides: Default;
+    type Overrides: InitialOverrides;
 
     fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>)
     where
         Self: Sized;
+
+    /// Checks invariants that span multiple fields, such as `start_date < end_date`.
+    ///
+    /// This is run by [`try_manifest`] once the overrides have been applied, so that overrides
+    /// that break the entity's invariants are rejected.
+    fn check_constraints(_entity: &Self) -> Result<(), ConstraintViolation>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+}
+
+/// The overrides an entity is manifested with when none are given, such as by [`manifest`],
+/// [`persist`], or [`association`].
+///
+/// This is implemented for every type that implements [`Default`]. Overrides that can't
+/// sensibly implement `Default`, such as ones that hold a seeded RNG, can implement it directly
+/// instead:
+///
+/// ```
+/// # use malignius::InitialOverrides;
+/// # const DEFAULT_SEED: u64 = 42;
+/// # struct DiceOverrides {
+/// #     seed: u64,
+/// # }
+/// # impl DiceOverrides {
+/// #     fn from_seed(seed: u64) -> Self {
+/// #         Self { seed }
+/// #     }
+/// # }
+/// impl InitialOverrides for DiceOverrides {
+///     fn initial() -> Self {
+///         Self::from_seed(DEFAULT_SEED)
+///     }
+/// }
+/// # assert_eq!(DiceOverrides::initial().seed, DEFAULT_SEED);
+/// ```
+pub trait InitialOverrides {
+    fn initial() -> Self;
 }
 
+impl<T: Default> InitialOverrides for T {
+    fn initial() -> Self {
+        T::default()
+    }
+}
+
+/// An entity that can be persisted to its [`Manifest::Context`].
+///
+/// Entities that share a single table and are distinguished by a discriminator column can be
+/// modeled as an enum, with `persist` matching on the variant to pick the statement to run:
+///
+/// ```
+/// # use malignius::{Associations, Manifest, Persist};
+/// # use rusqlite::{params, Connection};
+/// # struct TestContext {
+/// #     conn: Connection,
+/// # }
+/// # enum Vehicle {
+/// #     Car { id: i64, seats: u8 },
+/// #     Truck { id: i64, payload: u32 },
+/// # }
+/// # impl Manifest for Vehicle {
+/// #     type Context = TestContext;
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<TestContext>) {
+/// #         (Vehicle::Car { id: 1, seats: 4 }, Associations::new())
+/// #     }
+/// # }
+/// impl Persist for Vehicle {
+///     type Err = rusqlite::Error;
+///
+///     async fn persist(ctx: &Self::Context, vehicle: Self) -> Result<Self, Self::Err> {
+///         match &vehicle {
+///             Vehicle::Car { id, seats } => ctx.conn.execute(
+///                 "insert into vehicle (id, kind, seats) values ($1, 'car', $2)",
+///                 params![id, seats],
+///             )?,
+///             Vehicle::Truck { id, payload } => ctx.conn.execute(
+///                 "insert into vehicle (id, kind, payload) values ($1, 'truck', $2)",
+///                 params![id, payload],
+///             )?,
+///         };
+///
+///         Ok(vehicle)
+///     }
+/// }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let conn = Connection::open_in_memory()?;
+/// # conn.execute_batch(
+/// #     "create table vehicle (id integer primary key, kind text, seats integer, payload real)",
+/// # )?;
+/// # let ctx = std::sync::Arc::new(TestContext { conn });
+/// # let _: Vehicle = malignius::persist(ctx.clone()).await?;
+/// # Ok(())
+/// # }
+/// ```
 pub trait Persist: Manifest {
     type Err;
 
@@ -24,46 +280,741 @@ pub trait Persist: Manifest {
     async fn persist(ctx: &Self::Context, entity: Self) -> Result<Self, Self::Err>
     where
         Self: Sized;
+
+    /// Decides whether the entity should actually be persisted.
+    ///
+    /// This is called after the entity's associations have been persisted. Returning
+    /// [`PersistDecision::Skip`] returns the entity as-is without calling [`Persist::persist`].
+    fn before_persist(_ctx: &Self::Context, entity: Self) -> PersistDecision<Self>
+    where
+        Self: Sized,
+    {
+        PersistDecision::Proceed(entity)
+    }
+
+    /// Persists several entities at once.
+    ///
+    /// This is used instead of [`Persist::persist`] when the type is configured as
+    /// [`batched`](PersistConfig::batched). The returned entities must be in the same order as
+    /// the given ones. By default each entity is persisted in turn.
+    #[allow(async_fn_in_trait)]
+    async fn persist_batch(ctx: &Self::Context, entities: Vec<Self>) -> Result<Vec<Self>, Self::Err>
+    where
+        Self: Sized,
+    {
+        let mut persisted = Vec::with_capacity(entities.len());
+        for entity in entities {
+            persisted.push(Self::persist(ctx, entity).await?);
+        }
+
+        Ok(persisted)
+    }
+
+    /// Begins a transaction, when the type is configured as
+    /// [`transactional`](PersistConfig::transactional). By default this does nothing.
+    #[allow(async_fn_in_trait)]
+    async fn begin_transaction(_ctx: &Self::Context) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    /// Commits the transaction begun by [`Persist::begin_transaction`]. By default this does
+    /// nothing.
+    #[allow(async_fn_in_trait)]
+    async fn commit_transaction(_ctx: &Self::Context) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    /// Rolls back the transaction begun by [`Persist::begin_transaction`]. By default this does
+    /// nothing.
+    #[allow(async_fn_in_trait)]
+    async fn rollback_transaction(_ctx: &Self::Context) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+/// The decision made by [`Persist::before_persist`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PersistDecision<T> {
+    /// Persist the entity.
+    Proceed(T),
+    /// Skip persisting the entity.
+    Skip(T),
+}
+
+/// An entity whose insert returns columns computed by the database.
+///
+/// This is useful for columns filled in by defaults, generated columns, or triggers, which are
+/// captured with a `RETURNING` clause and applied back onto the entity. Every
+/// `PersistReturning` entity implements [`Persist`].
+pub trait PersistReturning: Manifest {
+    type Err;
+
+    /// The columns returned by the insert.
+    type Returning;
+
+    /// Inserts the entity, returning the computed columns.
+    #[allow(async_fn_in_trait)]
+    async fn insert_returning(
+        ctx: &Self::Context,
+        entity: &Self,
+    ) -> Result<Self::Returning, Self::Err>;
+
+    /// Applies the returned columns to the entity.
+    fn apply_returning(entity: Self, returning: Self::Returning) -> Self
+    where
+        Self: Sized;
+}
+
+impl<T: PersistReturning> Persist for T {
+    type Err = T::Err;
+
+    async fn persist(ctx: &Self::Context, entity: Self) -> Result<Self, Self::Err> {
+        let returning = T::insert_returning(ctx, &entity).await?;
+
+        Ok(T::apply_returning(entity, returning))
+    }
 }
 
 #[inline(always)]
 pub fn manifest<T: Manifest>() -> T {
-    manifest_with(T::Overrides::default())
+    manifest_with(T::Overrides::initial())
 }
 
 pub fn manifest_with<T: Manifest>(overrides: T::Overrides) -> T {
-    let (entity, _) = T::manifest(overrides);
+    let (entity, _) = graph_path::with_root::<T, _>(|| T::manifest(overrides));
     entity
 }
 
+/// Manifests an entity after applying each of the override functions to the default overrides.
+///
+/// The functions are applied in order, so later functions take precedence. This allows
+/// reusable sets of overrides to be composed:
+///
+/// ```
+/// # use malignius::{manifest_with_all, Associations, Manifest};
+/// # #[derive(Default)]
+/// # struct UserOverrides {
+/// #     premium: Option<bool>,
+/// #     email_verified: Option<bool>,
+/// # }
+/// # struct User {
+/// #     premium: bool,
+/// #     email_verified: bool,
+/// # }
+/// # impl Manifest for User {
+/// #     type Context = ();
+/// #     type Overrides = UserOverrides;
+/// #     fn manifest(overrides: UserOverrides) -> (Self, Associations<()>) {
+/// #         let user = User {
+/// #             premium: overrides.premium.unwrap_or(false),
+/// #             email_verified: overrides.email_verified.unwrap_or(false),
+/// #         };
+/// #         (user, Associations::new())
+/// #     }
+/// # }
+/// # fn with_premium(overrides: &mut UserOverrides) {
+/// #     overrides.premium = Some(true);
+/// # }
+/// # fn with_verified_email(overrides: &mut UserOverrides) {
+/// #     overrides.email_verified = Some(true);
+/// # }
+/// let user: User = manifest_with_all([with_premium, with_verified_email]);
+/// # assert!(user.premium && user.email_verified);
+/// ```
+pub fn manifest_with_all<T: Manifest, F>(fns: impl IntoIterator<Item = F>) -> T
+where
+    F: FnOnce(&mut T::Overrides),
+{
+    let mut overrides = T::Overrides::initial();
+
+    for f in fns {
+        f(&mut overrides);
+    }
+
+    manifest_with(overrides)
+}
+
+/// Manifests `count` entities of type `T`.
+///
+/// Like [`manifest`], any associations the entities declare are discarded.
+#[inline(always)]
+pub fn build_many<T: Manifest>(count: usize) -> Vec<T> {
+    build_many_with(count, |_| T::Overrides::initial())
+}
+
+/// Manifests `count` entities of type `T`, using the overrides returned by `overrides` for the
+/// entity at each index.
+///
+/// ```
+/// # use malignius::{build_many_with, Associations, Manifest};
+/// # #[derive(Default)]
+/// # struct MovieOverrides {
+/// #     title: Option<String>,
+/// #     year: Option<u32>,
+/// # }
+/// # struct Movie {
+/// #     title: String,
+/// #     year: u32,
+/// # }
+/// # impl Manifest for Movie {
+/// #     type Context = ();
+/// #     type Overrides = MovieOverrides;
+/// #     fn manifest(overrides: MovieOverrides) -> (Self, Associations<()>) {
+/// #         let movie = Movie {
+/// #             title: overrides.title.unwrap_or_else(|| "Arrival".into()),
+/// #             year: overrides.year.unwrap_or(2016),
+/// #         };
+/// #         (movie, Associations::new())
+/// #     }
+/// # }
+/// let movies: Vec<Movie> = build_many_with(5, |index| MovieOverrides {
+///     title: Some(format!("Movie {index}")),
+///     ..Default::default()
+/// });
+/// # assert_eq!(movies[4].title, "Movie 4");
+/// # assert_eq!(movies[4].year, 2016);
+/// ```
+pub fn build_many_with<T: Manifest>(
+    count: usize,
+    mut overrides: impl FnMut(usize) -> T::Overrides,
+) -> Vec<T> {
+    (0..count)
+        .map(|index| {
+            let overrides = overrides(index);
+            let (entity, _) = graph_path::with_root_at::<T, _>(index, || T::manifest(overrides));
+            entity
+        })
+        .collect()
+}
+
+/// Manifests a value object to be embedded in another entity.
+///
+/// Unlike [`association`], the value object is not registered to be persisted, so its fields
+/// can be flattened into the columns of the entity that embeds it. Any associations the value
+/// object declares are discarded.
+#[inline(always)]
+pub fn embed<T: Manifest>() -> T {
+    manifest::<T>()
+}
+
+/// Manifests a value object to be embedded in another entity using the given overrides.
+///
+/// See [`embed`].
+#[inline(always)]
+pub fn embed_with<T: Manifest>(overrides: T::Overrides) -> T {
+    manifest_with::<T>(overrides)
+}
+
 #[inline(always)]
-pub async fn persist<T: Persist>(ctx: Arc<T::Context>) -> Result<T, T::Err> {
-    persist_with(ctx, T::Overrides::default()).await
+pub async fn persist<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+) -> Result<T, MaligniusError<T::Err>> {
+    persist_with(ctx, T::Overrides::initial()).await
 }
 
-pub async fn persist_with<T: Persist>(
+pub async fn persist_with<T: Persist + 'static>(
     ctx: Arc<T::Context>,
     overrides: T::Overrides,
-) -> Result<T, T::Err> {
-    let (entity, associations) = T::manifest(overrides);
+) -> Result<T, MaligniusError<T::Err>> {
+    persist_with_options(ctx, overrides, configured_options::<T>()).await
+}
+
+/// Persists an entity, persisting its associations of different types concurrently.
+///
+/// See [`PersistOptions::concurrent_types`].
+pub async fn persist_with_concurrent<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+) -> Result<T, MaligniusError<T::Err>> {
+    let options = PersistOptions {
+        concurrent_types: true,
+        ..configured_options::<T>()
+    };
+
+    persist_with_options(ctx, overrides, options).await
+}
+
+pub async fn persist_with_options<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+    options: PersistOptions,
+) -> Result<T, MaligniusError<T::Err>> {
+    persist_in_with_options::<T>(&ctx, overrides, options).await
+}
+
+/// Persists an entity using a borrowed context.
+///
+/// This allows persisting through a context that cannot be shared in an [`Arc`], such as a
+/// database transaction.
+#[inline(always)]
+pub async fn persist_in<T: Persist + 'static>(
+    ctx: &T::Context,
+) -> Result<T, MaligniusError<T::Err>> {
+    persist_in_with(ctx, T::Overrides::initial()).await
+}
+
+pub async fn persist_in_with<T: Persist + 'static>(
+    ctx: &T::Context,
+    overrides: T::Overrides,
+) -> Result<T, MaligniusError<T::Err>> {
+    persist_in_with_options(ctx, overrides, configured_options::<T>()).await
+}
+
+pub async fn persist_in_with_options<T: Persist + 'static>(
+    ctx: &T::Context,
+    overrides: T::Overrides,
+    options: PersistOptions,
+) -> Result<T, MaligniusError<T::Err>> {
+    persist_root_at::<T>(ctx, 0, overrides, options).await
+}
+
+/// Persists `count` entities of type `T` one after the other, along with their associations.
+///
+/// Each entity is persisted as if by its own call to [`persist`], so values drawn from
+/// sequences, such as unique names, differ between them.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::{persist_many, Associations, Manifest, Persist};
+/// # struct Author;
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         (Self, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let authors: Vec<Author> = persist_many(ctx, 3).await?;
+/// # assert_eq!(authors.len(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[inline(always)]
+pub async fn persist_many<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    count: usize,
+) -> Result<Vec<T>, MaligniusError<T::Err>> {
+    persist_many_with(ctx, count, |_| T::Overrides::initial()).await
+}
+
+/// Persists `count` entities of type `T` one after the other, using the overrides returned by
+/// `overrides` for the entity at each index.
+///
+/// See [`persist_many`].
+pub async fn persist_many_with<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    count: usize,
+    mut overrides: impl FnMut(usize) -> T::Overrides,
+) -> Result<Vec<T>, MaligniusError<T::Err>> {
+    let mut persisted = Vec::with_capacity(count);
+    for index in 0..count {
+        let entity =
+            persist_root_at::<T>(&ctx, index, overrides(index), configured_options::<T>()).await?;
+        persisted.push(entity);
+    }
+
+    Ok(persisted)
+}
+
+/// Persists an entity as the `index`th root of the graph.
+async fn persist_root_at<T: Persist + 'static>(
+    ctx: &T::Context,
+    index: usize,
+    overrides: T::Overrides,
+    options: PersistOptions,
+) -> Result<T, MaligniusError<T::Err>> {
+    persist_manifested_root_at(ctx, index, || T::manifest(overrides), options).await
+}
+
+/// Persists the entity returned by `manifest` as the `index`th root of the graph.
+///
+/// The entity is manifested and persisted within the scopes of its own graph, so that
+/// [`keyed_association`]s, [`sequences::per_persist`] counters and the graph path start over for
+/// it.
+async fn persist_manifested_root_at<T: Persist + 'static>(
+    ctx: &T::Context,
+    index: usize,
+    manifest: impl FnOnce() -> (T, Associations<T::Context>),
+    mut options: PersistOptions,
+) -> Result<T, MaligniusError<T::Err>> {
+    let tenant = options.tenant.take();
+    let graph = async move {
+        let mut persisted = persist_manifested(ctx, vec![manifest()], &options).await?;
+
+        Ok(persisted.remove(0))
+    };
+
+    in_root_scopes::<T, _>(index, tenant::in_tenant(tenant, graph)).await
+}
+
+/// Runs the future within the scopes of the `index`th root of the graph.
+async fn in_root_scopes<T: ?Sized, F: Future>(index: usize, future: F) -> F::Output {
+    let graph = graph_path::in_root_at::<T, _>(index, future);
+
+    let graph = dedup::in_dedup_scope(graph);
+
+    sequences::in_persist_scope(graph).await
+}
+
+/// Persists `count` entities of type `T` as roots of the graph numbered from `first_index`,
+/// manifesting the entity at each offset with `manifest`.
+///
+/// Each entity is manifested and has its associations persisted within the scopes of its own
+/// graph, the same way as by [`persist_many_with`]. If `T` is configured as
+/// [`batched`](PersistConfig::batched) the entities themselves are then persisted in batches,
+/// otherwise each is persisted within its own scopes.
+pub(crate) async fn persist_roots_at<T: Persist + 'static>(
+    ctx: &T::Context,
+    first_index: usize,
+    count: usize,
+    mut manifest: impl FnMut(usize) -> (T, Associations<T::Context>),
+    mut options: PersistOptions,
+) -> Result<Vec<T>, MaligniusError<T::Err>> {
+    let config = config::config_for::<T>();
+    if !config.batched {
+        let mut persisted = Vec::with_capacity(count);
+        for offset in 0..count {
+            let entity = persist_manifested_root_at(
+                ctx,
+                first_index + offset,
+                || manifest(offset),
+                options.clone(),
+            )
+            .await?;
+            persisted.push(entity);
+        }
+
+        return Ok(persisted);
+    }
+
+    let tenant = options.tenant.take();
+    let roots = in_configured_transaction::<T, _>(
+        ctx,
+        &config,
+        Box::pin(async {
+            let mut entities = Vec::with_capacity(count);
+            for offset in 0..count {
+                let root = async {
+                    let (entity, associations) = manifest(offset);
+                    persist_entity_associations(ctx, entity, associations, &options).await
+                };
+                entities.push(in_root_scopes::<T, _>(first_index + offset, root).await?);
+            }
+
+            persist_entities(ctx, entities, &options, &config).await
+        }),
+    );
+
+    tenant::in_tenant(tenant, roots).await
+}
+
+/// Returns the [`PersistOptions`] to persist `T` with when none are given, adjusted by the
+/// [`PersistConfig`] registered for `T`.
+///
+/// These are the default options, or the ones inherited from the entity that registered `T` as an
+/// association.
+pub(crate) fn configured_options<T: 'static>() -> PersistOptions {
+    let mut options = options::inherited_options().unwrap_or_default();
+    if let Some(max_concurrency) = config::config_for::<T>().max_concurrency {
+        options.max_concurrency = max_concurrency;
+    }
+
+    options
+}
+
+/// Persists the associations, returning the persisted entities along with their type names in the
+/// order they were started.
+///
+/// Persisting stops at the first association that fails, and its error is returned.
+async fn persist_associations<Context: 'static>(
+    ctx: &Context,
+    associations: Associations<Context>,
+    options: &PersistOptions,
+) -> Result<Vec<(&'static str, Box<dyn Any>)>, Box<dyn std::error::Error>> {
+    let mut associations = associations.into_ordered()?;
+    if let Some(only) = &options.only {
+        associations.retain(|association| only.contains(&association.entity_type));
+    }
+
+    let entity_type_names = associations
+        .iter()
+        .map(|association| association.entity_type_name)
+        .collect::<Vec<_>>();
+
+    let persisted = if options.concurrent_types {
+        persist_types_concurrently(ctx, associations, options).await
+    } else {
+        let started_at = Instant::now();
+        stream::iter(associations.into_iter().enumerate())
+            .map(|(index, association)| async move {
+                if let Some(throttle) = options.throttle {
+                    let starts_at = started_at + throttle * index as u32;
+                    Delay::new(starts_at.saturating_duration_since(Instant::now())).await;
+                }
+
+                options::inheriting_options(options, (association.persist)(ctx)).await
+            })
+            .buffered(options.max_concurrency.max(1))
+            .try_collect()
+            .await
+    };
+    if let Err(err) = &persisted {
+        options.error_logging.log(&match err.source() {
+            Some(source) => format!("{err}: {source}"),
+            None => err.to_string(),
+        });
+    }
+    let persisted = entity_type_names
+        .into_iter()
+        .zip(persisted?)
+        .collect::<Vec<_>>();
+
+    if let Some(check) = &options.verify_associations {
+        for (entity_type_name, association) in &persisted {
+            if !check.check(ctx, association.as_ref()) {
+                let err = UnverifiedAssociation {
+                    entity_type: entity_type_name,
+                };
+                options.error_logging.log(&err.to_string());
+
+                return Err(Box::new(err));
+            }
+        }
+    }
+
+    Ok(persisted)
+}
+
+/// Persists associations of different types concurrently, and associations of the same type one
+/// at a time in the order they were registered.
+///
+/// The associations must already be ordered, so that those of the same type are next to each
+/// other.
+async fn persist_types_concurrently<Context: 'static>(
+    ctx: &Context,
+    associations: Vec<AnyAssociation<Context>>,
+    options: &PersistOptions,
+) -> Result<Vec<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    let mut groups: Vec<Vec<AnyAssociation<Context>>> = Vec::new();
+    for association in associations {
+        match groups.last_mut() {
+            Some(group) if group[0].entity_type == association.entity_type => {
+                group.push(association)
+            }
+            _ => groups.push(vec![association]),
+        }
+    }
+
+    let persisted = future::try_join_all(groups.into_iter().map(|group| async move {
+        let mut persisted = Vec::with_capacity(group.len());
+        for association in group {
+            persisted.push(options::inheriting_options(options, (association.persist)(ctx)).await?);
+        }
+
+        Ok::<_, Box<dyn std::error::Error>>(persisted)
+    }))
+    .await?;
+
+    Ok(persisted.into_iter().flatten().collect())
+}
+
+/// Persists entities that have already been manifested, along with their associations.
+///
+/// The entities are returned in the order they were given. If `T` is configured as
+/// [`batched`](PersistConfig::batched) they are persisted with a single call to
+/// [`Persist::persist_batch`], otherwise they are persisted one at a time. If `T` is configured as
+/// [`transactional`](PersistConfig::transactional) all of it happens in a single transaction.
+pub(crate) async fn persist_manifested<T: Persist + 'static>(
+    ctx: &T::Context,
+    manifested: Vec<(T, Associations<T::Context>)>,
+    options: &PersistOptions,
+) -> Result<Vec<T>, MaligniusError<T::Err>> {
+    let config = config::config_for::<T>();
+
+    // The future is boxed so that persisting an entity doesn't need a copy of it on the stack
+    // for every level of the graph.
+    in_configured_transaction::<T, _>(
+        ctx,
+        &config,
+        Box::pin(async {
+            let mut entities = Vec::with_capacity(manifested.len());
+            for (entity, associations) in manifested {
+                entities
+                    .push(persist_entity_associations(ctx, entity, associations, options).await?);
+            }
+
+            persist_entities(ctx, entities, options, &config).await
+        }),
+    )
+    .await
+}
+
+/// Runs the future in a transaction if `T` is configured as
+/// [`transactional`](PersistConfig::transactional), rolling it back if the future fails.
+async fn in_configured_transaction<T: Persist, R>(
+    ctx: &T::Context,
+    config: &PersistConfig,
+    future: impl Future<Output = Result<R, MaligniusError<T::Err>>>,
+) -> Result<R, MaligniusError<T::Err>> {
+    if !config.transactional {
+        return future.await;
+    }
+
+    T::begin_transaction(ctx).await?;
+    match future.await {
+        Ok(persisted) => {
+            T::commit_transaction(ctx).await?;
+
+            Ok(persisted)
+        }
+        Err(err) => {
+            // The error that caused the rollback says more than any from the rollback itself.
+            let _ = T::rollback_transaction(ctx).await;
+
+            Err(err)
+        }
+    }
+}
+
+/// Persists the associations of a manifested entity, returning the entity once they have been.
+async fn persist_entity_associations<T: Persist + 'static>(
+    ctx: &T::Context,
+    mut entity: T,
+    associations: Associations<T::Context>,
+    options: &PersistOptions,
+) -> Result<T, MaligniusError<T::Err>> {
+    let children = persist_associations(ctx, associations, options)
+        .await
+        .map_err(MaligniusError::Association)?;
+    for (entity_type_name, child) in children {
+        observers::notify_association_persisted(&mut entity, child.as_ref());
+        persisted_associations::record_persisted_association(entity_type_name, child);
+    }
+
+    Ok(entity)
+}
+
+/// Persists entities whose associations have already been persisted.
+async fn persist_entities<T: Persist + 'static>(
+    ctx: &T::Context,
+    entities: Vec<T>,
+    options: &PersistOptions,
+    config: &PersistConfig,
+) -> Result<Vec<T>, MaligniusError<T::Err>> {
+    if !config.batched {
+        let mut persisted = Vec::with_capacity(entities.len());
+        for entity in entities {
+            persisted.push(persist_entity(ctx, entity, options).await?);
+        }
+
+        return Ok(persisted);
+    }
+
+    let count = entities.len();
+    let mut proceeding = Vec::with_capacity(count);
+    let mut skipped = Vec::new();
+    for (index, entity) in entities.into_iter().enumerate() {
+        match T::before_persist(ctx, entity) {
+            PersistDecision::Proceed(entity) => proceeding.push(entity),
+            PersistDecision::Skip(entity) => skipped.push((index, entity)),
+        }
+    }
+
+    let batch_size = config.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1);
+    let mut persisted = Vec::with_capacity(proceeding.len());
+    let mut proceeding = proceeding.into_iter().peekable();
+    while proceeding.peek().is_some() {
+        let batch: Vec<T> = proceeding.by_ref().take(batch_size).collect();
+        let expected = batch.len();
+
+        let batch = T::persist_batch(ctx, batch).await;
+        if batch.is_err() {
+            log_persist_error::<T>(options);
+        }
+        let batch = batch?;
+
+        if batch.len() != expected {
+            let err = BatchSizeMismatch {
+                entity_type: std::any::type_name::<T>(),
+                expected,
+                returned: batch.len(),
+            };
+            options.error_logging.log(&err.to_string());
 
-    for association in associations.associations {
-        (association.persist)(ctx.clone()).await.unwrap();
+            return Err(MaligniusError::Association(Box::new(err)));
+        }
+        persisted.extend(batch);
+    }
+
+    for entity in &persisted {
+        observers::notify_persisted(entity);
     }
 
-    T::persist(&ctx, entity).await
+    // Every batch returned as many entities as it was given, so there is one entity for each of
+    // the indices that were not skipped.
+    let mut persisted = persisted.into_iter();
+    let mut skipped = skipped.into_iter().peekable();
+
+    Ok((0..count)
+        .filter_map(
+            |index| match skipped.next_if(|(skipped_index, _)| *skipped_index == index) {
+                Some((_, entity)) => Some(entity),
+                None => persisted.next(),
+            },
+        )
+        .collect())
+}
+
+async fn persist_entity<T: Persist + 'static>(
+    ctx: &T::Context,
+    entity: T,
+    options: &PersistOptions,
+) -> Result<T, T::Err> {
+    let entity = match T::before_persist(ctx, entity) {
+        PersistDecision::Proceed(entity) => {
+            let persisted = T::persist(ctx, entity).await;
+            if persisted.is_err() {
+                log_persist_error::<T>(options);
+            }
+
+            persisted?
+        }
+        PersistDecision::Skip(entity) => return Ok(entity),
+    };
+
+    observers::notify_persisted(&entity);
+
+    Ok(entity)
+}
+
+fn log_persist_error<T>(options: &PersistOptions) {
+    options.error_logging.log(&format!(
+        "failed to persist `{}`",
+        std::any::type_name::<T>()
+    ));
 }
 
 #[cfg(test)]
 mod tests {
+    use std::any::TypeId;
+    use std::borrow::Cow;
+    use std::cell::Cell;
+
     use derive_builder::Builder;
     use rusqlite::{params, Connection};
 
-    use super::*;
+    use crate::test_support::TestContext;
 
-    struct TestContext {
-        pub conn: Connection,
-    }
+    use super::*;
 
     #[derive(Debug, Builder, PartialEq, Eq)]
     struct Movie {
@@ -131,11 +1082,50 @@ mod tests {
         )
     }
 
+    #[test]
+    fn manifest_with_all_applies_every_override_function() {
+        fn with_sequel_title(movie: &mut MovieBuilder) {
+            movie.title("Inception 2".into());
+        }
+
+        fn with_future_year(movie: &mut MovieBuilder) {
+            movie.year(2030);
+        }
+
+        let movie: Movie = manifest_with_all([with_sequel_title, with_future_year]);
+
+        assert_eq!(
+            movie,
+            Movie {
+                title: "Inception 2".into(),
+                year: 2030
+            }
+        )
+    }
+
+    #[test]
+    fn build_many_with_varies_each_entity_by_index() {
+        let movies: Vec<Movie> = build_many_with(5, |index| {
+            let mut movie = MovieBuilder::default();
+            movie.title(format!("Movie {index}"));
+            movie
+        });
+
+        assert_eq!(
+            movies
+                .iter()
+                .map(|movie| movie.title.as_str())
+                .collect::<Vec<_>>(),
+            ["Movie 0", "Movie 1", "Movie 2", "Movie 3", "Movie 4"]
+        );
+        assert!(movies.iter().all(|movie| movie.year == 2010));
+
+        assert_eq!(build_many::<Movie>(3).len(), 3);
+    }
+
     #[tokio::test]
     async fn persist_works() -> Result<(), Box<dyn std::error::Error>> {
-        let conn = Connection::open(":memory:")?;
-
-        conn.execute(
+        let ctx = TestContext::shared(
             r#"
                 create table if not exists movie (
                     id integer primary key,
@@ -143,11 +1133,8 @@ mod tests {
                     year integer not null
                 );
             "#,
-            (),
         )?;
 
-        let ctx = Arc::new(TestContext { conn });
-
         let movie: Movie = persist(ctx.clone()).await?;
 
         assert_eq!(
@@ -178,9 +1165,7 @@ mod tests {
 
     #[tokio::test]
     async fn persist_works_with_overrides() -> Result<(), Box<dyn std::error::Error>> {
-        let conn = Connection::open(":memory:")?;
-
-        conn.execute(
+        let ctx = TestContext::shared(
             r#"
                 create table if not exists movie (
                     id integer primary key,
@@ -188,11 +1173,8 @@ mod tests {
                     year integer not null
                 );
             "#,
-            (),
         )?;
 
-        let ctx = Arc::new(TestContext { conn });
-
         let movie: Movie = persist_with(ctx.clone(), {
             let mut movie = MovieBuilder::default();
             movie.title("The Social Network".into());
@@ -358,35 +1340,30 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn persist_works_with_an_entity_hierarchy() -> Result<(), Box<dyn std::error::Error>> {
-        let conn = Connection::open(":memory:")?;
-
-        conn.pragma_update(None, "foreign_keys", "on")?;
-
-        conn.execute_batch(
-            r#"
-                create table if not exists author (
-                    id integer primary key,
-                    name text not null unique
-                );
+    const HIERARCHY_SCHEMA: &str = r#"
+        pragma foreign_keys = on;
 
-                create table if not exists post (
-                    id integer primary key,
-                    author_id integer not null references author (id),
-                    title text not null
-                );
+        create table if not exists author (
+            id integer primary key,
+            name text not null unique
+        );
 
-                create table if not exists comment (
-                    id integer primary key,
-                    post_id integer not null references post (id),
-                    username text not null
-                );
-            "#,
-        )?;
+        create table if not exists post (
+            id integer primary key,
+            author_id integer not null references author (id),
+            title text not null
+        );
 
-        let ctx = Arc::new(TestContext { conn });
+        create table if not exists comment (
+            id integer primary key,
+            post_id integer not null references post (id),
+            username text not null
+        );
+    "#;
 
+    #[tokio::test]
+    async fn persist_works_with_an_entity_hierarchy() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
         let comment: Comment = persist(ctx.clone()).await?;
 
         let persisted_comments = {
@@ -434,4 +1411,1224 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn on_persisted_callbacks_fire_for_every_entity_in_a_graph(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        use std::cell::RefCell;
+
+        // Callbacks are process-wide, so we record into a thread-local to avoid
+        // observing entities persisted by tests running on other threads.
+        thread_local! {
+            static PERSISTED: RefCell<Vec<(usize, &'static str)>> = const { RefCell::new(Vec::new()) };
+        }
+
+        let first =
+            on_persisted(|type_name, _| PERSISTED.with_borrow_mut(|log| log.push((1, type_name))));
+        let second =
+            on_persisted(|type_name, _| PERSISTED.with_borrow_mut(|log| log.push((2, type_name))));
+
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        let _: Comment = persist(ctx.clone()).await?;
+
+        let persisted = PERSISTED.with_borrow(|log| log.clone());
+
+        assert_eq!(
+            persisted,
+            vec![
+                (1, std::any::type_name::<Author>()),
+                (2, std::any::type_name::<Author>()),
+                (1, std::any::type_name::<Post>()),
+                (2, std::any::type_name::<Post>()),
+                (1, std::any::type_name::<Comment>()),
+                (2, std::any::type_name::<Comment>()),
+            ]
+        );
+
+        drop(first);
+        drop(second);
+
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        let _: Comment = persist(ctx.clone()).await?;
+
+        assert_eq!(
+            PERSISTED.with_borrow(|log| log.len()),
+            persisted.len(),
+            "dropped callbacks should no longer fire"
+        );
+
+        Ok(())
+    }
+
+    #[derive(Debug, Builder, PartialEq, Eq)]
+    struct Tag {
+        pub name: Cow<'static, str>,
+        pub description: Cow<'static, str>,
+    }
+
+    static DEFAULT_TAG_DESCRIPTION: &str = "Posts about the Rust programming language.";
+
+    impl Manifest for Tag {
+        type Context = ();
+        type Overrides = TagBuilder;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    name: overrides.name.unwrap_or(Cow::Borrowed("rust")),
+                    description: overrides
+                        .description
+                        .unwrap_or(Cow::Borrowed(DEFAULT_TAG_DESCRIPTION)),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Tag {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, tag: Self) -> Result<Self, Self::Err> {
+            Ok(tag)
+        }
+    }
+
+    #[tokio::test]
+    async fn cow_defaults_are_borrowed_instead_of_allocated(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let tags = (0..1_000).map(|_| manifest::<Tag>()).collect::<Vec<_>>();
+
+        // Every default points at the same static string rather than a copy of it.
+        assert!(tags.iter().all(|tag| matches!(
+            &tag.description,
+            Cow::Borrowed(description) if description.as_ptr() == DEFAULT_TAG_DESCRIPTION.as_ptr()
+        )));
+
+        let tag: Tag = persist_with(Arc::new(()), {
+            let mut tag = TagBuilder::default();
+            tag.name(Cow::Owned(format!("rust-{}", 2024)));
+            tag
+        })
+        .await?;
+
+        assert!(matches!(tag.name, Cow::Owned(_)));
+        assert!(matches!(tag.description, Cow::Borrowed(_)));
+        assert_eq!(tag.name, "rust-2024");
+
+        Ok(())
+    }
+
+    /// Overrides that hold the state of a seeded RNG, so they have no sensible `Default`.
+    struct DiceRollOverrides {
+        state: Cell<u64>,
+        pub sides: Option<u64>,
+    }
+
+    impl DiceRollOverrides {
+        fn from_seed(seed: u64) -> Self {
+            Self {
+                state: Cell::new(seed),
+                sides: None,
+            }
+        }
+
+        fn next_u64(&self) -> u64 {
+            // A linear congruential generator, which is plenty for picking test data.
+            let state = self
+                .state
+                .get()
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            self.state.set(state);
+
+            state >> 33
+        }
+    }
+
+    impl InitialOverrides for DiceRollOverrides {
+        fn initial() -> Self {
+            Self::from_seed(42)
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct DiceRoll {
+        pub sides: u64,
+        pub value: u64,
+    }
+
+    impl Manifest for DiceRoll {
+        type Context = ();
+        type Overrides = DiceRollOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let sides = overrides.sides.unwrap_or(6);
+
+            (
+                Self {
+                    sides,
+                    value: overrides.next_u64() % sides + 1,
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for DiceRoll {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, roll: Self) -> Result<Self, Self::Err> {
+            Ok(roll)
+        }
+    }
+
+    #[tokio::test]
+    async fn overrides_without_a_default_use_their_initial_overrides(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let roll: DiceRoll = manifest();
+        assert_eq!(roll, manifest_with(DiceRollOverrides::from_seed(42)));
+        assert!((1..=6).contains(&roll.value));
+
+        let roll: DiceRoll = persist(Arc::new(())).await?;
+        assert_eq!(roll, manifest_with(DiceRollOverrides::from_seed(42)));
+
+        let roll: DiceRoll = manifest_with(DiceRollOverrides {
+            sides: Some(20),
+            ..DiceRollOverrides::from_seed(7)
+        });
+        assert_eq!(roll.sides, 20);
+        assert!((1..=20).contains(&roll.value));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn persist_tuple_persists_every_entity() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        ctx.conn.execute(
+            r#"
+                create table if not exists movie (
+                    id integer primary key,
+                    title text not null unique,
+                    year integer not null
+                );
+            "#,
+            (),
+        )?;
+
+        let (movie, author) = persist_tuple::<(Movie, Author)>(ctx.clone()).await?;
+
+        let persisted_movie = ctx
+            .conn
+            .query_row("select title, year from movie", [], |row| {
+                Ok(Movie {
+                    title: row.get(0)?,
+                    year: row.get(1)?,
+                })
+            })?;
+        let persisted_author = ctx
+            .conn
+            .query_row("select id, name from author", [], |row| {
+                Ok(Author {
+                    id: AuthorId(row.get(0)?),
+                    name: row.get(1)?,
+                })
+            })?;
+
+        assert_eq!(movie, persisted_movie);
+        assert_eq!(author, persisted_author);
+
+        Ok(())
+    }
+
+    struct PublishedPost {
+        pub author: Author,
+        pub post: Post,
+        pub comments: Vec<Comment>,
+    }
+
+    impl Scenario for PublishedPost {
+        type Context = TestContext;
+        type Err = MaligniusError<rusqlite::Error>;
+
+        async fn build(ctx: Arc<Self::Context>) -> Result<Self, Self::Err> {
+            let author: Author = persist(ctx.clone()).await?;
+            let post: Post = persist_with(ctx.clone(), {
+                let mut post = PostBuilder::default();
+                post.author_id(author.id);
+                post
+            })
+            .await?;
+
+            let mut comments = Vec::new();
+            for id in 1..=3 {
+                comments.push(
+                    persist_with(ctx.clone(), {
+                        let mut comment = CommentBuilder::default();
+                        comment.id(CommentId(id)).post_id(post.id);
+                        comment
+                    })
+                    .await?,
+                );
+            }
+
+            Ok(Self {
+                author,
+                post,
+                comments,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn build_scenario_returns_the_entities_it_created(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        let scenario: PublishedPost = build_scenario(ctx.clone()).await?;
+
+        assert_eq!(scenario.post.author_id, scenario.author.id);
+        assert_eq!(
+            scenario
+                .comments
+                .iter()
+                .map(|comment| (comment.id, comment.post_id))
+                .collect::<Vec<_>>(),
+            vec![
+                (CommentId(1), scenario.post.id),
+                (CommentId(2), scenario.post.id),
+                (CommentId(3), scenario.post.id),
+            ]
+        );
+
+        let comment_count = ctx
+            .conn
+            .query_row("select count(*) from comment", [], |row| {
+                row.get::<_, u32>(0)
+            })?;
+        assert_eq!(comment_count, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn persist_with_order_records_the_whole_graph() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        let (_, order) =
+            persist_with_order::<Comment>(ctx.clone(), CommentBuilder::default()).await?;
+
+        assert_eq!(
+            order,
+            vec![
+                std::any::type_name::<Author>(),
+                std::any::type_name::<Post>(),
+                std::any::type_name::<Comment>(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// A post whose author is wired up through an [`Edge`] rather than a plain association.
+    #[derive(Debug)]
+    struct EdgePost {
+        pub id: PostId,
+        pub author_id: AuthorId,
+    }
+
+    impl Manifest for EdgePost {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+
+            let author_id = edge::<EdgePost, Author, _>(&mut associations, |author| author.id);
+
+            (
+                Self {
+                    id: PostId(1),
+                    author_id,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for EdgePost {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "
+                    insert into post (id, author_id, title) values ($1, $2, 'Edge Post')
+                ",
+                params![post.id.0, post.author_id.0],
+            )?;
+
+            Ok(post)
+        }
+    }
+
+    #[tokio::test]
+    async fn edge_wires_the_child_key_into_the_parent() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        let post: EdgePost = persist(ctx.clone()).await?;
+
+        let author_ids = {
+            let mut stmt = ctx.conn.prepare("select id from author")?;
+            let author_ids = stmt
+                .query_map([], |row| Ok(AuthorId(row.get(0)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            author_ids
+        };
+
+        assert_eq!(author_ids, vec![post.author_id]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn persist_many_with_persists_every_entity() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        let authors: Vec<Author> = persist_many_with(ctx.clone(), 3, |index| {
+            let mut author = AuthorBuilder::default();
+            author.id(AuthorId(index as u32 + 1));
+            author.name(format!("Author {}", index + 1));
+            author
+        })
+        .await?;
+
+        assert_eq!(
+            authors.iter().map(|author| author.id).collect::<Vec<_>>(),
+            [AuthorId(1), AuthorId(2), AuthorId(3)]
+        );
+
+        let count: usize = ctx
+            .conn
+            .query_row("select count(*) from author", [], |row| row.get(0))?;
+        assert_eq!(count, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn associations_can_be_inspected_without_persisting_them() {
+        let (_, associations) = Post::manifest(PostBuilder::default());
+
+        assert!(!associations.is_empty());
+        assert_eq!(associations.len(), 1);
+        assert_eq!(associations.entity_types(), vec![TypeId::of::<Author>()]);
+    }
+
+    #[tokio::test]
+    async fn association_errors_chain_to_the_underlying_error(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Without an `author` table the `Author` association will fail to persist.
+        let ctx = TestContext::shared("")?;
+
+        let (_, associations) = Post::manifest(PostBuilder::default());
+
+        let mut errors = Vec::new();
+        for association in associations.associations {
+            if let Err(err) = (association.persist)(&ctx).await {
+                errors.push(err);
+            }
+        }
+
+        assert_eq!(errors.len(), 1);
+
+        let err = errors.remove(0);
+        let association_err = err
+            .downcast_ref::<AssociationError>()
+            .expect("expected an AssociationError");
+
+        assert_eq!(
+            association_err.entity_type(),
+            std::any::type_name::<Author>()
+        );
+
+        let source = err.source().expect("expected a source error");
+
+        assert!(source.downcast_ref::<rusqlite::Error>().is_some());
+
+        Ok(())
+    }
+
+    /// A post whose author is always "Ted Chiang".
+    #[derive(Debug)]
+    struct FeaturedPost {
+        pub author_id: AuthorId,
+        pub author_name: String,
+    }
+
+    impl Manifest for FeaturedPost {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author: Author = association_with(&mut associations, {
+                let mut author = AuthorBuilder::default();
+                author.id(AuthorId(7)).name("Ted Chiang".into());
+                author
+            });
+
+            (
+                Self {
+                    author_id: author.id,
+                    author_name: author.name,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for FeaturedPost {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into post (id, author_id, title) values (1, $1, 'Featured')",
+                params![post.author_id.0],
+            )?;
+
+            Ok(post)
+        }
+    }
+
+    #[tokio::test]
+    async fn association_with_persists_the_overridden_association(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        let post: FeaturedPost = persist(ctx.clone()).await?;
+
+        assert_eq!(post.author_id, AuthorId(7));
+        assert_eq!(post.author_name, "Ted Chiang");
+
+        let (id, name): (u32, String) =
+            ctx.conn
+                .query_row("select id, name from author", [], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?;
+        assert_eq!((id, name.as_str()), (7, "Ted Chiang"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn association_errors_are_returned_instead_of_panicking(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Without an `author` table the `Author` association will fail to persist.
+        let ctx = TestContext::shared("")?;
+
+        let err = persist::<Post>(ctx.clone()).await.unwrap_err();
+
+        let MaligniusError::Association(err) = err else {
+            panic!("expected an association error, but got {err:?}");
+        };
+        let association_err = err
+            .downcast_ref::<AssociationError>()
+            .expect("expected an AssociationError");
+
+        assert_eq!(
+            association_err.entity_type(),
+            std::any::type_name::<Author>()
+        );
+        assert!(err
+            .source()
+            .is_some_and(|source| source.downcast_ref::<rusqlite::Error>().is_some()));
+
+        Ok(())
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum VehicleKind {
+        Car,
+        Truck,
+    }
+
+    #[derive(Debug, Default)]
+    struct VehicleOverrides {
+        pub id: Option<u32>,
+        pub kind: Option<VehicleKind>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Vehicle {
+        Car { id: u32, seats: u32 },
+        Truck { id: u32, payload: u32 },
+    }
+
+    impl Manifest for Vehicle {
+        type Context = TestContext;
+        type Overrides = VehicleOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let id = overrides.id.unwrap_or(1);
+
+            let vehicle = match overrides.kind.unwrap_or(VehicleKind::Car) {
+                VehicleKind::Car => Self::Car { id, seats: 5 },
+                VehicleKind::Truck => Self::Truck { id, payload: 2000 },
+            };
+
+            (vehicle, Associations::new())
+        }
+    }
+
+    impl Persist for Vehicle {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, vehicle: Self) -> Result<Self, Self::Err> {
+            match &vehicle {
+                Vehicle::Car { id, seats } => ctx.conn.execute(
+                    "
+                        insert into vehicle (id, kind, seats) values ($1, 'car', $2)
+                    ",
+                    params![id, seats],
+                )?,
+                Vehicle::Truck { id, payload } => ctx.conn.execute(
+                    "
+                        insert into vehicle (id, kind, payload) values ($1, 'truck', $2)
+                    ",
+                    params![id, payload],
+                )?,
+            };
+
+            Ok(vehicle)
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_works_with_a_discriminated_entity() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                create table if not exists vehicle (
+                    id integer primary key,
+                    kind text not null,
+                    seats integer,
+                    payload integer
+                );
+            "#,
+        )?;
+
+        let car: Vehicle = persist(ctx.clone()).await?;
+        let truck: Vehicle = persist_with(
+            ctx.clone(),
+            VehicleOverrides {
+                id: Some(2),
+                kind: Some(VehicleKind::Truck),
+            },
+        )
+        .await?;
+
+        assert_eq!(car, Vehicle::Car { id: 1, seats: 5 });
+        assert_eq!(
+            truck,
+            Vehicle::Truck {
+                id: 2,
+                payload: 2000
+            }
+        );
+
+        let persisted_vehicles = {
+            let mut stmt = ctx
+                .conn
+                .prepare("select id, kind, seats, payload from vehicle order by id")?;
+            let persisted_vehicles = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, u32>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<u32>>(2)?,
+                        row.get::<_, Option<u32>>(3)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            persisted_vehicles
+        };
+
+        assert_eq!(
+            persisted_vehicles,
+            vec![
+                (1, "car".to_string(), Some(5), None),
+                (2, "truck".to_string(), None, Some(2000)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// A comment whose post is registered through [`lazy_association`].
+    #[derive(Debug, Builder, PartialEq, Eq)]
+    struct LazyComment {
+        pub id: CommentId,
+        pub post_id: PostId,
+    }
+
+    impl Manifest for LazyComment {
+        type Context = TestContext;
+        type Overrides = LazyCommentBuilder;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+
+            let post_id =
+                lazy_association::<Post, _>(&mut associations, overrides.post_id, |post| post.id);
+
+            (
+                Self {
+                    id: overrides.id.unwrap_or(CommentId(1)),
+                    post_id,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for LazyComment {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "
+                    insert into comment (id, post_id, username) values ($1, $2, 'lazy')
+                ",
+                params![comment.id.0, comment.post_id.0],
+            )?;
+
+            Ok(comment)
+        }
+    }
+
+    #[tokio::test]
+    async fn overriding_a_foreign_key_does_not_create_the_association(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        ctx.conn.execute_batch(
+            "
+                insert into author (id, name) values (1, 'Existing Author');
+                insert into post (id, author_id, title) values (7, 1, 'Existing Post');
+            ",
+        )?;
+
+        let overrides = || {
+            let mut comment = LazyCommentBuilder::default();
+            comment.post_id(PostId(7));
+            comment
+        };
+
+        let (_, associations) = LazyComment::manifest(overrides());
+
+        assert!(associations.associations.is_empty());
+
+        let comment: LazyComment = persist_with(ctx.clone(), overrides()).await?;
+
+        assert_eq!(comment.post_id, PostId(7));
+
+        let post_count: u32 = ctx
+            .conn
+            .query_row("select count(*) from post", [], |row| row.get(0))?;
+        let author_count: u32 = ctx
+            .conn
+            .query_row("select count(*) from author", [], |row| row.get(0))?;
+
+        assert_eq!(post_count, 1);
+        assert_eq!(author_count, 1);
+
+        Ok(())
+    }
+
+    #[derive(Debug, Builder, PartialEq, Eq, Clone)]
+    struct Address {
+        pub street: String,
+        pub city: String,
+    }
+
+    impl Manifest for Address {
+        type Context = TestContext;
+        type Overrides = AddressBuilder;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    street: overrides.street.unwrap_or("1 Main St".into()),
+                    city: overrides.city.unwrap_or("Springfield".into()),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[derive(Debug, Builder, PartialEq, Eq)]
+    struct Order {
+        pub id: u32,
+        pub shipping_address: Address,
+    }
+
+    impl Manifest for Order {
+        type Context = TestContext;
+        type Overrides = OrderBuilder;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: overrides.id.unwrap_or(1),
+                    shipping_address: overrides.shipping_address.unwrap_or_else(embed::<Address>),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[test]
+    fn embed_manifests_a_value_object_without_registering_it() {
+        let (order, associations) = Order::manifest(OrderBuilder::default());
+
+        assert!(associations.associations.is_empty());
+        assert_eq!(
+            order,
+            Order {
+                id: 1,
+                shipping_address: Address {
+                    street: "1 Main St".into(),
+                    city: "Springfield".into()
+                }
+            }
+        );
+
+        let order: Order = manifest_with({
+            let mut order = OrderBuilder::default();
+            order.shipping_address(embed_with({
+                let mut address = AddressBuilder::default();
+                address.city("Shelbyville".into());
+                address
+            }));
+            order
+        });
+
+        assert_eq!(
+            order.shipping_address,
+            Address {
+                street: "1 Main St".into(),
+                city: "Shelbyville".into()
+            }
+        );
+    }
+
+    #[derive(Debug, Builder, PartialEq, Eq)]
+    struct User {
+        pub id: u32,
+        pub email: String,
+        pub deleted_at: Option<i64>,
+    }
+
+    impl Manifest for User {
+        type Context = TestContext;
+        type Overrides = UserBuilder;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: overrides.id.unwrap_or(1),
+                    email: overrides.email.unwrap_or("user1@example.com".into()),
+                    deleted_at: overrides.deleted_at.unwrap_or(None),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl ManifestVariant for User {
+        fn variant(name: &str) -> Option<Self::Overrides> {
+            match name {
+                "deleted" => Some({
+                    let mut user = UserBuilder::default();
+                    user.deleted_at(Some(1_700_000_000));
+                    user
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    impl Persist for User {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, user: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "
+                    insert into user (id, email, deleted_at) values ($1, $2, $3)
+                ",
+                params![user.id, user.email, user.deleted_at],
+            )?;
+
+            Ok(user)
+        }
+    }
+
+    #[tokio::test]
+    async fn manifest_variant_builds_a_soft_deleted_entity(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                create table if not exists user (
+                    id integer primary key,
+                    email text not null,
+                    deleted_at integer
+                );
+            "#,
+        )?;
+
+        let live: User = manifest();
+        let deleted: User = manifest_variant("deleted")?;
+
+        assert_eq!(live.deleted_at, None);
+        assert_eq!(deleted.deleted_at, Some(1_700_000_000));
+
+        let live: User = persist(ctx.clone()).await?;
+        let deleted: User = persist_with(ctx.clone(), {
+            let mut user = User::variant("deleted").unwrap();
+            user.id(2);
+            user
+        })
+        .await?;
+
+        let deleted_at = |id: u32| {
+            ctx.conn
+                .query_row("select deleted_at from user where id = $1", [id], |row| {
+                    row.get::<_, Option<i64>>(0)
+                })
+        };
+
+        assert_eq!(deleted_at(live.id)?, None);
+        assert_eq!(deleted_at(deleted.id)?, Some(1_700_000_000));
+
+        assert_eq!(
+            manifest_variant::<User>("archived"),
+            Err(UnknownVariantError {
+                entity_type: std::any::type_name::<User>(),
+                variant: "archived".into()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[derive(Debug, Builder, PartialEq, Eq)]
+    struct Track {
+        pub title: String,
+        #[builder(setter(skip))]
+        pub slug: Option<String>,
+    }
+
+    impl Manifest for Track {
+        type Context = TestContext;
+        type Overrides = TrackBuilder;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    title: overrides.title.unwrap_or("Hey Jude".into()),
+                    slug: None,
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl PersistReturning for Track {
+        type Err = rusqlite::Error;
+        type Returning = String;
+
+        async fn insert_returning(
+            ctx: &Self::Context,
+            track: &Self,
+        ) -> Result<Self::Returning, Self::Err> {
+            ctx.conn.query_row(
+                "
+                    insert into track (title) values ($1) returning slug
+                ",
+                params![track.title],
+                |row| row.get(0),
+            )
+        }
+
+        fn apply_returning(track: Self, slug: Self::Returning) -> Self {
+            Self {
+                slug: Some(slug),
+                ..track
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_captures_returned_columns() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                create table if not exists track (
+                    id integer primary key,
+                    title text not null,
+                    slug text generated always as (lower(replace(title, ' ', '-'))) stored
+                );
+            "#,
+        )?;
+
+        let track: Track = persist(ctx.clone()).await?;
+
+        assert_eq!(
+            track,
+            Track {
+                title: "Hey Jude".into(),
+                slug: Some("hey-jude".into())
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn persist_counter_counts_every_entity_in_its_scope(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(HIERARCHY_SCHEMA)?;
+
+        let outer = PersistCounter::start();
+
+        let _: Author = persist_with(ctx.clone(), {
+            let mut author = AuthorBuilder::default();
+            author.id(AuthorId(2));
+            author.name("Author 2".into());
+            author
+        })
+        .await?;
+
+        {
+            let inner = PersistCounter::start();
+
+            let _: Comment = persist(ctx.clone()).await?;
+
+            assert_eq!(inner.total(), 3);
+        }
+
+        let _: Movie = manifest();
+
+        assert_eq!(outer.total(), 4);
+
+        Ok(())
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    struct PublisherId(u32);
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Publisher {
+        pub id: PublisherId,
+    }
+
+    impl Manifest for Publisher {
+        type Context = Connection;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { id: PublisherId(1) }, Associations::new())
+        }
+    }
+
+    impl Persist for Publisher {
+        type Err = rusqlite::Error;
+
+        async fn persist(conn: &Self::Context, publisher: Self) -> Result<Self, Self::Err> {
+            conn.execute(
+                "
+                    insert into publisher (id) values ($1)
+                ",
+                params![publisher.id.0],
+            )?;
+
+            Ok(publisher)
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Book {
+        pub publisher_id: PublisherId,
+        pub title: String,
+    }
+
+    impl Manifest for Book {
+        type Context = Connection;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+
+            let publisher_id = association::<Publisher>(&mut associations).id;
+
+            (
+                Self {
+                    publisher_id,
+                    title: "Dune".into(),
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Book {
+        type Err = rusqlite::Error;
+
+        async fn persist(conn: &Self::Context, book: Self) -> Result<Self, Self::Err> {
+            conn.execute(
+                "
+                    insert into book (publisher_id, title) values ($1, $2)
+                ",
+                params![book.publisher_id.0, book.title],
+            )?;
+
+            Ok(book)
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_in_works_with_a_borrowed_transaction() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mut conn = Connection::open(":memory:")?;
+
+        conn.execute_batch(
+            r#"
+                create table if not exists publisher (
+                    id integer primary key
+                );
+
+                create table if not exists book (
+                    id integer primary key,
+                    publisher_id integer not null references publisher (id),
+                    title text not null
+                );
+            "#,
+        )?;
+
+        let count = |conn: &Connection, table: &str| {
+            conn.query_row(&format!("select count(*) from {table}"), [], |row| {
+                row.get::<_, u32>(0)
+            })
+        };
+
+        {
+            let tx = conn.transaction()?;
+
+            let book = persist_in::<Book>(&tx).await?;
+
+            assert_eq!(book.publisher_id, PublisherId(1));
+            assert_eq!(count(&tx, "publisher")?, 1);
+            assert_eq!(count(&tx, "book")?, 1);
+
+            tx.rollback()?;
+        }
+
+        assert_eq!(count(&conn, "publisher")?, 0);
+        assert_eq!(count(&conn, "book")?, 0);
+
+        Ok(())
+    }
+
+    #[derive(Debug, Builder, PartialEq, Eq)]
+    struct Announcement {
+        pub message: String,
+        pub published: bool,
+    }
+
+    impl Manifest for Announcement {
+        type Context = TestContext;
+        type Overrides = AnnouncementBuilder;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    message: overrides.message.unwrap_or("Hello, world!".into()),
+                    published: overrides.published.unwrap_or(true),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Announcement {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, announcement: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "
+                    insert into announcement (message) values ($1)
+                ",
+                params![announcement.message],
+            )?;
+
+            Ok(announcement)
+        }
+
+        fn before_persist(_ctx: &Self::Context, announcement: Self) -> PersistDecision<Self> {
+            if announcement.published {
+                PersistDecision::Proceed(announcement)
+            } else {
+                PersistDecision::Skip(announcement)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn before_persist_can_skip_the_insert() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                create table if not exists announcement (
+                    id integer primary key,
+                    message text not null
+                );
+            "#,
+        )?;
+
+        let counter = PersistCounter::start();
+
+        let draft: Announcement = persist_with(ctx.clone(), {
+            let mut announcement = AnnouncementBuilder::default();
+            announcement.published(false);
+            announcement
+        })
+        .await?;
+
+        assert_eq!(
+            draft,
+            Announcement {
+                message: "Hello, world!".into(),
+                published: false
+            }
+        );
+
+        let announcement_count = || {
+            ctx.conn
+                .query_row("select count(*) from announcement", [], |row| {
+                    row.get::<_, u32>(0)
+                })
+        };
+
+        assert_eq!(announcement_count()?, 0);
+        assert_eq!(counter.total(), 0);
+
+        let _: Announcement = persist(ctx.clone()).await?;
+
+        assert_eq!(announcement_count()?, 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,179 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+/// An entity that can be stored in a [`MemoryStore`].
+pub trait MemoryEntity: Clone + Send + 'static {
+    type Id: Eq + Hash + Clone + Send + 'static;
+
+    /// Returns the key the entity is stored under.
+    fn id(&self) -> Self::Id;
+}
+
+/// An in-memory store that can be used as a [`Persist`](crate::Persist) context.
+///
+/// Entities are kept in a separate map per type, keyed by [`MemoryEntity::id`].
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::{persist, Associations, Manifest, MemoryEntity, MemoryStore, Persist};
+/// # #[derive(Clone)]
+/// # struct Movie {
+/// #     id: u32,
+/// # }
+/// # impl MemoryEntity for Movie {
+/// #     type Id = u32;
+/// #     fn id(&self) -> u32 {
+/// #         self.id
+/// #     }
+/// # }
+/// # impl Manifest for Movie {
+/// #     type Context = MemoryStore;
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<MemoryStore>) {
+/// #         (Self { id: 1 }, Associations::new())
+/// #     }
+/// # }
+/// impl Persist for Movie {
+///     type Err = std::convert::Infallible;
+///
+///     async fn persist(ctx: &Self::Context, movie: Self) -> Result<Self, Self::Err> {
+///         Ok(ctx.insert(movie))
+///     }
+/// }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(MemoryStore::new());
+/// # let movie: Movie = persist(ctx.clone()).await?;
+/// # assert!(ctx.get::<Movie>(&movie.id).is_some());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct MemoryStore {
+    tables: Mutex<HashMap<TypeId, Box<dyn Any + Send>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the entity, replacing any existing entity with the same id.
+    pub fn insert<T: MemoryEntity>(&self, entity: T) -> T {
+        self.with_table::<T, _>(|table| {
+            table.insert(entity.id(), entity.clone());
+        });
+
+        entity
+    }
+
+    /// Returns the entity with the given id.
+    pub fn get<T: MemoryEntity>(&self, id: &T::Id) -> Option<T> {
+        self.with_table::<T, _>(|table| table.get(id).cloned())
+    }
+
+    /// Returns all of the stored entities of type `T`, in no particular order.
+    pub fn all<T: MemoryEntity>(&self) -> Vec<T> {
+        self.with_table::<T, _>(|table| table.values().cloned().collect())
+    }
+
+    /// Returns the number of stored entities of type `T`.
+    pub fn count<T: MemoryEntity>(&self) -> usize {
+        self.with_table::<T, _>(|table| table.len())
+    }
+
+    fn with_table<T: MemoryEntity, R>(&self, f: impl FnOnce(&mut HashMap<T::Id, T>) -> R) -> R {
+        let mut tables = self.tables.lock().unwrap();
+        let table = tables
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::<HashMap<T::Id, T>>::default());
+
+        f(table.downcast_mut().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    use crate::{persist, persist_with, Associations, Manifest, Persist};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct MovieOverrides {
+        pub id: Option<u32>,
+        pub title: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    struct Movie {
+        pub id: u32,
+        pub title: String,
+    }
+
+    impl MemoryEntity for Movie {
+        type Id = u32;
+
+        fn id(&self) -> Self::Id {
+            self.id
+        }
+    }
+
+    impl Manifest for Movie {
+        type Context = MemoryStore;
+        type Overrides = MovieOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: overrides.id.unwrap_or(1),
+                    title: overrides.title.unwrap_or("Inception".into()),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Movie {
+        type Err = Infallible;
+
+        async fn persist(ctx: &Self::Context, movie: Self) -> Result<Self, Self::Err> {
+            Ok(ctx.insert(movie))
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_works_with_a_memory_store() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = Arc::new(MemoryStore::new());
+
+        let inception: Movie = persist(ctx.clone()).await?;
+        let social_network: Movie = persist_with(
+            ctx.clone(),
+            MovieOverrides {
+                id: Some(2),
+                title: Some("The Social Network".into()),
+            },
+        )
+        .await?;
+
+        assert_eq!(ctx.count::<Movie>(), 2);
+        assert_eq!(ctx.get::<Movie>(&1), Some(inception));
+        assert_eq!(ctx.get::<Movie>(&2), Some(social_network));
+        assert_eq!(ctx.get::<Movie>(&3), None);
+
+        let mut titles = ctx
+            .all::<Movie>()
+            .into_iter()
+            .map(|movie| movie.title)
+            .collect::<Vec<_>>();
+        titles.sort();
+
+        assert_eq!(titles, vec!["Inception", "The Social Network"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+const FIRST_NAMES: &[&str] = &[
+    "Ada",
+    "Alan",
+    "Barbara",
+    "Claude",
+    "Donald",
+    "Edsger",
+    "Frances",
+    "Grace",
+    "Hedy",
+    "Ivan",
+    "Joan",
+    "Ken",
+    "Katherine",
+    "Linus",
+    "Margaret",
+    "Niklaus",
+    "Radia",
+    "Shafi",
+    "Tim",
+    "Yukihiro",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Allen",
+    "Backus",
+    "Cerf",
+    "Dijkstra",
+    "Engelbart",
+    "Goldwasser",
+    "Hamilton",
+    "Hopper",
+    "Johnson",
+    "Kay",
+    "Knuth",
+    "Lamarr",
+    "Liskov",
+    "Lovelace",
+    "Perlman",
+    "Ritchie",
+    "Shannon",
+    "Sutherland",
+    "Thompson",
+    "Turing",
+];
+
+const WORDS: &[&str] = &[
+    "amber", "anchor", "breeze", "cedar", "comet", "delta", "ember", "falcon", "garnet", "harbor",
+    "island", "juniper", "lantern", "meadow", "nectar", "orbit", "pebble", "quartz", "river",
+    "summit", "thicket", "umber", "velvet", "willow",
+];
+
+/// A deterministic generator of plausible first names, last names, and words.
+///
+/// Values are picked from small word lists bundled with the crate. Each value is determined
+/// entirely by the seed and the index it is requested at, so the same seed always yields the same
+/// values. Because the methods take `&self`, a generator can be used inside a
+/// [`Sequence`](crate::Sequence) producer:
+///
+/// ```
+/// # use malignius::{NameGenerator, Sequence};
+/// let names = NameGenerator::new(42);
+/// let mut authors = Sequence::new(move |n| names.full_name(n));
+/// # assert_eq!(authors.next(), NameGenerator::new(42).full_name(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NameGenerator {
+    seed: u64,
+}
+
+impl NameGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Returns the first name at index `n`.
+    pub fn first_name(&self, n: usize) -> &'static str {
+        self.pick(FIRST_NAMES, n, 0)
+    }
+
+    /// Returns the last name at index `n`.
+    pub fn last_name(&self, n: usize) -> &'static str {
+        self.pick(LAST_NAMES, n, 1)
+    }
+
+    /// Returns a first and last name at index `n`, such as `Grace Hopper`.
+    pub fn full_name(&self, n: usize) -> String {
+        format!("{} {}", self.first_name(n), self.last_name(n))
+    }
+
+    /// Returns the word at index `n`.
+    pub fn word(&self, n: usize) -> &'static str {
+        self.pick(WORDS, n, 2)
+    }
+
+    /// Picks a value from `list`, using a different `stream` for each list so that the first and
+    /// last names at the same index aren't correlated.
+    fn pick(&self, list: &'static [&'static str], n: usize, stream: u64) -> &'static str {
+        let hash = splitmix64(self.seed ^ splitmix64((n as u64) << 2 | stream));
+
+        list[(hash % list.len() as u64) as usize]
+    }
+}
+
+/// The SplitMix64 finalizer, which scrambles its input into a well-distributed hash.
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Sequence;
+
+    use super::*;
+
+    #[test]
+    fn names_are_reproducible_for_the_same_seed() {
+        let first = Sequence::new(move |n| NameGenerator::new(42).full_name(n)).take_vec(20);
+        let second = Sequence::new(move |n| NameGenerator::new(42).full_name(n)).take_vec(20);
+        let other = Sequence::new(move |n| NameGenerator::new(7).full_name(n)).take_vec(20);
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn names_come_from_the_embedded_lists() {
+        let names = NameGenerator::new(42);
+
+        for n in 0..100 {
+            assert!(FIRST_NAMES.contains(&names.first_name(n)));
+            assert!(LAST_NAMES.contains(&names.last_name(n)));
+            assert!(WORDS.contains(&names.word(n)));
+            assert_eq!(
+                names.full_name(n),
+                format!("{} {}", names.first_name(n), names.last_name(n))
+            );
+        }
+    }
+}
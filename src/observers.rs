@@ -0,0 +1,523 @@
+use std::any::{type_name, Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::scope::Scoped;
+use crate::{persist_with, MaligniusError, Persist};
+
+type PersistedCallback = Box<dyn Fn(&'static str, &dyn Any) + Send + Sync>;
+
+static PERSISTED_CALLBACKS: RwLock<Vec<(u64, PersistedCallback)>> = RwLock::new(Vec::new());
+
+static NEXT_CALLBACK_ID: AtomicU64 = AtomicU64::new(0);
+
+type AssociationPersistedCallback = Box<dyn Fn(&mut dyn Any, &dyn Any) + Send + Sync>;
+
+type RegisteredAssociationPersistedCallback = (u64, TypeId, AssociationPersistedCallback);
+
+static ASSOCIATION_PERSISTED_CALLBACKS: RwLock<Vec<RegisteredAssociationPersistedCallback>> =
+    RwLock::new(Vec::new());
+
+thread_local! {
+    static PERSIST_COUNTERS: RefCell<Vec<Rc<Cell<usize>>>> = const { RefCell::new(Vec::new()) };
+    static PERSIST_ORDER: RefCell<Option<Rc<RefCell<Vec<&'static str>>>>> = const { RefCell::new(None) };
+}
+
+/// Registers a process-wide callback that is invoked after every entity is persisted.
+///
+/// The callback receives the type name of the entity and the persisted entity itself.
+/// Callbacks run in the order they were registered, until the returned guard is dropped.
+///
+/// Callbacks must not call `on_persisted` themselves.
+///
+/// ```
+/// # use malignius::on_persisted;
+/// let _guard = on_persisted(|type_name, _| println!("persisted {type_name}"));
+/// ```
+pub fn on_persisted(
+    callback: impl Fn(&'static str, &dyn Any) + Send + Sync + 'static,
+) -> CallbackGuard {
+    let id = NEXT_CALLBACK_ID.fetch_add(1, Ordering::Relaxed);
+
+    PERSISTED_CALLBACKS
+        .write()
+        .unwrap()
+        .push((id, Box::new(callback)));
+
+    CallbackGuard {
+        id,
+        unregister: |id| {
+            PERSISTED_CALLBACKS
+                .write()
+                .unwrap()
+                .retain(|(callback_id, _)| *callback_id != id);
+        },
+    }
+}
+
+/// Unregisters a callback when it is dropped.
+///
+/// To keep a callback registered for the rest of the process, pass its guard to
+/// [`std::mem::forget`].
+#[must_use = "the callback is unregistered as soon as the guard is dropped"]
+pub struct CallbackGuard {
+    id: u64,
+    unregister: fn(u64),
+}
+
+impl Drop for CallbackGuard {
+    fn drop(&mut self) {
+        (self.unregister)(self.id);
+    }
+}
+
+/// Registers a process-wide callback that is invoked after each association of a `T` is persisted.
+///
+/// The callback receives the parent entity, before it is persisted, and the persisted association.
+/// This allows reconciling the parent with values generated while persisting the association,
+/// such as database-assigned ids.
+///
+/// The callback stays registered until the returned guard is dropped.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 1 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Post { author_id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let author: Author = association(&mut associations);
+/// #         (Self { author_id: author.id }, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Comment;
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Post = association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let _guard = on_association_persisted::<Post>(|post, child| {
+///     if let Some(author) = child.downcast_ref::<Author>() {
+///         post.author_id = author.id;
+///     }
+/// });
+/// # let post: Post = persist(ctx).await?;
+/// # assert_eq!(post.author_id, 1);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Callbacks must not call `on_association_persisted` themselves.
+pub fn on_association_persisted<T: 'static>(
+    callback: impl Fn(&mut T, &dyn Any) + Send + Sync + 'static,
+) -> CallbackGuard {
+    let id = NEXT_CALLBACK_ID.fetch_add(1, Ordering::Relaxed);
+
+    ASSOCIATION_PERSISTED_CALLBACKS.write().unwrap().push((
+        id,
+        TypeId::of::<T>(),
+        Box::new(move |parent, child| {
+            if let Some(parent) = parent.downcast_mut::<T>() {
+                callback(parent, child);
+            }
+        }),
+    ));
+
+    CallbackGuard {
+        id,
+        unregister: |id| {
+            ASSOCIATION_PERSISTED_CALLBACKS
+                .write()
+                .unwrap()
+                .retain(|(callback_id, _, _)| *callback_id != id);
+        },
+    }
+}
+
+/// Counts the entities persisted on the current thread while it is alive.
+///
+/// Every entity counts, including associations. Counters can be nested, in which case each
+/// of them counts the entities persisted in its scope.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 1 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Post { author_id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let author: Author = association(&mut associations);
+/// #         (Self { author_id: author.id }, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Comment;
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Post = association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let counter = PersistCounter::start();
+/// let _: Comment = persist(ctx).await?;
+/// assert_eq!(counter.total(), 3);
+/// # Ok(())
+/// # }
+/// ```
+pub struct PersistCounter {
+    count: Rc<Cell<usize>>,
+}
+
+impl PersistCounter {
+    /// Starts counting persisted entities.
+    pub fn start() -> Self {
+        let count = Rc::new(Cell::new(0));
+
+        PERSIST_COUNTERS.with_borrow_mut(|counters| counters.push(count.clone()));
+
+        Self { count }
+    }
+
+    /// Returns the number of entities persisted since the counter was started.
+    pub fn total(&self) -> usize {
+        self.count.get()
+    }
+}
+
+impl Drop for PersistCounter {
+    fn drop(&mut self) {
+        PERSIST_COUNTERS.with_borrow_mut(|counters| {
+            counters.retain(|count| !Rc::ptr_eq(count, &self.count));
+        });
+    }
+}
+
+/// Persists an entity, returning it along with the type names of every entity persisted, in the
+/// order they were persisted.
+///
+/// The order covers the whole graph, including nested associations, and ends with the entity
+/// itself.
+///
+/// ```
+/// # use std::any::type_name;
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 1 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Post { author_id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let author: Author = association(&mut associations);
+/// #         (Self { author_id: author.id }, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Comment;
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Post = association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let (comment, order) = persist_with_order::<Comment>(ctx, Default::default()).await?;
+/// assert_eq!(order, vec![type_name::<Author>(), type_name::<Post>(), type_name::<Comment>()]);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_with_order<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+) -> Result<(T, Vec<&'static str>), MaligniusError<T::Err>> {
+    let (entity, order) = persist_recording_order::<T>(ctx, overrides).await;
+
+    Ok((entity?, order))
+}
+
+/// Persists an entity, returning the type names of every entity persisted even if it fails.
+pub(crate) async fn persist_recording_order<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+) -> (Result<T, MaligniusError<T::Err>>, Vec<&'static str>) {
+    let order = Rc::new(RefCell::new(Vec::new()));
+
+    let entity = Scoped::new(
+        &PERSIST_ORDER,
+        order.clone(),
+        persist_with::<T>(ctx, overrides),
+    )
+    .await;
+
+    (entity, order.take())
+}
+
+pub(crate) fn notify_persisted<T: 'static>(entity: &T) {
+    PERSIST_ORDER.with_borrow(|order| {
+        if let Some(order) = order {
+            order.borrow_mut().push(type_name::<T>());
+        }
+    });
+
+    PERSIST_COUNTERS.with_borrow(|counters| {
+        for count in counters {
+            count.set(count.get() + 1);
+        }
+    });
+
+    for (_, callback) in PERSISTED_CALLBACKS.read().unwrap().iter() {
+        callback(type_name::<T>(), entity);
+    }
+}
+
+pub(crate) fn notify_association_persisted<T: 'static>(parent: &mut T, child: &dyn Any) {
+    for (_, parent_type, callback) in ASSOCIATION_PERSISTED_CALLBACKS.read().unwrap().iter() {
+        if *parent_type == TypeId::of::<T>() {
+            callback(parent, child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rusqlite::params;
+
+    use crate::test_support::TestContext;
+    use crate::{association, persist, Associations, Manifest, Persist};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct AuthorOverrides {}
+
+    #[derive(Debug)]
+    struct Author {
+        pub id: Option<i64>,
+        pub name: String,
+    }
+
+    impl Manifest for Author {
+        type Context = TestContext;
+        type Overrides = AuthorOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: None,
+                    name: "Ursula K. Le Guin".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Author {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into author (name) values ($1)",
+                params![author.name],
+            )?;
+
+            Ok(Self {
+                id: Some(ctx.conn.last_insert_rowid()),
+                ..author
+            })
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct BookOverrides {}
+
+    #[derive(Debug)]
+    struct Book {
+        pub author_id: Option<i64>,
+        pub title: String,
+    }
+
+    impl Manifest for Book {
+        type Context = TestContext;
+        type Overrides = BookOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author: Author = association(&mut associations);
+
+            (
+                Self {
+                    // The author's id is only known once it has been persisted.
+                    author_id: author.id,
+                    title: "The Dispossessed".into(),
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Book {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, book: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into book (author_id, title) values ($1, $2)",
+                params![book.author_id, book.title],
+            )?;
+
+            Ok(book)
+        }
+    }
+
+    #[tokio::test]
+    async fn association_persisted_callbacks_can_copy_generated_ids_into_the_parent(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                create table if not exists author (
+                    id integer primary key autoincrement,
+                    name text not null
+                );
+
+                create table if not exists book (
+                    id integer primary key,
+                    author_id integer not null references author (id),
+                    title text not null
+                );
+            "#,
+        )?;
+
+        let guard = on_association_persisted::<Book>(|book, child| {
+            if let Some(author) = child.downcast_ref::<Author>() {
+                book.author_id = author.id;
+            }
+        });
+
+        let book: Book = persist(ctx.clone()).await?;
+
+        let author_id = ctx
+            .conn
+            .query_row("select id from author", [], |row| row.get::<_, i64>(0))?;
+
+        assert_eq!(book.author_id, Some(author_id));
+
+        drop(guard);
+
+        let result = persist::<Book>(ctx.clone()).await;
+        assert!(
+            result.is_err(),
+            "without the callback the book has no author id to insert"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::{persist, MaligniusError, Persist};
+
+type OnceSlot = Arc<futures::lock::Mutex<Option<Box<dyn Any + Send>>>>;
+
+/// The cached entities, by the address of the context they were persisted to and their type.
+static PERSISTED_ONCE: Mutex<Option<HashMap<(usize, TypeId), OnceSlot>>> = Mutex::new(None);
+
+/// Persists an entity at most once per process and context.
+///
+/// The first call persists the entity and caches it. Subsequent calls for the same type and
+/// context return the cached entity without persisting anything. This is intended for reference
+/// data (such as countries or currencies) that is shared by every test using the same database.
+/// A test that persists to a context of its own, such as a fresh in-memory database, gets its
+/// own copy of the entity persisted to it.
+///
+/// Contexts are told apart by their address, so every context passed to this is kept alive for
+/// the rest of the process, rather than being dropped and having its address reused by a context
+/// that doesn't have the entity.
+///
+/// If the first persist fails, the error is returned and the next call will try again.
+pub async fn persist_once<T>(ctx: Arc<T::Context>) -> Result<T, MaligniusError<T::Err>>
+where
+    T: Persist + Clone + Send + 'static,
+{
+    let key = (Arc::as_ptr(&ctx) as *const () as usize, TypeId::of::<T>());
+    let slot = PERSISTED_ONCE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .entry(key)
+        .or_insert_with(|| {
+            std::mem::forget(ctx.clone());
+            OnceSlot::default()
+        })
+        .clone();
+
+    // Concurrent callers wait here until the first persist has finished.
+    let mut slot = slot.lock().await;
+
+    if let Some(entity) = slot.as_ref() {
+        return Ok(entity.downcast_ref::<T>().unwrap().clone());
+    }
+
+    let entity = persist::<T>(ctx).await?;
+    *slot = Some(Box::new(entity.clone()));
+
+    Ok(entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::params;
+
+    use crate::test_support::TestContext;
+    use crate::{Associations, Manifest};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct CountryOverrides {
+        pub code: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    struct Country {
+        pub code: String,
+    }
+
+    impl Manifest for Country {
+        type Context = TestContext;
+        type Overrides = CountryOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    code: overrides.code.unwrap_or("US".into()),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Country {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, country: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "
+                    insert into country (code) values ($1)
+                ",
+                params![country.code],
+            )?;
+
+            Ok(country)
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_once_only_persists_the_first_time() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                create table if not exists country (
+                    code text primary key
+                );
+            "#,
+        )?;
+
+        let first: Country = persist_once(ctx.clone()).await?;
+        let second: Country = persist_once(ctx.clone()).await?;
+
+        assert_eq!(first, second);
+
+        let count: u32 = ctx
+            .conn
+            .query_row("select count(*) from country", [], |row| row.get(0))?;
+
+        assert_eq!(count, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn persist_once_persists_to_every_context() -> Result<(), Box<dyn std::error::Error>> {
+        for _ in 0..3 {
+            let ctx = TestContext::shared("create table country (code text primary key)")?;
+
+            let _: Country = persist_once(ctx.clone()).await?;
+            let _: Country = persist_once(ctx.clone()).await?;
+
+            let count: u32 = ctx
+                .conn
+                .query_row("select count(*) from country", [], |row| row.get(0))?;
+            assert_eq!(count, 1);
+        }
+
+        Ok(())
+    }
+}
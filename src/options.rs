@@ -0,0 +1,574 @@
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::scope::Scoped;
+use crate::AssociationCheck;
+
+thread_local! {
+    static INHERITED_OPTIONS: RefCell<Option<PersistOptions>> = const { RefCell::new(None) };
+}
+
+/// Options that control how an entity and its associations are persisted.
+///
+/// Associations are persisted with the options of the entity that registered them, so
+/// `throttle`, `verify_associations`, `only` and `error_logging` apply to the whole graph.
+/// `max_concurrency` and `concurrent_types` only apply to the associations of the entity being
+/// persisted.
+#[derive(Debug, Clone)]
+pub struct PersistOptions {
+    /// The maximum number of associations that may be persisted at the same time.
+    ///
+    /// This applies to the associations of the entity being persisted. Nested associations are
+    /// persisted one at a time, unless their type is configured with its own
+    /// [`max_concurrency`](crate::PersistConfig::max_concurrency). Defaults to `1`, which
+    /// persists associations sequentially in the order described on
+    /// [`Associations`](crate::Associations).
+    pub max_concurrency: usize,
+
+    /// Whether associations of different types are persisted concurrently.
+    ///
+    /// This applies to the associations of the entity being persisted, and is useful for cutting
+    /// down on round trips to a remote database. Associations of the same type are still
+    /// persisted one at a time, in the order they were registered, in case they depend on each
+    /// other. The entity itself is persisted once all of them have been. When set,
+    /// `max_concurrency` and `throttle` are ignored. Defaults to `false`.
+    pub concurrent_types: bool,
+
+    /// The minimum time between starting to persist one association and the next.
+    ///
+    /// This applies to the associations of every entity in the graph, and is useful for
+    /// avoiding rate limits when persisting to an external service. When `None`, associations
+    /// are persisted as quickly as possible.
+    pub throttle: Option<Duration>,
+
+    /// The tenant to persist the entity and its associations into.
+    ///
+    /// This is exposed to `Persist` implementations through
+    /// [`current_tenant`](crate::current_tenant). When `None`, the tenant of any enclosing
+    /// persist is kept.
+    pub tenant: Option<String>,
+
+    /// Checks that each association was actually persisted before persisting the entity.
+    ///
+    /// This applies to the associations of every entity in the graph. Persisting fails with an
+    /// [`UnverifiedAssociation`](crate::UnverifiedAssociation) naming the first association that
+    /// fails the check, which is clearer than the foreign key violation the entity's insert would
+    /// otherwise run into.
+    pub verify_associations: Option<AssociationCheck>,
+
+    /// The types of the associations that are persisted, or `None` to persist all of them.
+    ///
+    /// This applies to the associations of every entity in the graph. Associations of other
+    /// types, along with their own associations, are left unpersisted. The entity still holds
+    /// the values they were manifested with, so any foreign keys taken from them will only be
+    /// valid if matching rows already exist, such as when they were seeded beforehand.
+    ///
+    /// ```
+    /// # use std::any::TypeId;
+    /// # use std::collections::HashSet;
+    /// # use malignius::PersistOptions;
+    /// # struct Author;
+    /// let options = PersistOptions {
+    ///     only: Some(HashSet::from([TypeId::of::<Author>()])),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub only: Option<HashSet<TypeId>>,
+
+    /// Whether failures to persist the entity or its associations are logged before they are
+    /// returned. Defaults to [`ErrorLogging::Silent`].
+    pub error_logging: ErrorLogging,
+}
+
+impl Default for PersistOptions {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 1,
+            concurrent_types: false,
+            throttle: None,
+            tenant: None,
+            verify_associations: None,
+            only: None,
+            error_logging: ErrorLogging::default(),
+        }
+    }
+}
+
+impl PersistOptions {
+    /// Returns the options that the associations of an entity persisted with these options are
+    /// persisted with.
+    fn inherited(&self) -> Self {
+        Self {
+            max_concurrency: Self::default().max_concurrency,
+            concurrent_types: false,
+            tenant: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Returns the options inherited from the entity whose association is being persisted, if any.
+pub(crate) fn inherited_options() -> Option<PersistOptions> {
+    INHERITED_OPTIONS.with_borrow(|options| options.clone())
+}
+
+/// Runs the future, which persists an association, with the options inherited from the entity
+/// that registered it.
+pub(crate) async fn inheriting_options<F: Future>(
+    options: &PersistOptions,
+    future: F,
+) -> F::Output {
+    Scoped::new(&INHERITED_OPTIONS, options.inherited(), future).await
+}
+
+/// How failures to persist are logged, see [`PersistOptions::error_logging`].
+#[derive(Clone, Default)]
+pub enum ErrorLogging {
+    /// Failures are not logged. This is useful for tests that expect a persist to fail.
+    #[default]
+    Silent,
+    /// Failures are logged to standard error.
+    Log,
+    /// Failures are passed to the given function, such as to forward them to a test's logger.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use malignius::{ErrorLogging, PersistOptions};
+    /// let options = PersistOptions {
+    ///     error_logging: ErrorLogging::Custom(Arc::new(|message| eprintln!("{message}"))),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    Custom(Arc<dyn Fn(&str) + Send + Sync>),
+}
+
+impl fmt::Debug for ErrorLogging {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Silent => write!(f, "Silent"),
+            Self::Log => write!(f, "Log"),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+impl ErrorLogging {
+    pub(crate) fn log(&self, message: &str) {
+        self.log_to(&mut io::stderr(), message);
+    }
+
+    fn log_to(&self, out: &mut impl io::Write, message: &str) {
+        match self {
+            Self::Silent => {}
+            Self::Log => {
+                // Logging is best-effort, so a failure to write is not worth surfacing.
+                let _ = writeln!(out, "malignius: {message}");
+            }
+            Self::Custom(log) => log(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use crate::test_support::shared;
+    use crate::{
+        association, association_with, persist_with_concurrent, persist_with_options, Associations,
+        Manifest, Persist,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct InstrumentedContext {
+        pub in_flight: AtomicUsize,
+        pub max_in_flight: AtomicUsize,
+        pub persisted: AtomicUsize,
+        /// The numbers of the jobs, in the order they were persisted.
+        pub persisted_jobs: Mutex<Vec<usize>>,
+    }
+
+    struct Job {
+        pub number: usize,
+    }
+
+    impl Manifest for Job {
+        type Context = InstrumentedContext;
+        type Overrides = usize;
+
+        fn manifest(number: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { number }, Associations::new())
+        }
+    }
+
+    impl Persist for Job {
+        type Err = Infallible;
+
+        async fn persist(ctx: &Self::Context, job: Self) -> Result<Self, Self::Err> {
+            let in_flight = ctx.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            ctx.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+
+            for _ in 0..3 {
+                tokio::task::yield_now().await;
+            }
+
+            ctx.in_flight.fetch_sub(1, Ordering::SeqCst);
+            ctx.persisted.fetch_add(1, Ordering::SeqCst);
+            ctx.persisted_jobs.lock().unwrap().push(job.number);
+
+            Ok(job)
+        }
+    }
+
+    struct Queue;
+
+    impl Manifest for Queue {
+        type Context = InstrumentedContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+
+            for number in 1..=6 {
+                association_with::<Job>(&mut associations, number);
+            }
+
+            (Self, associations)
+        }
+    }
+
+    impl Persist for Queue {
+        type Err = Infallible;
+
+        async fn persist(_ctx: &Self::Context, queue: Self) -> Result<Self, Self::Err> {
+            Ok(queue)
+        }
+    }
+
+    async fn max_in_flight(max_concurrency: usize) -> usize {
+        let ctx = Arc::new(InstrumentedContext::default());
+
+        persist_with_options::<Queue>(
+            ctx.clone(),
+            (),
+            PersistOptions {
+                max_concurrency,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(ctx.persisted.load(Ordering::SeqCst), 6);
+
+        ctx.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn throttle_spaces_out_the_associations() {
+        let ctx = Arc::new(InstrumentedContext::default());
+        let started_at = std::time::Instant::now();
+
+        persist_with_options::<Queue>(
+            ctx.clone(),
+            (),
+            PersistOptions {
+                throttle: Some(Duration::from_millis(10)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(ctx.persisted.load(Ordering::SeqCst), 6);
+        // The first association starts right away, and each of the other five waits its turn.
+        assert!(started_at.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn silent_error_logging_writes_nothing() {
+        let mut out = Vec::new();
+
+        ErrorLogging::Silent.log_to(&mut out, "failed to persist `Job`");
+
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn error_logging_writes_the_message() {
+        let mut out = Vec::new();
+
+        ErrorLogging::Log.log_to(&mut out, "failed to persist `Job`");
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "malignius: failed to persist `Job`\n"
+        );
+    }
+
+    #[derive(Debug)]
+    struct Broken;
+
+    impl Manifest for Broken {
+        type Context = ();
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self, Associations::new())
+        }
+    }
+
+    impl Persist for Broken {
+        type Err = io::Error;
+
+        async fn persist(_ctx: &Self::Context, _broken: Self) -> Result<Self, Self::Err> {
+            Err(io::Error::other("disk full"))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Holder;
+
+    impl Manifest for Holder {
+        type Context = ();
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            association::<Broken>(&mut associations);
+
+            (Self, associations)
+        }
+    }
+
+    impl Persist for Holder {
+        type Err = io::Error;
+
+        async fn persist(_ctx: &Self::Context, holder: Self) -> Result<Self, Self::Err> {
+            Ok(holder)
+        }
+    }
+
+    /// Persists a `T` that fails, returning what was logged.
+    async fn log_failing_persist<T: Persist<Context = (), Overrides = ()> + 'static>() -> Vec<String>
+    {
+        let logged = Arc::new(Mutex::new(Vec::new()));
+
+        let log = logged.clone();
+        let result = persist_with_options::<T>(
+            Arc::new(()),
+            (),
+            PersistOptions {
+                error_logging: ErrorLogging::Custom(Arc::new(move |message| {
+                    log.lock().unwrap().push(message.to_owned())
+                })),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(result.is_err());
+
+        let logged = logged.lock().unwrap().clone();
+        logged
+    }
+
+    #[tokio::test]
+    async fn failing_persists_are_passed_to_custom_error_logging() {
+        let broken = std::any::type_name::<Broken>();
+
+        assert_eq!(
+            log_failing_persist::<Broken>().await,
+            [format!("failed to persist `{broken}`")]
+        );
+        assert_eq!(
+            log_failing_persist::<Holder>().await,
+            [
+                format!("failed to persist `{broken}`"),
+                format!("failed to persist association `{broken}`: disk full"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn associations_are_persisted_sequentially_by_default() {
+        assert_eq!(
+            max_in_flight(PersistOptions::default().max_concurrency).await,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn max_concurrency_bounds_the_associations_in_flight() {
+        assert_eq!(max_in_flight(2).await, 2);
+        assert_eq!(max_in_flight(4).await, 4);
+    }
+
+    #[derive(Default)]
+    struct RecordingContext {
+        pub persisted: RefCell<Vec<&'static str>>,
+    }
+
+    struct Author;
+
+    impl Manifest for Author {
+        type Context = RecordingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self, Associations::new())
+        }
+    }
+
+    impl Persist for Author {
+        type Err = Infallible;
+
+        async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            ctx.persisted.borrow_mut().push("author");
+            Ok(author)
+        }
+    }
+
+    struct Category;
+
+    impl Manifest for Category {
+        type Context = RecordingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self, Associations::new())
+        }
+    }
+
+    impl Persist for Category {
+        type Err = Infallible;
+
+        async fn persist(ctx: &Self::Context, category: Self) -> Result<Self, Self::Err> {
+            ctx.persisted.borrow_mut().push("category");
+            Ok(category)
+        }
+    }
+
+    struct Post;
+
+    impl Manifest for Post {
+        type Context = RecordingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            association::<Author>(&mut associations);
+            association::<Category>(&mut associations);
+
+            (Self, associations)
+        }
+    }
+
+    impl Persist for Post {
+        type Err = Infallible;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.persisted.borrow_mut().push("post");
+            Ok(post)
+        }
+    }
+
+    #[tokio::test]
+    async fn only_persists_the_allowed_associations() {
+        let ctx = shared(RecordingContext::default());
+
+        persist_with_options::<Post>(
+            ctx.clone(),
+            (),
+            PersistOptions {
+                only: Some(HashSet::from([TypeId::of::<Author>()])),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(*ctx.persisted.borrow(), ["author", "post"]);
+    }
+
+    struct Review;
+
+    impl Manifest for Review {
+        type Context = RecordingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            association::<Post>(&mut associations);
+
+            (Self, associations)
+        }
+    }
+
+    impl Persist for Review {
+        type Err = Infallible;
+
+        async fn persist(ctx: &Self::Context, review: Self) -> Result<Self, Self::Err> {
+            ctx.persisted.borrow_mut().push("review");
+            Ok(review)
+        }
+    }
+
+    #[tokio::test]
+    async fn options_apply_to_nested_associations() {
+        let ctx = shared(RecordingContext::default());
+
+        persist_with_options::<Review>(
+            ctx.clone(),
+            (),
+            PersistOptions {
+                only: Some(HashSet::from([
+                    TypeId::of::<Post>(),
+                    TypeId::of::<Author>(),
+                ])),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(*ctx.persisted.borrow(), ["author", "post", "review"]);
+    }
+
+    #[tokio::test]
+    async fn persist_with_concurrent_persists_every_association() {
+        let ctx = shared(RecordingContext::default());
+
+        let post = persist_with_concurrent::<Post>(ctx.clone(), ()).await;
+
+        assert!(post.is_ok());
+
+        let persisted = ctx.persisted.borrow();
+        assert_eq!(persisted.len(), 3);
+        assert!(persisted.contains(&"author"));
+        assert!(persisted.contains(&"category"));
+        assert_eq!(persisted.last(), Some(&"post"));
+    }
+
+    #[tokio::test]
+    async fn concurrent_types_persists_associations_of_the_same_type_in_order() {
+        let ctx = Arc::new(InstrumentedContext::default());
+
+        persist_with_concurrent::<Queue>(ctx.clone(), ())
+            .await
+            .unwrap();
+
+        assert_eq!(*ctx.persisted_jobs.lock().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(ctx.max_in_flight.load(Ordering::SeqCst), 1);
+    }
+}
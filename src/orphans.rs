@@ -0,0 +1,154 @@
+use std::any::type_name;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::observers::persist_recording_order;
+use crate::{MaligniusError, Persist};
+
+/// The entities that a failed persist left behind.
+///
+/// Without a transaction, the associations that were persisted before the failure remain in the
+/// database. The report lists their type names in the order they were persisted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrphanReport {
+    entity_types: Vec<&'static str>,
+}
+
+impl OrphanReport {
+    /// Returns the type names of the orphaned entities, in the order they were persisted.
+    pub fn entity_types(&self) -> &[&'static str] {
+        &self.entity_types
+    }
+
+    /// Returns whether an entity of type `T` was orphaned.
+    pub fn contains<T: ?Sized>(&self) -> bool {
+        self.entity_types.contains(&type_name::<T>())
+    }
+
+    /// Returns whether nothing was orphaned.
+    pub fn is_empty(&self) -> bool {
+        self.entity_types.is_empty()
+    }
+}
+
+impl fmt::Display for OrphanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entity_type in &self.entity_types {
+            writeln!(f, "{entity_type}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A persist that failed, along with the entities it left behind.
+#[derive(Debug)]
+pub struct FailedPersist<E> {
+    pub error: E,
+    pub orphans: OrphanReport,
+}
+
+impl<E: fmt::Display> fmt::Display for FailedPersist<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} orphaned entities)",
+            self.error,
+            self.orphans.entity_types.len()
+        )
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for FailedPersist<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Persists an entity, reporting the entities that were left behind if it fails.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Author;
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Debug)]
+/// # struct Post;
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Author = association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::fmt::Error;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         Err(std::fmt::Error)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let err = persist_reporting_orphans::<Post>(ctx, Default::default())
+///     .await
+///     .unwrap_err();
+///
+/// assert!(err.orphans.contains::<Author>());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_reporting_orphans<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+) -> Result<T, FailedPersist<MaligniusError<T::Err>>> {
+    let (entity, entity_types) = persist_recording_order::<T>(ctx, overrides).await;
+
+    entity.map_err(|error| FailedPersist {
+        error,
+        orphans: OrphanReport { entity_types },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{Author, Post, TestContext};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn failed_persists_report_the_entities_left_behind(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // There is no `post` table, so persisting the post always fails.
+        let ctx = TestContext::shared(
+            "create table author (id integer primary key, name text not null)",
+        )?;
+
+        let err = persist_reporting_orphans::<Post>(ctx.clone(), ())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.orphans.entity_types(), [type_name::<Author>()]);
+        assert!(err.orphans.contains::<Author>());
+        assert!(!err.orphans.contains::<Post>());
+
+        assert_eq!(ctx.count("select count(*) from author")?, 1);
+
+        Ok(())
+    }
+}
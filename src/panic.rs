@@ -0,0 +1,41 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// A panic that was caught while running a user-provided closure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicError {
+    /// What the closure was being run for.
+    pub context: String,
+    /// The panic message.
+    pub message: String,
+}
+
+impl fmt::Display for PanicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} panicked: {}", self.context, self.message)
+    }
+}
+
+impl std::error::Error for PanicError {}
+
+/// Runs `f`, converting any panic into a [`PanicError`] described by `context`.
+pub(crate) fn catch_panic<R>(
+    context: impl FnOnce() -> String,
+    f: impl FnOnce() -> R,
+) -> Result<R, PanicError> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|payload| PanicError {
+        context: context(),
+        message: panic_message(payload.as_ref()),
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
@@ -0,0 +1,218 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::scope::Scoped;
+use crate::{persist_with, MaligniusError, Persist};
+
+type PersistedEntities = Rc<RefCell<Vec<(&'static str, Box<dyn Any>)>>>;
+
+thread_local! {
+    static PERSISTED_ASSOCIATIONS: RefCell<Option<PersistedEntities>> = const { RefCell::new(None) };
+}
+
+/// The associations persisted by [`persist_with_associations`].
+///
+/// This covers the whole graph, including nested associations, in the order they were
+/// persisted.
+#[derive(Default)]
+pub struct PersistedAssociations {
+    /// The persisted associations along with their type names.
+    entities: Vec<(&'static str, Box<dyn Any>)>,
+}
+
+impl PersistedAssociations {
+    /// Returns the first persisted association of type `T`, or `None` if there is none.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.entities
+            .iter()
+            .find_map(|(_, entity)| entity.downcast_ref::<T>())
+    }
+
+    /// Returns every persisted association of type `T`, in the order they were persisted.
+    pub fn get_all<T: 'static>(&self) -> Vec<&T> {
+        self.entities
+            .iter()
+            .filter_map(|(_, entity)| entity.downcast_ref::<T>())
+            .collect()
+    }
+
+    /// Returns the type name of every persisted association along with the association, in the
+    /// order they were persisted.
+    #[cfg_attr(not(feature = "fixtures"), allow(dead_code))]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&'static str, &dyn Any)> {
+        self.entities
+            .iter()
+            .map(|(entity_type, entity)| (*entity_type, entity.as_ref()))
+    }
+
+    /// Returns the number of persisted associations, of any type.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns whether no associations were persisted.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+impl std::fmt::Debug for PersistedAssociations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PersistedAssociations")
+            .field("len", &self.entities.len())
+            .finish()
+    }
+}
+
+/// Persists an entity, returning it along with every association persisted for it.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 1 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Post { author_id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let author: Author = association(&mut associations);
+/// #         (Self { author_id: author.id }, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Comment;
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Post = association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let (comment, associations) =
+///     persist_with_associations::<Comment>(ctx, Default::default()).await?;
+/// let author = associations.get::<Author>().unwrap();
+/// # assert_eq!(author.id, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_with_associations<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+) -> Result<(T, PersistedAssociations), MaligniusError<T::Err>> {
+    let entities = Rc::new(RefCell::new(Vec::new()));
+
+    let entity = Scoped::new(
+        &PERSISTED_ASSOCIATIONS,
+        entities.clone(),
+        persist_with::<T>(ctx, overrides),
+    )
+    .await?;
+
+    Ok((
+        entity,
+        PersistedAssociations {
+            entities: entities.take(),
+        },
+    ))
+}
+
+/// Records a persisted association for [`persist_with_associations`], dropping it if no call is
+/// collecting them.
+pub(crate) fn record_persisted_association(entity_type_name: &'static str, entity: Box<dyn Any>) {
+    PERSISTED_ASSOCIATIONS.with_borrow(|entities| {
+        if let Some(entities) = entities {
+            entities.borrow_mut().push((entity_type_name, entity));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{Author, Post, TestContext, SCHEMA};
+    use crate::{association, Associations, Manifest};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Comment;
+
+    impl Manifest for Comment {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            association::<Post>(&mut associations);
+
+            (Self, associations)
+        }
+    }
+
+    impl Persist for Comment {
+        type Err = rusqlite::Error;
+
+        async fn persist(_ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+            Ok(comment)
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_with_associations_returns_nested_associations(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let (_comment, associations) =
+            persist_with_associations::<Comment>(ctx.clone(), ()).await?;
+
+        assert!(!associations.is_empty());
+        assert_eq!(associations.len(), 2);
+
+        let post = associations.get::<Post>().unwrap();
+        assert_eq!(post.id, 1);
+        assert_eq!(post.title, "The Fifth Season");
+
+        let author = associations.get::<Author>().unwrap();
+        assert_eq!(author.id, 1);
+        assert_eq!(author.name, "N. K. Jemisin");
+
+        assert!(associations.get::<Comment>().is_none());
+        assert_eq!(associations.get_all::<Author>().len(), 1);
+
+        Ok(())
+    }
+}
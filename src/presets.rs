@@ -0,0 +1,151 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+
+use crate::{manifest_with, Manifest};
+
+/// A collection of named override presets loaded from TOML.
+///
+/// Each top-level table in the TOML document is a preset, and its keys are deserialized into
+/// the [`Manifest::Overrides`] of the entity being manifested:
+///
+/// ```toml
+/// [blockbuster]
+/// title = "Avatar"
+/// year = 2009
+/// ```
+#[derive(Debug, Clone)]
+pub struct Presets {
+    table: toml::Table,
+}
+
+impl Presets {
+    /// Parses presets from a TOML string.
+    pub fn from_toml(source: &str) -> Result<Self, PresetError> {
+        let table = source.parse::<toml::Table>().map_err(PresetError::Toml)?;
+
+        Ok(Self { table })
+    }
+
+    /// Returns the overrides for `T` defined by the named preset.
+    pub fn overrides<T: Manifest>(&self, name: &str) -> Result<T::Overrides, PresetError>
+    where
+        T::Overrides: DeserializeOwned,
+    {
+        let preset = self
+            .table
+            .get(name)
+            .ok_or_else(|| PresetError::NotFound(name.to_owned()))?;
+
+        preset.clone().try_into().map_err(PresetError::Toml)
+    }
+}
+
+/// Manifests an entity using the overrides from the named preset.
+pub fn manifest_preset<T: Manifest>(presets: &Presets, name: &str) -> Result<T, PresetError>
+where
+    T::Overrides: DeserializeOwned,
+{
+    Ok(manifest_with::<T>(presets.overrides::<T>(name)?))
+}
+
+/// An error that occurred while loading a preset.
+#[derive(Debug)]
+pub enum PresetError {
+    /// No preset with the given name exists.
+    NotFound(String),
+    /// The presets could not be parsed or deserialized.
+    Toml(toml::de::Error),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(name) => write!(f, "preset `{name}` not found"),
+            Self::Toml(err) => write!(f, "invalid preset: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NotFound(_) => None,
+            Self::Toml(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::Associations;
+
+    use super::*;
+
+    #[derive(Debug, Default, Deserialize)]
+    struct MovieOverrides {
+        pub title: Option<String>,
+        pub year: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Movie {
+        pub title: String,
+        pub year: u32,
+    }
+
+    impl Manifest for Movie {
+        type Context = ();
+        type Overrides = MovieOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    title: overrides.title.unwrap_or("Inception".into()),
+                    year: overrides.year.unwrap_or(2010),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    const PRESETS: &str = r#"
+        [blockbuster]
+        title = "Avatar"
+        year = 2009
+
+        [classic]
+        year = 1942
+    "#;
+
+    #[test]
+    fn manifest_preset_applies_the_preset_overrides() {
+        let presets = Presets::from_toml(PRESETS).unwrap();
+
+        assert_eq!(
+            manifest_preset::<Movie>(&presets, "blockbuster").unwrap(),
+            Movie {
+                title: "Avatar".into(),
+                year: 2009
+            }
+        );
+        assert_eq!(
+            manifest_preset::<Movie>(&presets, "classic").unwrap(),
+            Movie {
+                title: "Inception".into(),
+                year: 1942
+            }
+        );
+    }
+
+    #[test]
+    fn manifest_preset_errors_for_an_unknown_preset() {
+        let presets = Presets::from_toml(PRESETS).unwrap();
+
+        let err = manifest_preset::<Movie>(&presets, "indie").unwrap_err();
+
+        assert!(matches!(err, PresetError::NotFound(name) if name == "indie"));
+    }
+}
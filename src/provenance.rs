@@ -0,0 +1,191 @@
+use std::fmt;
+
+use crate::Manifest;
+
+/// Where the value of a field of a manifested entity came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The value is the factory's default.
+    Default,
+    /// The value was supplied through the overrides.
+    Override,
+}
+
+/// Overrides that can report which of their fields have been set.
+///
+/// For overrides made up of `Option` fields this can be derived with
+/// `#[derive(OverrideProvenance)]` (with the `derive` feature), including on the builders
+/// generated by `derive_builder` with `#[builder(derive(OverrideProvenance))]`.
+pub trait OverrideProvenance {
+    /// Returns the name of each field along with where its value will come from.
+    fn field_sources(&self) -> Vec<(&'static str, Source)>;
+}
+
+/// Which fields of a manifested entity came from defaults and which from overrides.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProvenanceReport {
+    fields: Vec<(&'static str, Source)>,
+}
+
+impl ProvenanceReport {
+    /// Returns where the value of `field` came from, or `None` if the field is unknown.
+    pub fn source(&self, field: &str) -> Option<Source> {
+        self.fields
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, source)| *source)
+    }
+
+    /// Returns the name of each field along with where its value came from.
+    pub fn fields(&self) -> &[(&'static str, Source)] {
+        &self.fields
+    }
+
+    /// Returns the names of the fields whose values were overridden.
+    pub fn overridden(&self) -> Vec<&'static str> {
+        self.fields_from(Source::Override)
+    }
+
+    /// Returns the names of the fields whose values are the factory's defaults.
+    pub fn defaulted(&self) -> Vec<&'static str> {
+        self.fields_from(Source::Default)
+    }
+
+    fn fields_from(&self, source: Source) -> Vec<&'static str> {
+        self.fields
+            .iter()
+            .filter(|(_, field_source)| *field_source == source)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+impl fmt::Display for ProvenanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, source) in &self.fields {
+            let source = match source {
+                Source::Default => "default",
+                Source::Override => "override",
+            };
+
+            writeln!(f, "{name}: {source}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Manifests an entity, reporting which of its fields came from defaults and which from the
+/// overrides.
+///
+/// ```
+/// # use malignius::{manifest_with_provenance, Associations, Manifest, OverrideProvenance, Source};
+/// # #[derive(Default)]
+/// # struct MovieOverrides {
+/// #     title: Option<String>,
+/// #     year: Option<u32>,
+/// # }
+/// # impl OverrideProvenance for MovieOverrides {
+/// #     fn field_sources(&self) -> Vec<(&'static str, Source)> {
+/// #         let source = |set: bool| if set { Source::Override } else { Source::Default };
+/// #         vec![
+/// #             ("title", source(self.title.is_some())),
+/// #             ("year", source(self.year.is_some())),
+/// #         ]
+/// #     }
+/// # }
+/// # struct Movie {
+/// #     title: String,
+/// #     year: u32,
+/// # }
+/// # impl Manifest for Movie {
+/// #     type Context = ();
+/// #     type Overrides = MovieOverrides;
+/// #     fn manifest(overrides: MovieOverrides) -> (Self, Associations<()>) {
+/// #         let movie = Movie {
+/// #             title: overrides.title.unwrap_or_else(|| "Arrival".into()),
+/// #             year: overrides.year.unwrap_or(2016),
+/// #         };
+/// #         (movie, Associations::new())
+/// #     }
+/// # }
+/// # let overrides = MovieOverrides {
+/// #     title: Some("Dune".into()),
+/// #     ..Default::default()
+/// # };
+/// let (movie, provenance) = manifest_with_provenance::<Movie>(overrides);
+/// assert_eq!(provenance.source("title"), Some(Source::Override));
+/// # assert_eq!(provenance.source("year"), Some(Source::Default));
+/// ```
+pub fn manifest_with_provenance<T: Manifest>(overrides: T::Overrides) -> (T, ProvenanceReport)
+where
+    T::Overrides: OverrideProvenance,
+{
+    let report = ProvenanceReport {
+        fields: overrides.field_sources(),
+    };
+
+    let (entity, _) = crate::graph_path::with_root::<T, _>(|| T::manifest(overrides));
+
+    (entity, report)
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use crate::Associations;
+
+    use super::*;
+
+    #[derive(Debug, Default, crate::OverrideProvenance)]
+    struct MovieOverrides {
+        pub title: Option<String>,
+        pub year: Option<u32>,
+        pub rating: Option<u8>,
+    }
+
+    #[derive(Debug)]
+    struct Movie {
+        pub title: String,
+        pub year: u32,
+        pub rating: u8,
+    }
+
+    impl Manifest for Movie {
+        type Context = ();
+        type Overrides = MovieOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    title: overrides.title.unwrap_or("Arrival".into()),
+                    year: overrides.year.unwrap_or(2016),
+                    rating: overrides.rating.unwrap_or(4),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[test]
+    fn provenance_marks_overridden_fields() {
+        let (movie, provenance) = manifest_with_provenance::<Movie>(MovieOverrides {
+            title: Some("Dune".into()),
+            ..Default::default()
+        });
+
+        assert_eq!(movie.title, "Dune");
+        assert_eq!(movie.year, 2016);
+        assert_eq!(movie.rating, 4);
+        assert_eq!(provenance.source("title"), Some(Source::Override));
+        assert_eq!(provenance.source("year"), Some(Source::Default));
+        assert_eq!(provenance.source("rating"), Some(Source::Default));
+        assert_eq!(provenance.source("runtime"), None);
+
+        assert_eq!(provenance.overridden(), ["title"]);
+        assert_eq!(provenance.defaulted(), ["year", "rating"]);
+        assert_eq!(
+            provenance.to_string(),
+            "title: override\nyear: default\nrating: default\n"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use crate::{persist_with, MaligniusError, Persist};
+
+/// Persists an entity, manifesting it again whenever it violates a check constraint.
+///
+/// Each attempt manifests the entity from scratch, so random and sequence-backed defaults are
+/// pulled again. This is useful when a default can occasionally fall outside of a constraint such
+/// as `rating between 1 and 5`. The `is_check_violation` closure decides which errors from
+/// persisting the entity itself are check violations; any other error, including a failure to
+/// persist an association, is returned immediately. After `max_attempts` attempts the
+/// last check violation is returned. The entity is always attempted at least once, so a
+/// `max_attempts` of `0` behaves like `1`.
+///
+/// Associations persisted by a failed attempt are not rolled back.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # use rusqlite::Connection;
+/// # struct TestContext {
+/// #     conn: Connection,
+/// # }
+/// # #[derive(Debug)]
+/// # struct Review {
+/// #     rating: i64,
+/// # }
+/// # impl Manifest for Review {
+/// #     type Context = TestContext;
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<TestContext>) {
+/// #         // The first rating is out of range.
+/// #         let rating = sequences::next_named("rating", |n| 7 - n as i64);
+/// #         (Self { rating }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Review {
+/// #     type Err = rusqlite::Error;
+/// #     async fn persist(ctx: &TestContext, review: Self) -> Result<Self, Self::Err> {
+/// #         let sql = "insert into review (rating) values ($1)";
+/// #         ctx.conn.execute(sql, [review.rating])?;
+/// #         Ok(review)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let conn = Connection::open_in_memory()?;
+/// # conn.execute_batch("create table review (rating integer check (rating between 1 and 5))")?;
+/// # let ctx = Arc::new(TestContext { conn });
+/// let review: Review = persist_retrying_check_violations(ctx, Default::default(), 5, |err| {
+///     matches!(
+///         err,
+///         rusqlite::Error::SqliteFailure(err, _)
+///             if err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_CHECK
+///     )
+/// })
+/// .await?;
+/// # assert_eq!(review.rating, 5);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_retrying_check_violations<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+    max_attempts: usize,
+    is_check_violation: impl Fn(&T::Err) -> bool,
+) -> Result<T, MaligniusError<T::Err>>
+where
+    T::Overrides: Clone,
+{
+    let mut attempt = 1;
+    loop {
+        match persist_with::<T>(ctx.clone(), overrides.clone()).await {
+            Err(MaligniusError::Persist(err))
+                if attempt < max_attempts && is_check_violation(&err) =>
+            {
+                attempt += 1
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use rusqlite::{ffi, params};
+
+    use crate::test_support::TestContext;
+    use crate::{Associations, Manifest};
+
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        create table review (
+            id integer primary key,
+            rating integer not null check (rating between 1 and 5)
+        );
+    "#;
+
+    thread_local! {
+        static RATINGS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Review {
+        pub rating: u8,
+    }
+
+    impl Manifest for Review {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            // The first rating is out of range, the next one is valid.
+            let n = RATINGS.replace(RATINGS.get() + 1);
+
+            (
+                Self {
+                    rating: [0, 4][n.min(1)],
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Review {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, review: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into review (rating) values ($1)",
+                params![review.rating],
+            )?;
+
+            Ok(review)
+        }
+    }
+
+    fn is_check_violation(err: &rusqlite::Error) -> bool {
+        matches!(
+            err,
+            rusqlite::Error::SqliteFailure(err, _) if err.extended_code == ffi::SQLITE_CONSTRAINT_CHECK
+        )
+    }
+
+    #[tokio::test]
+    async fn persist_retries_after_a_check_violation() -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let review: Review =
+            persist_retrying_check_violations(ctx.clone(), (), 3, is_check_violation).await?;
+
+        assert_eq!(review, Review { rating: 4 });
+        assert_eq!(RATINGS.get(), 2);
+
+        let ratings = ctx
+            .conn
+            .prepare("select rating from review")?
+            .query_map([], |row| row.get::<_, u8>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        assert_eq!(ratings, vec![4]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn persist_gives_up_after_the_maximum_number_of_attempts() {
+        let ctx = TestContext::shared(SCHEMA).unwrap();
+
+        let result =
+            persist_retrying_check_violations::<Review>(ctx, (), 1, is_check_violation).await;
+
+        assert!(result.is_err_and(|err| err.persist_error().is_some_and(is_check_violation)));
+    }
+
+    #[tokio::test]
+    async fn persist_is_attempted_once_with_no_attempts_allowed() {
+        let ctx = TestContext::shared(SCHEMA).unwrap();
+
+        let result =
+            persist_retrying_check_violations::<Review>(ctx, (), 0, is_check_violation).await;
+
+        assert!(result.is_err_and(|err| err.persist_error().is_some_and(is_check_violation)));
+        assert_eq!(RATINGS.get(), 1);
+    }
+}
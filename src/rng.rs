@@ -0,0 +1,125 @@
+use rand::{Rng, RngCore};
+
+use crate::{graph_path, Associations, Manifest};
+
+/// A [`Manifest`] whose defaults are drawn from a caller-provided RNG.
+pub trait ManifestWithRng: Manifest {
+    fn manifest_with_rng(
+        overrides: Self::Overrides,
+        rng: &mut dyn RngCore,
+    ) -> (Self, Associations<Self::Context>)
+    where
+        Self: Sized;
+}
+
+/// Manifests an entity with the given overrides, pulling any random defaults from the given RNG.
+///
+/// Passing a seeded RNG makes the manifested entity reproducible. Like
+/// [`manifest_with`](crate::manifest_with), any associations the entity declares are discarded.
+///
+/// ```
+/// # use malignius::{manifest_with_rng, Associations, Manifest, ManifestWithRng};
+/// # use rand::rngs::StdRng;
+/// # use rand::{Rng, RngCore, SeedableRng};
+/// # struct Player {
+/// #     level: u8,
+/// # }
+/// # impl Manifest for Player {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<()>) {
+/// #         (Self { level: 1 }, Associations::new())
+/// #     }
+/// # }
+/// # impl ManifestWithRng for Player {
+/// #     fn manifest_with_rng(_overrides: (), rng: &mut dyn RngCore) -> (Self, Associations<()>) {
+/// #         (Self { level: rng.gen_range(1..=99) }, Associations::new())
+/// #     }
+/// # }
+/// let player: Player = manifest_with_rng(Default::default(), &mut StdRng::seed_from_u64(42));
+/// # let again: Player = manifest_with_rng((), &mut StdRng::seed_from_u64(42));
+/// # assert_eq!(player.level, again.level);
+/// ```
+pub fn manifest_with_rng<T: ManifestWithRng>(overrides: T::Overrides, rng: &mut impl Rng) -> T {
+    let (entity, _) = graph_path::with_root::<T, _>(|| T::manifest_with_rng(overrides, rng));
+    entity
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct PlayerOverrides {
+        pub name: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Player {
+        pub name: String,
+        pub score: u32,
+    }
+
+    impl Manifest for Player {
+        type Context = ();
+        type Overrides = PlayerOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    name: overrides.name.unwrap_or("Player 1".into()),
+                    score: 0,
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl ManifestWithRng for Player {
+        fn manifest_with_rng(
+            overrides: Self::Overrides,
+            rng: &mut dyn RngCore,
+        ) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    name: overrides
+                        .name
+                        .unwrap_or_else(|| format!("Player {}", rng.gen_range(1..=1_000_000))),
+                    score: rng.gen_range(0..=1_000_000),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[test]
+    fn manifest_with_rng_is_reproducible_for_the_same_seed() {
+        let first: Player = manifest_with_rng(Default::default(), &mut StdRng::seed_from_u64(42));
+        let second: Player = manifest_with_rng(Default::default(), &mut StdRng::seed_from_u64(42));
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn manifest_with_rng_diverges_for_different_seeds() {
+        let first: Player = manifest_with_rng(Default::default(), &mut StdRng::seed_from_u64(1));
+        let second: Player = manifest_with_rng(Default::default(), &mut StdRng::seed_from_u64(2));
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn manifest_with_rng_applies_the_overrides() {
+        let player: Player = manifest_with_rng(
+            PlayerOverrides {
+                name: Some("Ada".into()),
+            },
+            &mut StdRng::seed_from_u64(42),
+        );
+
+        assert_eq!(player.name, "Ada");
+    }
+}
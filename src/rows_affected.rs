@@ -0,0 +1,410 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::scope::Scoped;
+use crate::{persist_in_with, MaligniusError, Persist};
+
+thread_local! {
+    static ROWS_AFFECTED: RefCell<Option<Rc<RefCell<Vec<RowsAffected>>>>> = const { RefCell::new(None) };
+    static STATEMENT_COUNTERS: RefCell<Vec<Rc<Cell<usize>>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The number of rows affected by a statement executed while persisting an entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowsAffected {
+    /// The type name of the entity the statement persisted.
+    pub entity_type: &'static str,
+
+    /// The number of rows the statement affected, as reported by the backend.
+    pub rows: usize,
+}
+
+/// An entity returned by [`persist_reporting_rows`], along with the rows affected persisting it.
+#[derive(Debug)]
+pub struct PersistReport<T> {
+    pub entity: T,
+
+    /// The rows affected by each statement, in the order the statements were executed.
+    ///
+    /// This includes the statements for the entity's associations. Only statements whose
+    /// `Persist` implementation calls [`record_rows_affected`] are included, which
+    /// `#[derive(Persist)]` does.
+    pub rows_affected: Vec<RowsAffected>,
+}
+
+/// Records the number of rows affected by a statement that persisted an entity of type `T`.
+///
+/// This is intended to be called from [`Persist::persist`] with the count returned by the
+/// backend. The rows are only recorded within [`persist_reporting_rows`], but every call counts
+/// as a statement for the running [`PersistStatementCounter`]s.
+pub fn record_rows_affected<T: ?Sized>(rows: usize) {
+    STATEMENT_COUNTERS.with_borrow(|counters| {
+        for count in counters {
+            count.set(count.get() + 1);
+        }
+    });
+
+    ROWS_AFFECTED.with_borrow(|rows_affected| {
+        if let Some(rows_affected) = rows_affected {
+            rows_affected.borrow_mut().push(RowsAffected {
+                entity_type: std::any::type_name::<T>(),
+                rows,
+            });
+        }
+    });
+}
+
+/// Persists an entity using a borrowed context, reporting the rows affected by each statement.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: 1 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         record_rows_affected::<Self>(1);
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Post { author_id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let author: Author = association(&mut associations);
+/// #         (Self { author_id: author.id }, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         record_rows_affected::<Self>(1);
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Comment;
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Post = association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+/// #         record_rows_affected::<Self>(1);
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = ();
+/// let report = persist_reporting_rows::<Comment>(&ctx, Default::default()).await?;
+/// assert!(report.rows_affected.iter().all(|statement| statement.rows == 1));
+/// # assert_eq!(report.rows_affected.len(), 3);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_reporting_rows<T: Persist + 'static>(
+    ctx: &T::Context,
+    overrides: T::Overrides,
+) -> Result<PersistReport<T>, MaligniusError<T::Err>> {
+    let rows_affected = Rc::new(RefCell::new(Vec::new()));
+
+    let entity = Scoped::new(
+        &ROWS_AFFECTED,
+        rows_affected.clone(),
+        persist_in_with::<T>(ctx, overrides),
+    )
+    .await?;
+
+    Ok(PersistReport {
+        entity,
+        rows_affected: rows_affected.take(),
+    })
+}
+
+/// Counts the statements executed on the current thread while it is alive.
+///
+/// Only statements whose `Persist` implementation calls [`record_rows_affected`] are counted,
+/// which `#[derive(Persist)]` does. This is useful for asserting that a type configured as
+/// [`batched`](crate::PersistConfig::batched) is inserted in batches rather than a row at a
+/// time. Counters can be nested, in which case each of them counts the statements executed in
+/// its scope.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Debug)]
+/// # struct Author { id: u64 }
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self { id: sequences::next_named("author", |n| n as u64) }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), author: Self) -> Result<Self, Self::Err> {
+/// #         record_rows_affected::<Self>(1);
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[derive(Default)]
+/// # struct PostOverrides { author_id: Option<u64> }
+/// # #[derive(Debug)]
+/// # struct Post { id: u64, author_id: u64 }
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = PostOverrides;
+/// #     fn manifest(overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let id = sequences::next_named("post", |n| n as u64);
+/// #         (Self { id, author_id: overrides.author_id.unwrap_or(0) }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), post: Self) -> Result<Self, Self::Err> {
+/// #         record_rows_affected::<Self>(1);
+/// #         Ok(post)
+/// #     }
+/// #     async fn persist_batch(_ctx: &(), posts: Vec<Self>) -> Result<Vec<Self>, Self::Err> {
+/// #         record_rows_affected::<Self>(posts.len());
+/// #         Ok(posts)
+/// #     }
+/// # }
+/// # #[derive(Default)]
+/// # struct CommentOverrides { post_id: Option<u64> }
+/// # #[derive(Debug)]
+/// # struct Comment { id: u64, post_id: u64 }
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = CommentOverrides;
+/// #     fn manifest(overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let id = sequences::next_named("comment", |n| n as u64);
+/// #         (Self { id, post_id: overrides.post_id.unwrap_or(0) }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), comment: Self) -> Result<Self, Self::Err> {
+/// #         record_rows_affected::<Self>(1);
+/// #         Ok(comment)
+/// #     }
+/// #     async fn persist_batch(_ctx: &(), comments: Vec<Self>) -> Result<Vec<Self>, Self::Err> {
+/// #         record_rows_affected::<Self>(comments.len());
+/// #         Ok(comments)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// # configure::<Post>(PersistConfig {
+/// #     batched: true,
+/// #     ..Default::default()
+/// # });
+/// # configure::<Comment>(PersistConfig {
+/// #     batched: true,
+/// #     ..Default::default()
+/// # });
+/// let counter = PersistStatementCounter::start();
+/// let hierarchy = persist_bulk_hierarchy::<Author, Post, Comment>(
+///     ctx,
+///     1,
+///     10,
+///     10,
+///     |author| PostOverrides { author_id: Some(author.id) },
+///     |post| CommentOverrides { post_id: Some(post.id) },
+/// )
+/// .await?;
+/// counter.assert_at_most(3);
+/// # Ok(())
+/// # }
+/// ```
+pub struct PersistStatementCounter {
+    count: Rc<Cell<usize>>,
+}
+
+impl PersistStatementCounter {
+    /// Starts counting executed statements.
+    pub fn start() -> Self {
+        let count = Rc::new(Cell::new(0));
+
+        STATEMENT_COUNTERS.with_borrow_mut(|counters| counters.push(count.clone()));
+
+        Self { count }
+    }
+
+    /// Returns the number of statements executed since the counter was started.
+    pub fn total(&self) -> usize {
+        self.count.get()
+    }
+
+    /// Asserts that no more than `max` statements have been executed since the counter was
+    /// started.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `max` statements have been executed.
+    #[track_caller]
+    pub fn assert_at_most(&self, max: usize) {
+        let total = self.total();
+        assert!(
+            total <= max,
+            "expected at most {max} statements to be executed, but {total} were"
+        );
+    }
+}
+
+impl Drop for PersistStatementCounter {
+    fn drop(&mut self) {
+        STATEMENT_COUNTERS.with_borrow_mut(|counters| {
+            counters.retain(|count| !Rc::ptr_eq(count, &self.count));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rusqlite::{params, params_from_iter};
+
+    use crate::test_support::TestContext;
+    use crate::{
+        configure, graph_path, persist, persist_manifested, persist_with, Associations, Manifest,
+        PersistConfig,
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Post {
+        pub id: i64,
+    }
+
+    impl Manifest for Post {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { id: 0 }, Associations::new())
+        }
+    }
+
+    impl Persist for Post {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, _post: Self) -> Result<Self, Self::Err> {
+            let rows = ctx.conn.execute("insert into post default values", ())?;
+            record_rows_affected::<Self>(rows);
+
+            Ok(Self {
+                id: ctx.conn.last_insert_rowid(),
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct Tag {
+        pub post_id: i64,
+    }
+
+    impl Manifest for Tag {
+        type Context = TestContext;
+        type Overrides = i64;
+
+        fn manifest(post_id: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { post_id }, Associations::new())
+        }
+    }
+
+    impl Persist for Tag {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, tag: Self) -> Result<Self, Self::Err> {
+            Ok(Self::persist_batch(ctx, vec![tag]).await?.remove(0))
+        }
+
+        async fn persist_batch(
+            ctx: &Self::Context,
+            tags: Vec<Self>,
+        ) -> Result<Vec<Self>, Self::Err> {
+            let values = vec!["(?)"; tags.len()].join(", ");
+            let rows = ctx.conn.execute(
+                &format!("insert into tag (post_id) values {values}"),
+                params_from_iter(tags.iter().map(|tag| tag.post_id)),
+            )?;
+            record_rows_affected::<Self>(rows);
+
+            Ok(tags)
+        }
+    }
+
+    #[tokio::test]
+    async fn batched_has_many_executes_fewer_statements_than_a_loop(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        configure::<Tag>(PersistConfig {
+            batched: true,
+            ..Default::default()
+        });
+
+        let ctx = TestContext::shared(
+            r#"
+                create table post (id integer primary key);
+                create table tag (post_id integer not null references post (id));
+            "#,
+        )?;
+
+        let looped = PersistStatementCounter::start();
+        let post: Post = persist(ctx.clone()).await?;
+        for _ in 0..10 {
+            let _: Tag = persist_with(ctx.clone(), post.id).await?;
+        }
+        assert_eq!(looped.total(), 11);
+        drop(looped);
+
+        let batched = PersistStatementCounter::start();
+        let post: Post = persist(ctx.clone()).await?;
+        let manifested = (0..10)
+            .map(|index| graph_path::with_root_at::<Tag, _>(index, || Tag::manifest(post.id)))
+            .collect();
+        let tags = persist_manifested::<Tag>(&ctx, manifested, &Default::default()).await?;
+        assert_eq!(tags.len(), 10);
+        batched.assert_at_most(2);
+
+        let rows: usize = ctx.conn.query_row(
+            "select count(*) from tag where post_id = $1",
+            params![post.id],
+            |row| row.get(0),
+        )?;
+        assert_eq!(rows, 10);
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "expected at most 1 statements to be executed, but 2 were")]
+    fn assert_at_most_fails_when_the_bound_is_exceeded() {
+        let counter = PersistStatementCounter::start();
+        record_rows_affected::<Tag>(1);
+        record_rows_affected::<Tag>(1);
+
+        counter.assert_at_most(1);
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+/// A reusable setup made up of several persisted entities.
+///
+/// Scenarios sit above individual factories: they compose multiple persist calls into a common
+/// setup, such as "a published post with an author and three comments", and return the entities
+/// that tests need to refer to.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use derive_builder::Builder;
+/// # use malignius::*;
+/// # use rusqlite::{params, Connection};
+/// # struct TestContext {
+/// #     conn: Connection,
+/// # }
+/// # struct Author {
+/// #     id: i64,
+/// # }
+/// # impl Manifest for Author {
+/// #     type Context = TestContext;
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<TestContext>) {
+/// #         (Self { id: 0 }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = rusqlite::Error;
+/// #     async fn persist(ctx: &TestContext, author: Self) -> Result<Self, Self::Err> {
+/// #         ctx.conn.execute("insert into author default values", ())?;
+/// #         Ok(Self { id: ctx.conn.last_insert_rowid() })
+/// #     }
+/// # }
+/// # #[derive(Builder)]
+/// # struct Post {
+/// #     author_id: i64,
+/// # }
+/// # impl Manifest for Post {
+/// #     type Context = TestContext;
+/// #     type Overrides = PostBuilder;
+/// #     fn manifest(overrides: PostBuilder) -> (Self, Associations<TestContext>) {
+/// #         (Self { author_id: overrides.author_id.unwrap_or(0) }, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = rusqlite::Error;
+/// #     async fn persist(ctx: &TestContext, post: Self) -> Result<Self, Self::Err> {
+/// #         let sql = "insert into post (author_id) values ($1)";
+/// #         ctx.conn.execute(sql, params![post.author_id])?;
+/// #         Ok(post)
+/// #     }
+/// # }
+/// struct PublishedPost {
+///     pub author: Author,
+///     pub post: Post,
+/// }
+///
+/// impl Scenario for PublishedPost {
+///     type Context = TestContext;
+///     type Err = MaligniusError<rusqlite::Error>;
+///
+///     async fn build(ctx: Arc<Self::Context>) -> Result<Self, Self::Err> {
+///         let author: Author = persist(ctx.clone()).await?;
+///         let post: Post = persist_with(ctx, {
+///             let mut post = PostBuilder::default();
+///             post.author_id(author.id);
+///             post
+///         })
+///         .await?;
+///
+///         Ok(Self { author, post })
+///     }
+/// }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let conn = Connection::open_in_memory()?;
+/// # conn.execute_batch(
+/// #     "create table author (id integer primary key);
+/// #      create table post (author_id integer not null references author (id));",
+/// # )?;
+/// # let scenario: PublishedPost = build_scenario(Arc::new(TestContext { conn })).await?;
+/// # assert_eq!(scenario.post.author_id, scenario.author.id);
+/// # Ok(())
+/// # }
+/// ```
+pub trait Scenario: Sized {
+    type Context;
+    type Err;
+
+    /// Persists the entities making up the scenario.
+    #[allow(async_fn_in_trait)]
+    async fn build(ctx: Arc<Self::Context>) -> Result<Self, Self::Err>;
+}
+
+/// Builds the scenario `S`, returning the entities it created.
+#[inline(always)]
+pub async fn build_scenario<S: Scenario>(ctx: Arc<S::Context>) -> Result<S, S::Err> {
+    S::build(ctx).await
+}
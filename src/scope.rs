@@ -0,0 +1,45 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread::LocalKey;
+
+/// A future that makes a value available through a thread-local for as long as it is polled.
+///
+/// The value is swapped into the thread-local before every poll and swapped back out
+/// afterwards, so multiple scoped futures can be interleaved on the same thread without
+/// observing each other's values.
+pub(crate) struct Scoped<F, V: 'static> {
+    key: &'static LocalKey<RefCell<Option<V>>>,
+    value: Option<V>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future, V: 'static> Scoped<F, V> {
+    pub(crate) fn new(key: &'static LocalKey<RefCell<Option<V>>>, value: V, future: F) -> Self {
+        Self {
+            key,
+            value: Some(value),
+            future: Box::pin(future),
+        }
+    }
+}
+
+// The inner future is boxed and the value is never pinned, so `Scoped` is safe to move.
+impl<F, V: 'static> Unpin for Scoped<F, V> {}
+
+impl<F: Future, V: 'static> Future for Scoped<F, V> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        this.key
+            .with(|slot| std::mem::swap(&mut *slot.borrow_mut(), &mut this.value));
+        let poll = this.future.as_mut().poll(cx);
+        this.key
+            .with(|slot| std::mem::swap(&mut *slot.borrow_mut(), &mut this.value));
+
+        poll
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+type ScopedProduceFn<K, T> = Box<dyn Fn(&K, usize) -> T>;
+
+/// A [`Sequence`](crate::Sequence) with an independent counter for each scope, such as the entity
+/// the values belong to.
+///
+/// The producer receives the scope along with the index, so values can include it:
+///
+/// ```
+/// # use malignius::ScopedSequence;
+/// # struct Customer { id: u32 }
+/// # let customer = Customer { id: 7 };
+/// let mut invoice_numbers =
+///     ScopedSequence::new(|customer_id: &u32, n| format!("INV-{customer_id}-{n}"));
+///
+/// assert_eq!(invoice_numbers.next(&customer.id), "INV-7-1");
+/// ```
+pub struct ScopedSequence<K, T> {
+    counters: HashMap<K, usize>,
+    produce: ScopedProduceFn<K, T>,
+}
+
+impl<K: Eq + Hash + Clone, T> ScopedSequence<K, T> {
+    pub fn new(produce: impl Fn(&K, usize) -> T + 'static) -> Self {
+        Self {
+            counters: HashMap::new(),
+            produce: Box::new(produce),
+        }
+    }
+
+    /// Returns the next value in the sequence for `scope`.
+    ///
+    /// Each scope starts at `1`.
+    pub fn next(&mut self, scope: &K) -> T {
+        let counter = self.counters.entry(scope.clone()).or_insert(1);
+
+        let n = *counter;
+        *counter += 1;
+
+        (self.produce)(scope, n)
+    }
+
+    /// Resets the sequence for `scope` back to the start, leaving other scopes as they are.
+    pub fn reset(&mut self, scope: &K) {
+        self.counters.remove(scope);
+    }
+
+    /// Resets the sequence for every scope back to the start.
+    pub fn reset_all(&mut self) {
+        self.counters.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_are_numbered_independently() {
+        let mut invoice_numbers =
+            ScopedSequence::new(|customer_id: &u32, n| format!("INV-{customer_id}-{n}"));
+
+        assert_eq!(invoice_numbers.next(&7), "INV-7-1");
+        assert_eq!(invoice_numbers.next(&7), "INV-7-2");
+        assert_eq!(invoice_numbers.next(&12), "INV-12-1");
+        assert_eq!(invoice_numbers.next(&7), "INV-7-3");
+        assert_eq!(invoice_numbers.next(&12), "INV-12-2");
+    }
+
+    #[test]
+    fn reset_only_affects_the_given_scope() {
+        let mut invoice_numbers =
+            ScopedSequence::new(|customer_id: &u32, n| format!("INV-{customer_id}-{n}"));
+
+        invoice_numbers.next(&7);
+        invoice_numbers.next(&12);
+
+        invoice_numbers.reset(&7);
+
+        assert_eq!(invoice_numbers.next(&7), "INV-7-1");
+        assert_eq!(invoice_numbers.next(&12), "INV-12-2");
+
+        invoice_numbers.reset_all();
+
+        assert_eq!(invoice_numbers.next(&12), "INV-12-1");
+    }
+}
@@ -1,26 +1,193 @@
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::panic::catch_panic;
+use crate::PanicError;
+
+/// A source of unique values, produced from an increasing index.
+///
+/// `Sequence` is an infinite [`Iterator`], so it works with the standard iterator adapters:
+///
+/// ```
+/// # use malignius::Sequence;
+/// let mut ids = Sequence::new(|n| n);
+/// let even_ids = ids.by_ref().map(|id| id * 2).take(3).collect::<Vec<_>>();
+/// # assert_eq!(even_ids, vec![2, 4, 6]);
+/// # assert_eq!(ids.next(), 4);
+/// ```
+///
+/// The inherent [`Sequence::next`] takes precedence over [`Iterator::next`] and returns the value
+/// itself rather than an `Option`. Likewise, [`Sequence::map`] returns another `Sequence`. Calling
+/// `take` uses [`Iterator::take`], which consumes the sequence unless it is called through
+/// [`Iterator::by_ref`]; use [`Sequence::take_vec`] to collect the next *n* values without giving
+/// up the sequence.
+///
+/// Cloning a sequence snapshots its position, so the clones can be advanced independently. The
+/// producer is shared between the clones rather than copied.
 pub struct Sequence<T> {
+    start: usize,
+    step: usize,
     counter: usize,
-    produce: Box<dyn Fn(usize) -> T>,
+    reserved: Vec<Range<usize>>,
+    produce: Arc<dyn Fn(usize) -> T>,
+}
+
+impl<T> Clone for Sequence<T> {
+    fn clone(&self) -> Self {
+        Self {
+            start: self.start,
+            step: self.step,
+            counter: self.counter,
+            reserved: self.reserved.clone(),
+            produce: self.produce.clone(),
+        }
+    }
 }
 
 impl<T> Sequence<T> {
     pub fn new(produce: impl Fn(usize) -> T + 'static) -> Self {
+        Self::with_start(1, produce)
+    }
+
+    /// Creates a sequence whose first value is produced from `start` instead of `1`.
+    ///
+    /// This is useful for avoiding collisions with rows that were inserted with fixed ids.
+    pub fn with_start(start: usize, produce: impl Fn(usize) -> T + 'static) -> Self {
         Self {
-            counter: 1,
-            produce: Box::new(produce),
+            start,
+            step: 1,
+            counter: start,
+            reserved: Vec::new(),
+            produce: Arc::new(produce),
+        }
+    }
+
+    /// Makes the sequence advance by `step` after each value instead of by `1`.
+    ///
+    /// ```
+    /// # use malignius::Sequence;
+    /// let mut ids = Sequence::new(|n| n).with_step(10);
+    /// assert_eq!(ids.take_vec(3), vec![1, 11, 21]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is `0`.
+    pub fn with_step(mut self, step: usize) -> Self {
+        assert!(step > 0, "a sequence cannot advance by a step of 0");
+
+        self.step = step;
+        self
+    }
+
+    /// Transforms the values produced by the sequence with `f`.
+    ///
+    /// The mapped sequence continues from where this one left off.
+    ///
+    /// ```
+    /// # use malignius::Sequence;
+    /// let mut labels = Sequence::new(|n| n).map(|id| format!("#{id}"));
+    /// # assert_eq!(labels.next(), "#1");
+    /// ```
+    pub fn map<U>(self, f: impl Fn(T) -> U + 'static) -> Sequence<U>
+    where
+        T: 'static,
+    {
+        let produce = self.produce;
+
+        Sequence {
+            start: self.start,
+            step: self.step,
+            counter: self.counter,
+            reserved: self.reserved,
+            produce: Arc::new(move |n| f(produce(n))),
         }
     }
 
+    /// Appends the configured [`unique_suffix`](crate::unique_suffix) to every value, so that
+    /// values do not collide with the ones left behind by other test runs.
+    ///
+    /// ```
+    /// # use malignius::Sequence;
+    /// let mut names = Sequence::new(|n| format!("Author {n}")).unique();
+    /// # assert!(names.next().starts_with("Author 1"));
+    /// ```
+    pub fn unique(self) -> Sequence<String>
+    where
+        T: fmt::Display + 'static,
+    {
+        self.map(crate::unique)
+    }
+
     /// Returns the next value in the sequence.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> T {
-        let n = self.counter;
-        self.counter += 1;
+        let n = self.next_index();
+        self.counter = n + self.step;
 
         (self.produce)(n)
     }
 
+    /// Advances the sequence past the next *n* values without producing them.
+    ///
+    /// This is useful for lining up with rows that were created elsewhere. It is the sequence's
+    /// own version of [`Iterator::skip`], which would consume the sequence and still produce the
+    /// skipped values.
+    pub fn advance(&mut self, n: usize) {
+        for _ in 0..n {
+            self.counter = self.next_index() + self.step;
+        }
+    }
+
+    /// Returns the value that the next call to [`Sequence::next`] will produce, without
+    /// advancing the sequence.
+    pub fn peek(&self) -> T {
+        (self.produce)(self.next_index())
+    }
+
+    /// Returns the value the sequence produces for `index`, without advancing the sequence.
+    ///
+    /// Unlike [`Iterator::nth`], this is a lookup rather than a skip: the values before `index`
+    /// are neither produced nor consumed.
+    pub fn nth(&self, index: usize) -> T {
+        (self.produce)(index)
+    }
+
+    /// Returns the index that the next call to [`Sequence::next`] will produce a value from.
+    ///
+    /// On a fresh sequence this is its start, and it goes up as values are produced.
+    pub fn current(&self) -> usize {
+        self.next_index()
+    }
+
+    /// Returns the next value in the sequence, returning an error if the producer panics.
+    ///
+    /// The sequence only advances when a value is produced successfully.
+    pub fn try_next(&mut self) -> Result<T, PanicError> {
+        let checkpoint = self.checkpoint();
+
+        let value = catch_panic(
+            || {
+                format!(
+                    "producing value {checkpoint} of `Sequence<{}>`",
+                    std::any::type_name::<T>()
+                )
+            },
+            || self.next(),
+        );
+
+        if value.is_err() {
+            self.rewind_to(checkpoint);
+        }
+
+        value
+    }
+
     /// Returns the next *n* values in the sequence.
-    pub fn take(&mut self, n: usize) -> Vec<T> {
+    ///
+    /// This is the eager counterpart to [`Iterator::take`], which would consume the sequence.
+    pub fn take_vec(&mut self, n: usize) -> Vec<T> {
         let mut values = Vec::with_capacity(n);
 
         for _ in 0..n {
@@ -29,6 +196,223 @@ impl<T> Sequence<T> {
 
         values
     }
+
+    /// Returns the next `N` values in the sequence as an array.
+    ///
+    /// ```
+    /// # use malignius::Sequence;
+    /// # let mut usernames = Sequence::new(|n| format!("user{n}"));
+    /// let [alice, bob, carol] = usernames.take_array();
+    /// # assert_eq!([alice, bob, carol], ["user1", "user2", "user3"]);
+    /// ```
+    pub fn take_array<const N: usize>(&mut self) -> [T; N] {
+        std::array::from_fn(|_| self.next())
+    }
+
+    /// Returns an iterator over the next *n* values in the sequence.
+    ///
+    /// Unlike [`Sequence::take_vec`], the values are produced lazily as the iterator is consumed.
+    pub fn take_iter(&mut self, n: usize) -> impl Iterator<Item = T> + '_ {
+        (0..n).map(move |_| self.next())
+    }
+
+    /// Returns a checkpoint that the sequence can later be rewound to with
+    /// [`Sequence::rewind_to`].
+    pub fn checkpoint(&self) -> usize {
+        self.counter
+    }
+
+    /// Rewinds the sequence to the given checkpoint.
+    ///
+    /// Any values produced after the checkpoint was taken will be produced again.
+    pub fn rewind_to(&mut self, checkpoint: usize) {
+        self.counter = checkpoint;
+    }
+
+    /// Resets the sequence back to its initial state, so it produces the same values as a freshly
+    /// constructed one.
+    ///
+    /// The sequence starts over from its configured start, and reserved ranges are kept.
+    pub fn reset(&mut self) {
+        self.counter = self.start;
+    }
+
+    /// Reserves a range of indices so that the sequence will never produce values for them.
+    ///
+    /// This is useful when some of the values come from elsewhere, such as fixtures with
+    /// hardcoded ids. Values continue to be produced in strictly increasing order.
+    pub fn reserve(&mut self, range: Range<usize>) {
+        self.reserved.push(range);
+    }
+
+    /// Returns the index of the next value, skipping over any reserved ranges.
+    fn next_index(&self) -> usize {
+        let mut n = self.counter;
+        while let Some(range) = self.reserved.iter().find(|range| range.contains(&n)) {
+            n = range.end;
+        }
+
+        n
+    }
+}
+
+impl<T: Clone + 'static> Sequence<T> {
+    /// Creates a sequence that cycles through `values` in order, wrapping around after the last
+    /// one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    pub fn cycle(values: Vec<T>) -> Self {
+        assert!(
+            !values.is_empty(),
+            "cannot create a cyclic sequence from no values"
+        );
+
+        Self::new(move |n| values[cycle_offset(n, values.len())].clone())
+    }
+}
+
+#[cfg(feature = "rand")]
+impl<T: Clone + 'static> Sequence<T> {
+    /// Creates a sequence that yields a shuffled permutation of `values`, reshuffling each time
+    /// every value has been produced.
+    ///
+    /// The permutations are determined entirely by `seed`, so the same seed always yields the
+    /// same sequence of values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    pub fn shuffled(values: Vec<T>, seed: u64) -> Self {
+        use rand::rngs::StdRng;
+        use rand::seq::SliceRandom;
+        use rand::SeedableRng;
+
+        assert!(
+            !values.is_empty(),
+            "cannot create a shuffled sequence from no values"
+        );
+
+        Self::new(move |n| {
+            // Index `0` falls in the round before the one that starts at index `1`.
+            let round = (n.div_ceil(values.len()) as u64).wrapping_sub(1);
+
+            let mut indices = (0..values.len()).collect::<Vec<_>>();
+            indices.shuffle(&mut StdRng::seed_from_u64(seed.wrapping_add(round)));
+
+            values[indices[cycle_offset(n, values.len())]].clone()
+        })
+    }
+}
+
+#[cfg(feature = "strum")]
+impl<E: strum::IntoEnumIterator + 'static> Sequence<E> {
+    /// Creates a sequence that cycles through the variants of an enum in declaration order,
+    /// wrapping around after the last variant.
+    ///
+    /// # Panics
+    ///
+    /// Producing a value panics if the enum has no variants.
+    pub fn over_enum() -> Self {
+        Self::new(|n| {
+            let variant_count = E::iter().count();
+            assert!(
+                variant_count > 0,
+                "cannot cycle through an enum with no variants"
+            );
+
+            E::iter().nth(cycle_offset(n, variant_count)).unwrap()
+        })
+    }
+}
+
+impl Sequence<(usize, Vec<usize>)> {
+    /// Creates a sequence of parent ids, each paired with the ids of its children.
+    ///
+    /// `child_count` returns how many children the parent with the given id has. Child ids are
+    /// numbered consecutively across parents, so the children of parent `n` pick up where the
+    /// children of parent `n - 1` left off.
+    pub fn correlated(child_count: impl Fn(usize) -> usize + 'static) -> Self {
+        Self::new(move |parent_id| {
+            let first_child_id = 1 + (1..parent_id).map(&child_count).sum::<usize>();
+            let child_ids = (first_child_id..first_child_id + child_count(parent_id)).collect();
+
+            (parent_id, child_ids)
+        })
+    }
+}
+
+impl Sequence<usize> {
+    /// Creates a sequence that counts from `start` up to and including `end`, then wraps around.
+    ///
+    /// The value after `end` is `start` again, so `Sequence::ranged(1, 3)` produces
+    /// `1, 2, 3, 1, 2, ...`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is greater than `end`.
+    pub fn ranged(start: usize, end: usize) -> Self {
+        assert!(
+            start <= end,
+            "cannot create a ranged sequence from {start} to {end}"
+        );
+
+        let len = end - start + 1;
+
+        Self::new(move |n| start + cycle_offset(n, len))
+    }
+}
+
+impl Sequence<String> {
+    /// Creates a sequence of file paths under `base`: `base/file1`, `base/file2`, and so on.
+    ///
+    /// Any trailing slashes on `base` are ignored, so `tmp` and `tmp/` produce the same paths.
+    pub fn paths(base: impl Into<String>) -> Self {
+        let base = trim_trailing_slashes(base.into());
+
+        Self::new(move |n| format!("{base}/file{n}"))
+    }
+
+    /// Creates a sequence of URLs under `base`: `base/1`, `base/2`, and so on.
+    ///
+    /// Any trailing slashes on `base` are ignored, so `https://example.com/users` and
+    /// `https://example.com/users/` produce the same URLs.
+    pub fn urls(base: impl Into<String>) -> Self {
+        let base = trim_trailing_slashes(base.into());
+
+        Self::new(move |n| format!("{base}/{n}"))
+    }
+}
+
+impl<T> Iterator for Sequence<T> {
+    type Item = T;
+
+    /// Returns the next value in the sequence, which is always `Some`.
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(Sequence::next(self))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+fn trim_trailing_slashes(mut base: String) -> String {
+    base.truncate(base.trim_end_matches('/').len());
+    base
+}
+
+/// Returns the offset of index `n` into a cycle of `len` values, where index `1` is the first
+/// value.
+///
+/// Index `0`, which can be looked up with [`Sequence::nth`] or used as a start, wraps around to
+/// the last value rather than underflowing.
+fn cycle_offset(n: usize, len: usize) -> usize {
+    match n % len {
+        0 => len - 1,
+        offset => offset - 1,
+    }
 }
 
 #[cfg(test)]
@@ -49,7 +433,379 @@ mod tests {
     fn take_produces_multiple_values() {
         let mut usernames = Sequence::new(|n| format!("jsmith{n}"));
 
-        assert_eq!(usernames.take(3), vec!["jsmith1", "jsmith2", "jsmith3"]);
-        assert_eq!(usernames.take(2), vec!["jsmith4", "jsmith5"]);
+        assert_eq!(usernames.take_vec(3), vec!["jsmith1", "jsmith2", "jsmith3"]);
+        assert_eq!(usernames.take_vec(2), vec!["jsmith4", "jsmith5"]);
+    }
+
+    #[test]
+    fn clones_advance_independently() {
+        let mut ids = Sequence::new(|n| n);
+        assert_eq!(ids.take_vec(2), vec![1, 2]);
+
+        let mut happy_path = ids.clone();
+        let mut edge_case = ids.clone();
+
+        assert_eq!(happy_path.take_vec(2), vec![3, 4]);
+
+        assert_eq!(edge_case.next(), 3);
+        edge_case.advance(1);
+        assert_eq!(edge_case.next(), 5);
+
+        assert_eq!(ids.next(), 3);
+    }
+
+    #[test]
+    fn take_array_produces_a_fixed_number_of_values() {
+        let mut usernames = Sequence::new(|n| format!("jsmith{n}"));
+
+        let [first, second, third] = usernames.take_array();
+
+        assert_eq!(first, "jsmith1");
+        assert_eq!(second, "jsmith2");
+        assert_eq!(third, "jsmith3");
+        assert_eq!(usernames.next(), "jsmith4");
+    }
+
+    #[test]
+    fn map_transforms_the_produced_values() {
+        let mut doubled = Sequence::new(|n| n).map(|n| n * 2);
+
+        assert_eq!(doubled.take_vec(3), vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn map_continues_from_the_current_position() {
+        let mut ids = Sequence::new(|n| n);
+        ids.take_vec(2);
+
+        let mut labels = ids.map(|id| format!("#{id}"));
+
+        assert_eq!(labels.next(), "#3");
+        assert_eq!(labels.next(), "#4");
+    }
+
+    #[test]
+    fn nth_looks_up_a_value_without_advancing() {
+        let mut squares = Sequence::new(|n| n * n);
+        squares.next();
+
+        assert_eq!(squares.nth(5), 25);
+        assert_eq!(squares.current(), 2);
+        assert_eq!(squares.next(), 4);
+    }
+
+    #[test]
+    fn rewind_to_returns_to_a_checkpoint() {
+        let mut usernames = Sequence::new(|n| format!("jsmith{n}"));
+
+        assert_eq!(usernames.take_vec(2), vec!["jsmith1", "jsmith2"]);
+
+        let checkpoint = usernames.checkpoint();
+
+        assert_eq!(usernames.take_vec(2), vec!["jsmith3", "jsmith4"]);
+
+        usernames.rewind_to(checkpoint);
+
+        assert_eq!(usernames.take_vec(3), vec!["jsmith3", "jsmith4", "jsmith5"]);
+    }
+
+    #[test]
+    fn next_skips_reserved_ranges() {
+        let mut ids = Sequence::new(|n| n);
+
+        ids.reserve(3..6);
+        ids.reserve(6..8);
+
+        assert_eq!(ids.take_vec(4), vec![1, 2, 8, 9]);
+    }
+
+    #[test]
+    fn correlated_produces_parent_ids_with_their_child_ids() {
+        let mut posts = Sequence::correlated(|post_id| post_id + 1);
+
+        assert_eq!(posts.next(), (1, vec![1, 2]));
+        assert_eq!(posts.next(), (2, vec![3, 4, 5]));
+        assert_eq!(posts.next(), (3, vec![6, 7, 8, 9]));
+    }
+
+    #[test]
+    fn try_next_converts_a_panic_into_an_error() {
+        let names = ["alice", "bob"];
+        let mut usernames = Sequence::new(move |n| names[n - 1].to_string());
+
+        assert_eq!(usernames.try_next(), Ok("alice".to_string()));
+        assert_eq!(usernames.try_next(), Ok("bob".to_string()));
+
+        let err = usernames.try_next().unwrap_err();
+
+        assert_eq!(
+            err.context,
+            "producing value 3 of `Sequence<alloc::string::String>`"
+        );
+        assert!(err.message.contains("index out of bounds"));
+        assert_eq!(usernames.checkpoint(), 3);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn shuffled_is_deterministic_for_a_seed() {
+        let colors = || vec!["red", "green", "blue", "yellow", "purple"];
+
+        let first = Sequence::shuffled(colors(), 7).take_vec(10);
+        let second = Sequence::shuffled(colors(), 7).take_vec(10);
+
+        assert_eq!(first, second);
+
+        for round in first.chunks(5) {
+            let mut round = round.to_vec();
+            round.sort();
+
+            assert_eq!(round, vec!["blue", "green", "purple", "red", "yellow"]);
+        }
+
+        assert_ne!(Sequence::shuffled(colors(), 8).take_vec(10), first);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn shuffled_looks_up_index_zero_without_underflowing() {
+        let colors = || vec!["red", "green", "blue"];
+
+        // Index 0 is the last value of the round before the first, which is the first round of
+        // the previous seed.
+        assert_eq!(
+            Sequence::shuffled(colors(), 7).nth(0),
+            Sequence::shuffled(colors(), 6).nth(3)
+        );
+    }
+
+    #[test]
+    fn take_iter_produces_values_lazily() {
+        let mut ids = Sequence::new(|n| n);
+
+        let mut expected = 1;
+        for id in ids.take_iter(100_000) {
+            assert_eq!(id, expected);
+            expected += 1;
+        }
+
+        assert_eq!(expected, 100_001);
+
+        let mut lazy = ids.take_iter(10);
+        assert_eq!(lazy.next(), Some(100_001));
+        drop(lazy);
+
+        assert_eq!(ids.next(), 100_002);
+    }
+
+    #[test]
+    fn sequences_work_with_iterator_adapters() {
+        let mut ids = Sequence::new(|n| n);
+
+        let evens = ids
+            .by_ref()
+            .map(|n| format!("user{}", n * 2))
+            .take(3)
+            .collect::<Vec<_>>();
+
+        assert_eq!(evens, vec!["user2", "user4", "user6"]);
+
+        let pairs = ids
+            .by_ref()
+            .zip(Sequence::new(|n| n * 10))
+            .filter(|(n, _)| n % 2 == 0)
+            .take(2)
+            .collect::<Vec<_>>();
+
+        assert_eq!(pairs, vec![(4, 10), (6, 30)]);
+        assert_eq!(ids.take_vec(2), vec![7, 8]);
+    }
+
+    #[test]
+    fn current_returns_the_upcoming_index() {
+        let mut usernames = Sequence::new(|n| format!("jsmith{n}"));
+
+        assert_eq!(usernames.current(), 1);
+
+        usernames.take_vec(3);
+        assert_eq!(usernames.current(), 4);
+
+        assert_eq!(usernames.peek(), "jsmith4");
+        assert_eq!(usernames.current(), 4);
+    }
+
+    #[test]
+    fn advance_skips_values_without_producing_them() {
+        let produced = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut ids = Sequence::new({
+            let produced = produced.clone();
+            move |n| {
+                produced.set(produced.get() + 1);
+                n
+            }
+        });
+
+        ids.advance(5);
+
+        assert_eq!(produced.get(), 0);
+        assert_eq!(ids.next(), Sequence::new(|n| n).take_vec(6).pop().unwrap());
+    }
+
+    #[test]
+    fn advance_respects_the_step_and_reserved_ranges() {
+        let mut ids = Sequence::new(|n| n).with_step(2);
+        ids.reserve(5..9);
+
+        ids.advance(3);
+
+        assert_eq!(ids.next(), 11);
+    }
+
+    #[test]
+    fn cycle_wraps_around_to_the_start() {
+        let mut statuses = Sequence::cycle(vec!["a", "b", "c"]);
+
+        assert_eq!(
+            statuses.take_vec(7),
+            vec!["a", "b", "c", "a", "b", "c", "a"]
+        );
+    }
+
+    #[test]
+    fn cycle_wraps_index_zero_around_to_the_last_value() {
+        let statuses = Sequence::cycle(vec!["a", "b", "c"]);
+
+        assert_eq!(statuses.nth(0), "c");
+        assert_eq!(statuses.nth(1), "a");
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot create a cyclic sequence from no values")]
+    fn cycle_panics_without_values() {
+        Sequence::<&str>::cycle(Vec::new());
+    }
+
+    #[test]
+    fn with_start_begins_at_the_given_value() {
+        let mut ids = Sequence::with_start(100, |n| n);
+
+        assert_eq!(ids.take_vec(3), vec![100, 101, 102]);
+
+        ids.reset();
+
+        assert_eq!(ids.next(), 100);
+    }
+
+    #[test]
+    fn with_step_advances_by_the_step() {
+        let mut ids = Sequence::new(|n| n).with_step(5);
+
+        assert_eq!(ids.next(), 1);
+        assert_eq!(ids.next(), 6);
+        assert_eq!(ids.take_vec(2), vec![11, 16]);
+    }
+
+    #[test]
+    fn with_step_combines_with_with_start() {
+        let mut ids = Sequence::with_start(10, |n| n).with_step(10);
+
+        assert_eq!(ids.take_vec(3), vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn peek_returns_the_next_value_without_advancing() {
+        let mut emails = Sequence::new(|n| format!("user{n}@example.com"));
+
+        assert_eq!(emails.peek(), "user1@example.com");
+        assert_eq!(emails.peek(), "user1@example.com");
+        assert_eq!(emails.next(), "user1@example.com");
+        assert_eq!(emails.peek(), "user2@example.com");
+    }
+
+    #[test]
+    fn peek_skips_reserved_ranges() {
+        let mut ids = Sequence::new(|n| n);
+        ids.reserve(1..4);
+
+        assert_eq!(ids.peek(), 4);
+        assert_eq!(ids.next(), 4);
+    }
+
+    #[test]
+    fn reset_starts_the_sequence_over() {
+        let email = |n: usize| format!("user{n}@example.com");
+        let mut emails = Sequence::new(email);
+
+        emails.take_vec(3);
+        emails.reset();
+
+        assert_eq!(emails.next(), Sequence::new(email).next());
+        assert_eq!(emails.next(), "user2@example.com");
+    }
+
+    #[test]
+    fn ranged_wraps_around_after_the_end() {
+        let mut ids = Sequence::ranged(98, 100);
+
+        assert_eq!(ids.take_vec(7), vec![98, 99, 100, 98, 99, 100, 98]);
+    }
+
+    #[test]
+    fn ranged_wraps_index_zero_around_to_the_end() {
+        assert_eq!(Sequence::ranged(98, 100).nth(0), 100);
+    }
+
+    #[test]
+    fn ranged_with_a_single_value_repeats_it() {
+        assert_eq!(Sequence::ranged(7, 7).take_vec(3), vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn paths_join_the_base_with_a_single_separator() {
+        assert_eq!(
+            Sequence::paths("/tmp/uploads").take_vec(2),
+            vec!["/tmp/uploads/file1", "/tmp/uploads/file2"]
+        );
+        assert_eq!(
+            Sequence::paths("/tmp/uploads/").take_vec(2),
+            vec!["/tmp/uploads/file1", "/tmp/uploads/file2"]
+        );
+    }
+
+    #[test]
+    fn urls_join_the_base_with_a_single_separator() {
+        assert_eq!(
+            Sequence::urls("https://example.com/users").take_vec(2),
+            vec!["https://example.com/users/1", "https://example.com/users/2"]
+        );
+        assert_eq!(
+            Sequence::urls("https://example.com/users//").take_vec(2),
+            vec!["https://example.com/users/1", "https://example.com/users/2"]
+        );
+    }
+
+    #[cfg(feature = "strum")]
+    #[test]
+    fn over_enum_cycles_through_the_variants() {
+        #[derive(Debug, PartialEq, Eq, strum::EnumIter)]
+        enum Status {
+            Draft,
+            Published,
+            Archived,
+        }
+
+        let mut statuses = Sequence::<Status>::over_enum();
+
+        assert_eq!(
+            statuses.take_vec(5),
+            vec![
+                Status::Draft,
+                Status::Published,
+                Status::Archived,
+                Status::Draft,
+                Status::Published
+            ]
+        );
+        assert_eq!(statuses.nth(0), Status::Archived);
     }
 }
@@ -0,0 +1,457 @@
+//! A process-global registry of named sequence counters.
+//!
+//! Counters are grouped into namespaces so that the sequences used by one group of tests can
+//! be reset without affecting any others.
+//!
+//! ```
+//! # use malignius::sequences;
+//! let invoice_number = sequences::namespace("billing").next("invoice", |n| format!("INV-{n}"));
+//!
+//! sequences::reset_namespace("billing");
+//! # assert_eq!(invoice_number, "INV-1");
+//! ```
+//!
+//! Sequences can also be scoped to a single persist call with [`per_persist`], which restarts
+//! them for every top-level persist.
+//!
+//! Named sequences registered with [`next_named`] keep their producer, so every call site using
+//! the same name draws from the same sequence:
+//!
+//! ```
+//! let email = malignius::sequences::next_named("email", |n| format!("user{n}@example.com"));
+//! # assert_eq!(email, "user1@example.com");
+//! ```
+//!
+//! [`reset_all`] starts every sequence in this module over, both the named ones and the ones in
+//! namespaces.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::scope::Scoped;
+
+type Counters = HashMap<String, HashMap<String, usize>>;
+
+static COUNTERS: Mutex<Option<Counters>> = Mutex::new(None);
+
+type PersistCounters = Rc<RefCell<HashMap<String, usize>>>;
+
+thread_local! {
+    static PERSIST_COUNTERS: RefCell<Option<PersistCounters>> = const { RefCell::new(None) };
+}
+
+fn with_counters<R>(f: impl FnOnce(&mut Counters) -> R) -> R {
+    f(COUNTERS.lock().unwrap().get_or_insert_with(HashMap::new))
+}
+
+/// A namespace of named sequence counters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace {
+    name: String,
+}
+
+/// Returns the namespace with the given name.
+pub fn namespace(name: impl Into<String>) -> Namespace {
+    Namespace { name: name.into() }
+}
+
+impl Namespace {
+    /// Returns the next value of the named sequence in this namespace.
+    ///
+    /// Counters start at `1` and are shared by every caller using the same namespace and name.
+    pub fn next<T>(&self, name: &str, produce: impl FnOnce(usize) -> T) -> T {
+        let n = with_counters(|counters| {
+            let counter = counters
+                .entry(self.name.clone())
+                .or_default()
+                .entry(name.to_owned())
+                .or_insert(1);
+
+            let n = *counter;
+            *counter += 1;
+
+            n
+        });
+
+        produce(n)
+    }
+
+    /// Resets every sequence in this namespace back to the start.
+    pub fn reset(&self) {
+        reset_namespace(&self.name);
+    }
+}
+
+/// Resets every sequence in the named namespace back to the start.
+pub fn reset_namespace(name: &str) {
+    with_counters(|counters| {
+        counters.remove(name);
+    });
+}
+
+/// Returns the next value of the named sequence in the current persist call.
+///
+/// Counters start at `1` at the beginning of every top-level persist and are shared by the entity
+/// and all of its associations. This is useful for numbering that restarts per entity, such as
+/// the line items of an order. Outside of a persist call, every value is the first one.
+///
+/// ```
+/// # use malignius::sequences;
+/// let position = sequences::per_persist("line_item", |n| n);
+/// # assert_eq!(position, 1);
+/// ```
+pub fn per_persist<T>(name: &str, produce: impl FnOnce(usize) -> T) -> T {
+    let n = PERSIST_COUNTERS.with_borrow(|counters| match counters {
+        Some(counters) => {
+            let mut counters = counters.borrow_mut();
+            let counter = counters.entry(name.to_owned()).or_insert(1);
+
+            let n = *counter;
+            *counter += 1;
+
+            n
+        }
+        None => 1,
+    });
+
+    produce(n)
+}
+
+/// Runs a persist call, starting fresh [`per_persist`] counters unless it is nested in another.
+pub(crate) async fn in_persist_scope<F: Future>(future: F) -> F::Output {
+    if PERSIST_COUNTERS.with_borrow(|counters| counters.is_some()) {
+        return future.await;
+    }
+
+    Scoped::new(&PERSIST_COUNTERS, Rc::default(), future).await
+}
+
+/// Returns a copy of the current [`per_persist`] counters, to be replayed with
+/// [`replaying_per_persist`].
+pub(crate) fn per_persist_checkpoint() -> HashMap<String, usize> {
+    PERSIST_COUNTERS.with_borrow(|counters| {
+        counters
+            .as_ref()
+            .map(|counters| counters.borrow().clone())
+            .unwrap_or_default()
+    })
+}
+
+/// Runs the future with [`per_persist`] counters restored from a checkpoint, leaving the counters
+/// of the enclosing persist call untouched.
+///
+/// This is used when an association is manifested again to be persisted, so that it draws the
+/// same values as when it was first manifested.
+pub(crate) async fn replaying_per_persist<F: Future>(
+    checkpoint: HashMap<String, usize>,
+    future: F,
+) -> F::Output {
+    Scoped::new(&PERSIST_COUNTERS, Rc::new(RefCell::new(checkpoint)), future).await
+}
+
+type ProduceFn<T> = Arc<dyn Fn(usize) -> T + Send + Sync>;
+
+struct NamedSequence {
+    counter: usize,
+    /// The [`ProduceFn`] the sequence was registered with.
+    produce: Box<dyn Any + Send>,
+}
+
+/// A registry of named sequences, each with its own counter and producer.
+///
+/// The process-global registry is used through [`next_named`], [`reset_named`] and
+/// [`reset_all`].
+pub struct SequenceRegistry {
+    sequences: Mutex<Option<HashMap<String, NamedSequence>>>,
+}
+
+impl Default for SequenceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SequenceRegistry {
+    pub const fn new() -> Self {
+        Self {
+            sequences: Mutex::new(None),
+        }
+    }
+
+    /// Returns the next value of the named sequence, registering it with `produce` if this is
+    /// the first time the name is used.
+    ///
+    /// The producer is only used when the name is first registered; later calls use the
+    /// registered producer and ignore the one they were given.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name was registered with a producer for a different type.
+    pub fn next<T: 'static>(
+        &self,
+        name: &str,
+        produce: impl Fn(usize) -> T + Send + Sync + 'static,
+    ) -> T {
+        let next = {
+            let mut sequences = self.sequences.lock().unwrap();
+            let sequence = sequences
+                .get_or_insert_with(HashMap::new)
+                .entry(name.to_owned())
+                .or_insert_with(|| NamedSequence {
+                    counter: 1,
+                    produce: Box::new(Arc::new(produce) as ProduceFn<T>),
+                });
+
+            sequence
+                .produce
+                .downcast_ref::<ProduceFn<T>>()
+                .cloned()
+                .map(|produce| {
+                    let n = sequence.counter;
+                    sequence.counter += 1;
+
+                    (n, produce)
+                })
+        };
+
+        // The lock is released first, so that panicking doesn't poison it and producers can use
+        // other named sequences.
+        let (n, produce) = next
+            .unwrap_or_else(|| panic!("sequence `{name}` was registered with a different type"));
+        produce(n)
+    }
+
+    /// Removes the named sequence, so that it starts over with a new producer.
+    pub fn reset(&self, name: &str) {
+        if let Some(sequences) = self.sequences.lock().unwrap().as_mut() {
+            sequences.remove(name);
+        }
+    }
+
+    /// Removes every sequence in the registry.
+    pub fn reset_all(&self) {
+        self.sequences.lock().unwrap().take();
+    }
+}
+
+static REGISTRY: SequenceRegistry = SequenceRegistry::new();
+
+/// Returns the next value of the named sequence in the process-global [`SequenceRegistry`].
+///
+/// The producer is only used when the name is first registered; later calls with the same name
+/// share its counter and producer, wherever they are made.
+///
+/// # Panics
+///
+/// Panics if the name was registered with a producer for a different type.
+pub fn next_named<T: 'static>(
+    name: &str,
+    produce: impl Fn(usize) -> T + Send + Sync + 'static,
+) -> T {
+    REGISTRY.next(name, produce)
+}
+
+/// Removes the named sequence from the process-global registry.
+pub fn reset_named(name: &str) {
+    REGISTRY.reset(name);
+}
+
+/// Removes every sequence from the process-global registry, and resets every [`Namespace`].
+pub fn reset_all() {
+    REGISTRY.reset_all();
+    with_counters(|counters| counters.clear());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{association, persist, Associations, Manifest, Persist};
+
+    use super::*;
+
+    /// Records the position of every persisted line item.
+    #[derive(Default)]
+    struct TestContext {
+        pub positions: Mutex<Vec<usize>>,
+    }
+
+    #[derive(Debug, Default)]
+    struct LineItemOverrides {}
+
+    #[derive(Debug)]
+    struct LineItem {
+        pub position: usize,
+    }
+
+    impl Manifest for LineItem {
+        type Context = TestContext;
+        type Overrides = LineItemOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    position: per_persist("line_item", |n| n),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for LineItem {
+        type Err = std::convert::Infallible;
+
+        async fn persist(ctx: &Self::Context, line_item: Self) -> Result<Self, Self::Err> {
+            ctx.positions.lock().unwrap().push(line_item.position);
+
+            Ok(line_item)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct OrderOverrides {}
+
+    #[derive(Debug)]
+    struct Order {
+        pub line_item_positions: Vec<usize>,
+    }
+
+    impl Manifest for Order {
+        type Context = TestContext;
+        type Overrides = OrderOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let line_item_positions = (0..3)
+                .map(|_| association::<LineItem>(&mut associations).position)
+                .collect();
+
+            (
+                Self {
+                    line_item_positions,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Order {
+        type Err = std::convert::Infallible;
+
+        async fn persist(_ctx: &Self::Context, order: Self) -> Result<Self, Self::Err> {
+            Ok(order)
+        }
+    }
+
+    #[tokio::test]
+    async fn per_persist_sequences_restart_for_every_persist_call(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = Arc::new(TestContext::default());
+
+        let first: Order = persist(ctx.clone()).await?;
+        let second: Order = persist(ctx.clone()).await?;
+
+        assert_eq!(first.line_item_positions, vec![1, 2, 3]);
+        assert_eq!(second.line_item_positions, vec![1, 2, 3]);
+        assert_eq!(*ctx.positions.lock().unwrap(), vec![1, 2, 3, 1, 2, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn resetting_a_namespace_does_not_affect_other_namespaces() {
+        let billing = namespace("resetting_a_namespace::billing");
+        let shipping = namespace("resetting_a_namespace::shipping");
+
+        let invoice = |n| format!("INV-{n}");
+        let parcel = |n| format!("PCL-{n}");
+
+        assert_eq!(billing.next("invoice", invoice), "INV-1");
+        assert_eq!(billing.next("invoice", invoice), "INV-2");
+        assert_eq!(shipping.next("parcel", parcel), "PCL-1");
+        assert_eq!(shipping.next("parcel", parcel), "PCL-2");
+
+        reset_namespace("resetting_a_namespace::billing");
+
+        assert_eq!(billing.next("invoice", invoice), "INV-1");
+        assert_eq!(shipping.next("parcel", parcel), "PCL-3");
+    }
+
+    fn email() -> String {
+        next_named("shared_counter::email", |n| format!("user{n}@example.com"))
+    }
+
+    #[test]
+    fn named_sequences_are_shared_between_call_sites() {
+        assert_eq!(email(), "user1@example.com");
+        assert_eq!(
+            next_named("shared_counter::email", |n| format!("other{n}@example.com")),
+            "user2@example.com"
+        );
+        assert_eq!(email(), "user3@example.com");
+
+        reset_named("shared_counter::email");
+
+        assert_eq!(
+            next_named("shared_counter::email", |n| format!("other{n}@example.com")),
+            "other1@example.com"
+        );
+    }
+
+    #[test]
+    fn reset_all_clears_every_named_sequence() {
+        let registry = SequenceRegistry::new();
+
+        assert_eq!(registry.next("id", |n| n), 1);
+        assert_eq!(registry.next("id", |n| n), 2);
+        assert_eq!(registry.next("email", |n| format!("user{n}")), "user1");
+
+        registry.reset_all();
+
+        assert_eq!(registry.next("id", |n| n * 10), 10);
+        assert_eq!(registry.next("email", |n| format!("admin{n}")), "admin1");
+    }
+
+    #[test]
+    #[should_panic(expected = "sequence `id` was registered with a different type")]
+    fn named_sequences_cannot_change_type() {
+        let registry = SequenceRegistry::new();
+
+        registry.next("id", |n| n);
+        registry.next("id", |n| n.to_string());
+    }
+
+    #[test]
+    fn registries_keep_working_after_a_type_mismatch() {
+        let registry = SequenceRegistry::new();
+
+        assert_eq!(registry.next("id", |n| n), 1);
+
+        let mismatch = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            registry.next("id", |n| n.to_string())
+        }));
+        assert!(mismatch.is_err());
+
+        assert_eq!(registry.next("id", |n| n), 2);
+        registry.reset("id");
+        assert_eq!(registry.next("id", |n| n.to_string()), "1");
+    }
+
+    #[test]
+    fn sequences_with_the_same_name_in_different_namespaces_are_independent() {
+        let first = namespace("same_name::first");
+        let second = namespace("same_name::second");
+
+        assert_eq!(first.next("id", |n| n), 1);
+        assert_eq!(first.next("id", |n| n), 2);
+        assert_eq!(second.next("id", |n| n), 1);
+
+        second.reset();
+
+        assert_eq!(first.next("id", |n| n), 3);
+    }
+}
@@ -0,0 +1,125 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::{graph_path, Associations, Manifest};
+
+/// A container of services that factories can call out to while manifesting.
+///
+/// Services are looked up by type, so trait objects should be stored behind a concrete type
+/// such as `Box<dyn PasswordHasher>`.
+#[derive(Default)]
+pub struct ManifestServices {
+    services: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl ManifestServices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a service, replacing any existing service of the same type.
+    pub fn insert<S: 'static>(&mut self, service: S) -> &mut Self {
+        self.services.insert(TypeId::of::<S>(), Box::new(service));
+        self
+    }
+
+    /// Returns the service of type `S`, if one was provided.
+    pub fn get<S: 'static>(&self) -> Option<&S> {
+        self.services
+            .get(&TypeId::of::<S>())
+            .and_then(|service| service.downcast_ref())
+    }
+}
+
+/// A [`Manifest`] that uses services from a [`ManifestServices`] container.
+pub trait ManifestWithServices: Manifest {
+    fn manifest_with_services(
+        overrides: Self::Overrides,
+        services: &ManifestServices,
+    ) -> (Self, Associations<Self::Context>)
+    where
+        Self: Sized;
+}
+
+/// Manifests an entity with the given overrides, making the given services available to its
+/// factory.
+///
+/// Like [`manifest_with`](crate::manifest_with), any associations the entity declares are
+/// discarded.
+pub fn manifest_with_services<T: ManifestWithServices>(
+    overrides: T::Overrides,
+    services: &ManifestServices,
+) -> T {
+    let (entity, _) =
+        graph_path::with_root::<T, _>(|| T::manifest_with_services(overrides, services));
+    entity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait PasswordHasher {
+        fn hash(&self, password: &str) -> String;
+    }
+
+    struct ReversingHasher;
+
+    impl PasswordHasher for ReversingHasher {
+        fn hash(&self, password: &str) -> String {
+            password.chars().rev().collect()
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Account {
+        pub username: String,
+        pub password_hash: String,
+    }
+
+    impl Manifest for Account {
+        type Context = ();
+        type Overrides = Option<String>;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            Self::manifest_with_services(overrides, &ManifestServices::new())
+        }
+    }
+
+    impl ManifestWithServices for Account {
+        fn manifest_with_services(
+            username: Self::Overrides,
+            services: &ManifestServices,
+        ) -> (Self, Associations<Self::Context>) {
+            let password = "hunter2";
+            let password_hash = match services.get::<Box<dyn PasswordHasher>>() {
+                Some(hasher) => hasher.hash(password),
+                None => password.to_owned(),
+            };
+
+            (
+                Self {
+                    username: username.unwrap_or_else(|| "jsmith".into()),
+                    password_hash,
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[test]
+    fn manifest_with_services_uses_the_provided_services() {
+        let mut services = ManifestServices::new();
+        services.insert::<Box<dyn PasswordHasher>>(Box::new(ReversingHasher));
+
+        let account: Account = manifest_with_services(Some("asmith".into()), &services);
+
+        assert_eq!(
+            account,
+            Account {
+                username: "asmith".into(),
+                password_hash: "2retnuh".into()
+            }
+        );
+    }
+}
@@ -0,0 +1,461 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::scope::Scoped;
+use crate::{
+    association, configured_options, graph_path, persist_manifested, AssociationError,
+    Associations, GraphShape, InitialOverrides, MaligniusError, Persist,
+};
+
+thread_local! {
+    static CURRENT_SESSION: RefCell<Option<Session>> = const { RefCell::new(None) };
+}
+
+struct SessionEntry {
+    /// The entity as it was manifested, which is handed to parents until it is persisted.
+    manifested: Box<dyn Any>,
+    /// The shape of the manifested entity's graph.
+    shape: GraphShape,
+    /// The manifested entity and its associations, until whichever parent persists first
+    /// persists them.
+    pending: futures::lock::Mutex<Option<Box<dyn Any>>>,
+    persisted: RefCell<Option<Box<dyn Any>>>,
+}
+
+/// A handle for sharing singleton associations between persists.
+///
+/// Entities registered with [`session_association`] are manifested and persisted at most once
+/// per session, no matter how many parents reference them or how many times the parents are
+/// persisted through the session.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Account;
+/// # impl Manifest for Account {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Account {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, account: Self) -> Result<Self, Self::Err> {
+/// #         Ok(account)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Post;
+/// # impl Manifest for Post {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Account = session_association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl Persist for Post {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+/// #         Ok(post)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Comment;
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         let mut associations = Associations::new();
+/// #         let _: Account = session_association(&mut associations);
+/// #         (Self, associations)
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// # let counter = PersistCounter::start();
+/// let session = Session::new();
+///
+/// let post: Post = session.persist(ctx.clone()).await?;
+/// let comment: Comment = session.persist(ctx.clone()).await?;
+/// # assert_eq!(counter.total(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct Session {
+    entries: Rc<RefCell<HashMap<TypeId, Rc<SessionEntry>>>>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Persists an entity within the session.
+    pub async fn persist<T: Persist + 'static>(
+        &self,
+        ctx: Arc<T::Context>,
+    ) -> Result<T, MaligniusError<T::Err>> {
+        self.persist_with(ctx, T::Overrides::initial()).await
+    }
+
+    /// Persists an entity with the given overrides within the session.
+    pub async fn persist_with<T: Persist + 'static>(
+        &self,
+        ctx: Arc<T::Context>,
+        overrides: T::Overrides,
+    ) -> Result<T, MaligniusError<T::Err>> {
+        Scoped::new(
+            &CURRENT_SESSION,
+            self.clone(),
+            crate::persist_with::<T>(ctx, overrides),
+        )
+        .await
+    }
+}
+
+/// Registers a singleton association that is persisted at most once per [`Session`].
+///
+/// Every parent in the session that calls this for `T` gets the same entity. Outside of a
+/// session this behaves like [`association`].
+///
+/// ```
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Account;
+/// # impl Manifest for Account {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Account {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, account: Self) -> Result<Self, Self::Err> {
+/// #         Ok(account)
+/// #     }
+/// # }
+/// # let mut associations = Associations::new();
+/// let system_account: Account = session_association(&mut associations);
+/// ```
+pub fn session_association<T>(associations: &mut Associations<T::Context>) -> T
+where
+    T: Persist + Clone + 'static,
+    T::Err: std::error::Error + 'static,
+{
+    let Some(session) = CURRENT_SESSION.with_borrow(|session| session.clone()) else {
+        return association::<T>(associations);
+    };
+
+    let entry = session.entries.borrow().get(&TypeId::of::<T>()).cloned();
+    let entry = entry.unwrap_or_else(|| {
+        let path = graph_path().map(|path| path.child::<T>(associations.associations.len()));
+        let manifested = match path {
+            Some(path) => graph_path::with_path(path, || T::manifest(T::Overrides::initial())),
+            None => T::manifest(T::Overrides::initial()),
+        };
+
+        let entry = Rc::new(SessionEntry {
+            manifested: Box::new(manifested.0.clone()),
+            shape: GraphShape::manifested::<T, _>(&manifested.1),
+            pending: futures::lock::Mutex::new(Some(Box::new(manifested))),
+            persisted: RefCell::new(None),
+        });
+        session
+            .entries
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), entry.clone());
+
+        entry
+    });
+
+    if let Some(persisted) = entry.persisted.borrow().as_ref() {
+        return persisted.downcast_ref::<T>().unwrap().clone();
+    }
+
+    let entity = entry.manifested.downcast_ref::<T>().unwrap().clone();
+
+    // Every parent registers the association, since whichever of them is persisted first needs
+    // the singleton to already exist.
+    associations.persist::<T, _>(entry.shape.clone(), move |ctx| {
+        Box::pin(async move {
+            let mut pending = entry.pending.lock().await;
+            if let Some(persisted) = entry.persisted.borrow().as_ref() {
+                return Ok(persisted.downcast_ref::<T>().unwrap().clone());
+            }
+
+            let Some(manifested) = pending.take() else {
+                return Err(AssociationError::new::<T>(Box::new(EarlierPersistFailed)).into());
+            };
+            let (entity, associations) = *manifested
+                .downcast::<(T, Associations<T::Context>)>()
+                .unwrap();
+
+            let persisted = persist_manifested(
+                ctx,
+                vec![(entity, associations)],
+                &configured_options::<T>(),
+            )
+            .await;
+            let mut persisted = match persisted {
+                Ok(persisted) => persisted,
+                Err(err) => {
+                    // The associations of the singleton were consumed by the failed persist, so
+                    // the entry is forgotten and the next persist in the session starts over.
+                    let mut entries = session.entries.borrow_mut();
+                    if entries
+                        .get(&TypeId::of::<T>())
+                        .is_some_and(|current| Rc::ptr_eq(current, &entry))
+                    {
+                        entries.remove(&TypeId::of::<T>());
+                    }
+
+                    return Err(err.into_association_error::<T>().into());
+                }
+            };
+
+            let entity = persisted.remove(0);
+            *entry.persisted.borrow_mut() = Some(Box::new(entity.clone()));
+
+            Ok(entity)
+        })
+    });
+
+    entity
+}
+
+/// The error returned to parents that were waiting on a session association whose persist
+/// failed.
+#[derive(Debug)]
+struct EarlierPersistFailed;
+
+impl fmt::Display for EarlierPersistFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an earlier persist of the session association failed")
+    }
+}
+
+impl std::error::Error for EarlierPersistFailed {}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::params;
+
+    use crate::test_support::TestContext;
+    use crate::{persist, Manifest};
+
+    use super::*;
+
+    const SCHEMA: &str = r#"
+                    create table account (
+                        name text primary key
+                    );
+
+                    create table post (
+                        id integer primary key,
+                        account_name text not null references account (name)
+                    );
+
+                    create table comment (
+                        id integer primary key,
+                        account_name text not null references account (name)
+                    );
+                "#;
+
+    #[derive(Debug, Default)]
+    struct AccountOverrides {}
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Account {
+        pub name: String,
+    }
+
+    impl Manifest for Account {
+        type Context = TestContext;
+        type Overrides = AccountOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    name: "system".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Account {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, account: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into account (name) values ($1)",
+                params![account.name],
+            )?;
+
+            Ok(account)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct PostOverrides {}
+
+    #[derive(Debug)]
+    struct Post {
+        pub account_name: String,
+    }
+
+    impl Manifest for Post {
+        type Context = TestContext;
+        type Overrides = PostOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let account: Account = session_association(&mut associations);
+
+            (
+                Self {
+                    account_name: account.name,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Post {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into post (account_name) values ($1)",
+                params![post.account_name],
+            )?;
+
+            Ok(post)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct CommentOverrides {}
+
+    #[derive(Debug)]
+    struct Comment {
+        pub account_name: String,
+    }
+
+    impl Manifest for Comment {
+        type Context = TestContext;
+        type Overrides = CommentOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let _post: Post = association(&mut associations);
+            let account: Account = session_association(&mut associations);
+
+            (
+                Self {
+                    account_name: account.name,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Comment {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into comment (account_name) values ($1)",
+                params![comment.account_name],
+            )?;
+
+            Ok(comment)
+        }
+    }
+
+    #[tokio::test]
+    async fn session_associations_are_persisted_once_per_session(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+        let session = Session::new();
+
+        let post: Post = session.persist(ctx.clone()).await?;
+        let comment: Comment = session.persist(ctx.clone()).await?;
+
+        assert_eq!(post.account_name, "system");
+        assert_eq!(comment.account_name, "system");
+
+        assert_eq!(ctx.count("select count(*) from account")?, 1);
+        assert_eq!(ctx.count("select count(*) from post")?, 2);
+        assert_eq!(ctx.count("select count(*) from comment")?, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn session_associations_are_shared_within_a_graph(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let _comment: Comment = Session::new().persist(ctx.clone()).await?;
+
+        assert_eq!(ctx.count("select count(*) from account")?, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn session_associations_are_regular_associations_outside_of_a_session(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let _post: Post = persist(ctx.clone()).await?;
+        let result = persist::<Post>(ctx.clone()).await;
+
+        // The second call inserts the account again, which violates its primary key.
+        assert!(matches!(result, Err(MaligniusError::Association(_))));
+        assert_eq!(ctx.count("select count(*) from account")?, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn session_associations_can_be_retried_after_failing(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+        let session = Session::new();
+
+        // Without an `account` table the singleton fails to persist.
+        ctx.conn.execute("drop table account", ())?;
+        let result = session.persist::<Post>(ctx.clone()).await;
+        assert!(matches!(result, Err(MaligniusError::Association(_))));
+
+        ctx.conn
+            .execute("create table account (name text primary key)", ())?;
+        let post: Post = session.persist(ctx.clone()).await?;
+        let comment: Comment = session.persist(ctx.clone()).await?;
+
+        assert_eq!(post.account_name, "system");
+        assert_eq!(comment.account_name, "system");
+        assert_eq!(ctx.count("select count(*) from account")?, 1);
+
+        Ok(())
+    }
+}
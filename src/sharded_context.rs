@@ -0,0 +1,225 @@
+use std::any::{type_name, TypeId};
+use std::collections::HashMap;
+
+/// A context that routes each entity type to its own shard, such as a separate database
+/// connection.
+///
+/// Every entity in the graph shares the `ShardedContext` as its context, and each
+/// [`Persist`](crate::Persist) implementation looks up the shard for its own type, so
+/// associations are persisted to the right shard regardless of which entity they belong to.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::{persist, Associations, Manifest, Persist, ShardedContext};
+/// # use rusqlite::{params, Connection};
+/// # struct Post {
+/// #     title: String,
+/// # }
+/// # impl Manifest for Post {
+/// #     type Context = ShardedContext<Connection>;
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<Self::Context>) {
+/// #         (Self { title: "Solarpunk".into() }, Associations::new())
+/// #     }
+/// # }
+/// impl Persist for Post {
+///     type Err = rusqlite::Error;
+///
+///     async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+///         ctx.shard::<Self>()
+///             .execute("insert into post (title) values ($1)", params![post.title])?;
+///
+///         Ok(post)
+///     }
+/// }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let posts = Connection::open_in_memory()?;
+/// # posts.execute_batch("create table post (title text not null)")?;
+/// # let mut ctx = ShardedContext::new();
+/// # ctx.insert::<Post>(posts);
+/// # let _: Post = persist(Arc::new(ctx)).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ShardedContext<C> {
+    shards: HashMap<TypeId, C>,
+}
+
+impl<C> Default for ShardedContext<C> {
+    fn default() -> Self {
+        Self {
+            shards: HashMap::new(),
+        }
+    }
+}
+
+impl<C> ShardedContext<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the shard that entities of type `T` are persisted to, replacing any existing shard
+    /// for that type.
+    pub fn insert<T: 'static>(&mut self, shard: C) -> &mut Self {
+        self.shards.insert(TypeId::of::<T>(), shard);
+        self
+    }
+
+    /// Returns the shard for entities of type `T`, if one was provided.
+    pub fn get<T: 'static>(&self) -> Option<&C> {
+        self.shards.get(&TypeId::of::<T>())
+    }
+
+    /// Returns the shard for entities of type `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no shard was provided for `T`.
+    pub fn shard<T: 'static>(&self) -> &C {
+        self.get::<T>()
+            .unwrap_or_else(|| panic!("no shard was provided for `{}`", type_name::<T>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rusqlite::{params, Connection};
+
+    use crate::test_support::shared;
+    use crate::{association, persist, Associations, Manifest, Persist};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct AuthorOverrides {}
+
+    #[derive(Debug)]
+    struct Author {
+        pub name: String,
+    }
+
+    impl Manifest for Author {
+        type Context = ShardedContext<Connection>;
+        type Overrides = AuthorOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    name: "Ursula K. Le Guin".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Author {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            ctx.shard::<Self>().execute(
+                "insert into author (name) values ($1)",
+                params![author.name],
+            )?;
+
+            Ok(author)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct PostOverrides {}
+
+    #[derive(Debug)]
+    struct Post {
+        pub title: String,
+        pub author_name: String,
+    }
+
+    impl Manifest for Post {
+        type Context = ShardedContext<Connection>;
+        type Overrides = PostOverrides;
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author: Author = association(&mut associations);
+
+            (
+                Self {
+                    title: "The Ones Who Walk Away from Omelas".into(),
+                    author_name: author.name,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Post {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            ctx.shard::<Self>().execute(
+                "insert into post (title, author_name) values ($1, $2)",
+                params![post.title, post.author_name],
+            )?;
+
+            Ok(post)
+        }
+    }
+
+    fn count_rows(conn: &Connection, table: &str) -> rusqlite::Result<usize> {
+        conn.query_row(
+            &format!(
+                "select count(*) from sqlite_master where type = 'table' and name = '{table}'"
+            ),
+            [],
+            |row| row.get::<_, usize>(0),
+        )
+        .and_then(|exists| match exists {
+            0 => Ok(0),
+            _ => conn.query_row(&format!("select count(*) from {table}"), [], |row| {
+                row.get(0)
+            }),
+        })
+    }
+
+    #[tokio::test]
+    async fn entities_are_persisted_to_the_shard_for_their_type(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let authors = Connection::open(":memory:")?;
+        authors.execute("create table author (name text not null)", ())?;
+
+        let posts = Connection::open(":memory:")?;
+        posts.execute(
+            "create table post (title text not null, author_name text not null)",
+            (),
+        )?;
+
+        let mut ctx = ShardedContext::new();
+        ctx.insert::<Author>(authors).insert::<Post>(posts);
+
+        let ctx = shared(ctx);
+
+        let post: Post = persist(ctx.clone()).await?;
+
+        let authors = ctx.shard::<Author>();
+        let posts = ctx.shard::<Post>();
+
+        assert_eq!(count_rows(authors, "author")?, 1);
+        assert_eq!(count_rows(authors, "post")?, 0);
+        assert_eq!(count_rows(posts, "post")?, 1);
+        assert_eq!(count_rows(posts, "author")?, 0);
+
+        let author_name: String =
+            posts.query_row("select author_name from post", [], |row| row.get(0))?;
+        assert_eq!(author_name, post.author_name);
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "no shard was provided for")]
+    fn shard_panics_for_types_without_a_shard() {
+        ShardedContext::<Connection>::new().shard::<Author>();
+    }
+}
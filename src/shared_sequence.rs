@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A [`Sequence`](crate::Sequence) that can be shared between threads and tasks.
+///
+/// Clones share the same counter, so every call to [`SharedSequence::next`] on any clone
+/// produces a value from a distinct index.
+///
+/// ```
+/// # use malignius::SharedSequence;
+/// # #[tokio::main]
+/// # async fn main() {
+/// let emails = SharedSequence::new(|n| format!("user{n}@example.com"));
+///
+/// tokio::spawn({
+///     let emails = emails.clone();
+///     async move { emails.next() }
+/// });
+/// # }
+/// ```
+pub struct SharedSequence<T> {
+    counter: Arc<AtomicUsize>,
+    produce: Arc<dyn Fn(usize) -> T + Send + Sync>,
+}
+
+impl<T> SharedSequence<T> {
+    pub fn new(produce: impl Fn(usize) -> T + Send + Sync + 'static) -> Self {
+        Self {
+            counter: Arc::new(AtomicUsize::new(1)),
+            produce: Arc::new(produce),
+        }
+    }
+
+    /// Returns the next value in the sequence.
+    pub fn next(&self) -> T {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed);
+
+        (self.produce)(n)
+    }
+}
+
+impl<T> Clone for SharedSequence<T> {
+    fn clone(&self) -> Self {
+        Self {
+            counter: self.counter.clone(),
+            produce: self.produce.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::thread;
+
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn shared_sequences_are_send_and_sync() {
+        assert_send_sync::<SharedSequence<String>>();
+    }
+
+    #[test]
+    fn shared_sequences_produce_distinct_values_across_threads() {
+        let ids = SharedSequence::new(|n| n);
+
+        let threads = (0..8)
+            .map(|_| {
+                let ids = ids.clone();
+                thread::spawn(move || (0..1_000).map(|_| ids.next()).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+
+        let ids = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect::<HashSet<_>>();
+
+        assert_eq!(ids.len(), 8_000);
+        assert_eq!(ids.iter().min(), Some(&1));
+        assert_eq!(ids.iter().max(), Some(&8_000));
+    }
+}
@@ -0,0 +1,236 @@
+use std::convert::Infallible;
+
+use crate::{CapturingContext, SqlValue};
+
+/// A connection that can execute SQL statements.
+///
+/// This is implemented once per database backend so that entities using
+/// `#[derive(Persist)]` can be persisted to any of them.
+pub trait SqlConnection {
+    type Err;
+
+    /// Executes the statement with the given parameters, returning the number of affected rows.
+    #[allow(async_fn_in_trait)]
+    async fn execute(&self, sql: &str, params: Vec<SqlValue>) -> Result<usize, Self::Err>;
+}
+
+/// Statements are recorded instead of executed, so no rows are ever affected.
+impl SqlConnection for CapturingContext {
+    type Err = Infallible;
+
+    async fn execute(&self, sql: &str, params: Vec<SqlValue>) -> Result<usize, Self::Err> {
+        CapturingContext::execute(self, sql, params);
+
+        Ok(0)
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use std::sync::Arc;
+
+    use rusqlite::params_from_iter;
+    use rusqlite::types::Value;
+
+    use crate::test_support::TestContext;
+    use crate::{
+        association, persist, persist_reporting_rows, Associations, CapturedStatement, Manifest,
+        Persist,
+    };
+
+    use super::*;
+
+    impl SqlConnection for TestContext {
+        type Err = rusqlite::Error;
+
+        async fn execute(&self, sql: &str, params: Vec<SqlValue>) -> Result<usize, Self::Err> {
+            let params = params.into_iter().map(|param| match param {
+                SqlValue::Null => Value::Null,
+                SqlValue::Integer(value) => Value::Integer(value),
+                SqlValue::Real(value) => Value::Real(value),
+                SqlValue::Text(value) => Value::Text(value),
+                SqlValue::Blob(value) => Value::Blob(value),
+            });
+
+            self.conn.execute(sql, params_from_iter(params))
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Persist)]
+    #[malignius(table = "movie")]
+    struct Movie {
+        pub title: String,
+        #[malignius(column = "release_year")]
+        pub year: u32,
+        #[malignius(skip)]
+        pub rating: Option<u8>,
+    }
+
+    impl Manifest for Movie {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    title: "Inception".into(),
+                    year: 2010,
+                    rating: None,
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Persist)]
+    #[malignius(table = "review")]
+    struct Review {
+        pub movie_title: String,
+        pub body: String,
+    }
+
+    impl Manifest for Review {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let movie: Movie = association(&mut associations);
+
+            (
+                Self {
+                    movie_title: movie.title,
+                    body: "A dream within a dream.".into(),
+                },
+                associations,
+            )
+        }
+    }
+
+    /// A column type that is not `Clone`, which the derive only needs a reference to.
+    #[derive(Debug, PartialEq, Eq)]
+    struct CatalogNumber(String);
+
+    impl From<&CatalogNumber> for SqlValue {
+        fn from(value: &CatalogNumber) -> Self {
+            Self::Text(value.0.clone())
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Persist)]
+    #[malignius(table = "album")]
+    struct Album {
+        pub title: String,
+        pub catalog_number: CatalogNumber,
+        pub year: Option<u16>,
+    }
+
+    impl Manifest for Album {
+        type Context = CapturingContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    title: "Abbey Road".into(),
+                    catalog_number: CatalogNumber("PCS 7088".into()),
+                    year: Some(1969),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn derived_persist_inserts_through_a_sql_connection(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                create table if not exists movie (
+                    id integer primary key,
+                    title text not null,
+                    release_year integer not null
+                );
+            "#,
+        )?;
+
+        let movie: Movie = persist(ctx.clone()).await?;
+
+        let persisted_movie = ctx.conn.query_row(
+            "
+                select title, release_year from movie where title = $1
+            ",
+            [movie.title.clone()],
+            |row| {
+                Ok(Movie {
+                    title: row.get(0)?,
+                    year: row.get(1)?,
+                    rating: None,
+                })
+            },
+        )?;
+
+        assert_eq!(movie, persisted_movie);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn derived_persist_reports_the_rows_affected_by_each_insert(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::open(
+            r#"
+                create table if not exists movie (
+                    id integer primary key,
+                    title text not null,
+                    release_year integer not null
+                );
+
+                create table if not exists review (
+                    id integer primary key,
+                    movie_title text not null,
+                    body text not null
+                );
+            "#,
+        )?;
+
+        let report = persist_reporting_rows::<Review>(&ctx, ()).await?;
+
+        assert_eq!(
+            report
+                .rows_affected
+                .iter()
+                .map(|statement| (statement.entity_type, statement.rows))
+                .collect::<Vec<_>>(),
+            vec![
+                (std::any::type_name::<Movie>(), 1),
+                (std::any::type_name::<Review>(), 1),
+            ]
+        );
+        assert_eq!(report.entity.movie_title, "Inception");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn derived_persist_generates_an_insert_statement(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = Arc::new(CapturingContext::new());
+
+        let _: Album = persist(ctx.clone()).await?;
+
+        assert_eq!(
+            ctx.statements(),
+            vec![CapturedStatement {
+                sql: "insert into album (title, catalog_number, year) values ($1, $2, $3)".into(),
+                params: vec![
+                    SqlValue::Text("Abbey Road".into()),
+                    SqlValue::Text("PCS 7088".into()),
+                    SqlValue::Integer(1969),
+                ],
+            }]
+        );
+
+        Ok(())
+    }
+}
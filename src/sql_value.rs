@@ -0,0 +1,126 @@
+/// A backend-agnostic SQL parameter value.
+///
+/// Values can be converted from references as well, which is how `#[derive(Persist)]` builds
+/// its parameters, so column types do not need to implement `Clone`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+macro_rules! impl_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for SqlValue {
+                fn from(value: $ty) -> Self {
+                    Self::Integer(value as i64)
+                }
+            }
+
+            impl From<&$ty> for SqlValue {
+                fn from(value: &$ty) -> Self {
+                    Self::Integer(*value as i64)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_integer!(i8, i16, i32, i64, u8, u16, u32, bool);
+
+impl From<f32> for SqlValue {
+    fn from(value: f32) -> Self {
+        Self::Real(value as f64)
+    }
+}
+
+impl From<&f32> for SqlValue {
+    fn from(value: &f32) -> Self {
+        Self::Real(*value as f64)
+    }
+}
+
+impl From<f64> for SqlValue {
+    fn from(value: f64) -> Self {
+        Self::Real(value)
+    }
+}
+
+impl From<&f64> for SqlValue {
+    fn from(value: &f64) -> Self {
+        Self::Real(*value)
+    }
+}
+
+impl From<String> for SqlValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&String> for SqlValue {
+    fn from(value: &String) -> Self {
+        Self::Text(value.clone())
+    }
+}
+
+impl From<&str> for SqlValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_owned())
+    }
+}
+
+impl From<&&str> for SqlValue {
+    fn from(value: &&str) -> Self {
+        Self::Text((*value).to_owned())
+    }
+}
+
+impl From<Vec<u8>> for SqlValue {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Blob(value)
+    }
+}
+
+impl From<&Vec<u8>> for SqlValue {
+    fn from(value: &Vec<u8>) -> Self {
+        Self::Blob(value.clone())
+    }
+}
+
+impl<T: Into<SqlValue>> From<Option<T>> for SqlValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+macro_rules! impl_from_optional_ref {
+    ($($ty:ty),*) => {
+        $(
+            impl From<&Option<$ty>> for SqlValue {
+                fn from(value: &Option<$ty>) -> Self {
+                    value.as_ref().map_or(Self::Null, Into::into)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_optional_ref!(
+    i8,
+    i16,
+    i32,
+    i64,
+    u8,
+    u16,
+    u32,
+    bool,
+    f32,
+    f64,
+    String,
+    &str,
+    Vec<u8>
+);
@@ -0,0 +1,218 @@
+use futures::lock::{Mutex, MutexGuard};
+use sqlx::{Database, Pool, Transaction};
+
+/// A context that persists everything into a single sqlx [`Transaction`].
+///
+/// Factories are handed the context through an [`Arc`](std::sync::Arc), but a transaction can
+/// be neither cloned nor shared, so the transaction is kept behind an async mutex. Rolling the
+/// transaction back at the end of a test discards everything the test persisted.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::{persist, Associations, Manifest, Persist, SqlxTransaction};
+/// # use sqlx::Sqlite;
+/// # struct User {
+/// #     name: String,
+/// # }
+/// # impl Manifest for User {
+/// #     type Context = SqlxTransaction<Sqlite>;
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: ()) -> (Self, Associations<Self::Context>) {
+/// #         (Self { name: "Ada".into() }, Associations::new())
+/// #     }
+/// # }
+/// impl Persist for User {
+///     type Err = sqlx::Error;
+///
+///     async fn persist(ctx: &Self::Context, user: Self) -> Result<Self, Self::Err> {
+///         sqlx::query("insert into user (name) values ($1)")
+///             .bind(&user.name)
+///             .execute(&mut **ctx.lock().await)
+///             .await?;
+///
+///         Ok(user)
+///     }
+/// }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let pool = sqlx::pool::PoolOptions::<Sqlite>::new()
+/// #     .max_connections(1)
+/// #     .connect("sqlite::memory:")
+/// #     .await?;
+/// # sqlx::query("create table user (name text not null)")
+/// #     .execute(&pool)
+/// #     .await?;
+///
+/// let ctx = Arc::new(SqlxTransaction::begin(&pool).await?);
+/// let user: User = persist(ctx.clone()).await?;
+///
+/// Arc::into_inner(ctx).unwrap().rollback().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Locking
+///
+/// Only one statement can run on the transaction at a time, so every `Persist` implementation
+/// waits for the lock before executing its statements. Associations are persisted before the
+/// entity that registered them, and each `persist` runs to completion before the next one
+/// starts, so this never contends when associations are persisted one at a time. With a
+/// [`max_concurrency`](crate::PersistOptions::max_concurrency) above `1`, or with
+/// [`concurrent_types`](crate::PersistOptions::concurrent_types), the associations take turns
+/// on the lock, so they are still effectively persisted one at a time.
+///
+/// The guard returned by [`SqlxTransaction::lock`] must not be held while persisting another
+/// entity, such as from within a scenario, since that entity would wait on the lock forever.
+pub struct SqlxTransaction<DB: Database> {
+    transaction: Mutex<Transaction<'static, DB>>,
+}
+
+impl<DB: Database> SqlxTransaction<DB> {
+    pub fn new(transaction: Transaction<'static, DB>) -> Self {
+        Self {
+            transaction: Mutex::new(transaction),
+        }
+    }
+
+    /// Begins a new transaction on a connection from the pool.
+    pub async fn begin(pool: &Pool<DB>) -> Result<Self, sqlx::Error> {
+        Ok(Self::new(pool.begin().await?))
+    }
+
+    /// Waits for exclusive access to the transaction.
+    ///
+    /// Dereferencing the guard twice yields the connection, which is what sqlx executes
+    /// queries against: `.execute(&mut **ctx.lock().await)`.
+    pub async fn lock(&self) -> MutexGuard<'_, Transaction<'static, DB>> {
+        self.transaction.lock().await
+    }
+
+    /// Returns the transaction, such as to commit it.
+    pub fn into_inner(self) -> Transaction<'static, DB> {
+        self.transaction.into_inner()
+    }
+
+    /// Rolls back everything persisted through the context.
+    pub async fn rollback(self) -> Result<(), sqlx::Error> {
+        self.into_inner().rollback().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use sqlx::{Sqlite, SqlitePool};
+
+    use crate::{association, persist, Associations, Manifest, Persist};
+
+    use super::*;
+
+    type TestContext = SqlxTransaction<Sqlite>;
+
+    #[derive(Debug, Clone)]
+    struct Author {
+        pub name: String,
+    }
+
+    impl Manifest for Author {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    name: "Becky Chambers".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Author {
+        type Err = sqlx::Error;
+
+        async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+            sqlx::query("insert into author (name) values ($1)")
+                .bind(&author.name)
+                .execute(&mut **ctx.lock().await)
+                .await?;
+
+            Ok(author)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Post {
+        pub author_name: String,
+    }
+
+    impl Manifest for Post {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let author: Author = association(&mut associations);
+
+            (
+                Self {
+                    author_name: author.name,
+                },
+                associations,
+            )
+        }
+    }
+
+    impl Persist for Post {
+        type Err = sqlx::Error;
+
+        async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+            sqlx::query("insert into post (author_name) values ($1)")
+                .bind(&post.author_name)
+                .execute(&mut **ctx.lock().await)
+                .await?;
+
+            Ok(post)
+        }
+    }
+
+    async fn count(pool: &SqlitePool, table: &str) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(&format!("select count(*) from {table}"))
+            .fetch_one(pool)
+            .await
+    }
+
+    #[tokio::test]
+    async fn rolling_back_discards_everything_persisted() -> Result<(), Box<dyn std::error::Error>>
+    {
+        // A single connection, so every query sees the same in-memory database.
+        let pool = sqlx::pool::PoolOptions::<Sqlite>::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::query("create table author (name text not null)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("create table post (author_name text not null)")
+            .execute(&pool)
+            .await?;
+
+        let ctx = Arc::new(SqlxTransaction::begin(&pool).await?);
+
+        let post: Post = persist(ctx.clone()).await?;
+        assert_eq!(post.author_name, "Becky Chambers");
+
+        let authors: i64 = sqlx::query_scalar("select count(*) from author")
+            .fetch_one(&mut **ctx.lock().await)
+            .await?;
+        assert_eq!(authors, 1);
+
+        Arc::into_inner(ctx).unwrap().rollback().await?;
+
+        assert_eq!(count(&pool, "author").await?, 0);
+        assert_eq!(count(&pool, "post").await?, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use crate::Manifest;
+
+/// Overrides that can report which of their fields have not been set.
+///
+/// For overrides made up of `Option` fields this can be derived with `#[derive(StrictOverrides)]`
+/// (with the `derive` feature), which checks every field of the overrides. For the builders
+/// generated by `derive_builder`, the derive can be added to the builder with
+/// `#[builder(derive(StrictOverrides))]`:
+///
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use derive_builder::Builder;
+/// # use malignius::StrictOverrides;
+/// #[derive(Builder)]
+/// #[builder(derive(StrictOverrides))]
+/// struct Movie {
+///     title: String,
+///     year: u32,
+/// }
+/// # let mut movie = MovieBuilder::default();
+/// # movie.year(2016);
+/// # assert_eq!(movie.unset_fields(), ["title"]);
+/// ```
+pub trait StrictOverrides {
+    /// Returns the names of the fields that have not been set.
+    fn unset_fields(&self) -> Vec<&'static str>;
+}
+
+/// The error returned by [`manifest_strict`] when required fields were not supplied.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MissingFieldsError {
+    pub fields: Vec<&'static str>,
+}
+
+impl fmt::Display for MissingFieldsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "missing required fields: {}", self.fields.join(", "))
+    }
+}
+
+impl std::error::Error for MissingFieldsError {}
+
+/// Manifests an entity without falling back to any of the factory's defaults.
+///
+/// Every field must be supplied through the overrides, otherwise an error listing the missing
+/// fields is returned.
+pub fn manifest_strict<T: Manifest>(overrides: T::Overrides) -> Result<T, MissingFieldsError>
+where
+    T::Overrides: StrictOverrides,
+{
+    let fields = overrides.unset_fields();
+    if !fields.is_empty() {
+        return Err(MissingFieldsError { fields });
+    }
+
+    let (entity, _) = crate::graph_path::with_root::<T, _>(|| T::manifest(overrides));
+
+    Ok(entity)
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use derive_builder::Builder;
+
+    use crate::Associations;
+
+    use super::*;
+
+    #[derive(Debug, Builder, PartialEq, Eq)]
+    #[builder(derive(crate::StrictOverrides))]
+    struct Movie {
+        pub title: String,
+        pub year: u32,
+    }
+
+    impl Manifest for Movie {
+        type Context = ();
+        type Overrides = MovieBuilder;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    title: overrides.title.unwrap_or("Inception".into()),
+                    year: overrides.year.unwrap_or(2010),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[test]
+    fn manifest_strict_works_when_every_field_is_supplied() {
+        let movie: Movie = manifest_strict({
+            let mut movie = MovieBuilder::default();
+            movie.title("The Social Network".into());
+            movie.year(2010);
+            movie
+        })
+        .unwrap();
+
+        assert_eq!(
+            movie,
+            Movie {
+                title: "The Social Network".into(),
+                year: 2010
+            }
+        )
+    }
+
+    #[test]
+    fn manifest_strict_errors_when_a_field_is_missing() {
+        let result = manifest_strict::<Movie>({
+            let mut movie = MovieBuilder::default();
+            movie.title("The Social Network".into());
+            movie
+        });
+
+        assert_eq!(
+            result,
+            Err(MissingFieldsError {
+                fields: vec!["year"]
+            })
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "missing required fields: year"
+        );
+    }
+
+    #[test]
+    fn every_field_of_the_overrides_is_checked() {
+        assert_eq!(MovieBuilder::default().unset_fields(), ["title", "year"]);
+    }
+}
@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::future::Future;
+
+use crate::scope::Scoped;
+
+thread_local! {
+    static CURRENT_TENANT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Returns the tenant that the entity currently being persisted belongs to.
+///
+/// This is set from [`PersistOptions::tenant`](crate::PersistOptions::tenant) for the duration
+/// of [`persist_with_options`](crate::persist_with_options), including while its associations
+/// are persisted. `Persist` implementations can use it to pick the schema or table to write to.
+pub fn current_tenant() -> Option<String> {
+    CURRENT_TENANT.with(|tenant| tenant.borrow().clone())
+}
+
+/// Runs the future with the given tenant as the [`current_tenant`].
+///
+/// When `tenant` is `None` the current tenant is left as-is.
+pub(crate) async fn in_tenant<F: Future>(tenant: Option<String>, future: F) -> F::Output {
+    match tenant {
+        Some(tenant) => Scoped::new(&CURRENT_TENANT, tenant, future).await,
+        None => future.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rusqlite::params;
+
+    use crate::test_support::TestContext;
+    use crate::{persist_with_options, Associations, Manifest, Persist, PersistOptions};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Movie {
+        pub title: String,
+    }
+
+    impl Manifest for Movie {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    title: "Inception".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Movie {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, movie: Self) -> Result<Self, Self::Err> {
+            let schema = current_tenant().unwrap_or("main".into());
+
+            ctx.conn.execute(
+                &format!("insert into {schema}.movie (title) values ($1)"),
+                params![movie.title],
+            )?;
+
+            Ok(movie)
+        }
+    }
+
+    fn count_movies(ctx: &TestContext, schema: &str) -> rusqlite::Result<u32> {
+        ctx.conn
+            .query_row(&format!("select count(*) from {schema}.movie"), [], |row| {
+                row.get(0)
+            })
+    }
+
+    #[tokio::test]
+    async fn persist_with_options_persists_into_the_given_tenant(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                attach database ':memory:' as tenant_a;
+                attach database ':memory:' as tenant_b;
+
+                create table tenant_a.movie (title text not null);
+                create table tenant_b.movie (title text not null);
+            "#,
+        )?;
+
+        let tenant = |name: &str| PersistOptions {
+            tenant: Some(name.into()),
+            ..Default::default()
+        };
+
+        persist_with_options::<Movie>(ctx.clone(), (), tenant("tenant_a")).await?;
+        persist_with_options::<Movie>(ctx.clone(), (), tenant("tenant_b")).await?;
+        persist_with_options::<Movie>(ctx.clone(), (), tenant("tenant_b")).await?;
+
+        assert_eq!(count_movies(&ctx, "tenant_a")?, 1);
+        assert_eq!(count_movies(&ctx, "tenant_b")?, 2);
+        assert_eq!(current_tenant(), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+//! Fixtures shared between the test modules.
+//!
+//! Most tests persist to an in-memory SQLite database through [`TestContext`]. Tests that only
+//! need a post and its author use [`Author`] and [`Post`], whose tables are created by
+//! [`SCHEMA`].
+
+use std::sync::Arc;
+
+use rusqlite::{params, Connection};
+
+use crate::{association, Associations, Manifest, Persist};
+
+/// The tables that [`Author`] and [`Post`] are persisted to.
+pub(crate) const SCHEMA: &str = r#"
+    create table author (
+        id integer primary key,
+        name text not null
+    );
+
+    create table post (
+        id integer primary key,
+        author_name text not null,
+        title text not null
+    );
+"#;
+
+/// Wraps a context in the `Arc` that persisting takes.
+///
+/// Most test contexts hold a `Connection` or a `RefCell`, so they are neither `Send` nor
+/// `Sync`. That is fine here, since every test persists on a single thread.
+#[allow(clippy::arc_with_non_send_sync)]
+pub(crate) fn shared<Context>(ctx: Context) -> Arc<Context> {
+    Arc::new(ctx)
+}
+
+/// A context that persists to an in-memory SQLite database.
+pub(crate) struct TestContext {
+    pub conn: Connection,
+}
+
+impl TestContext {
+    /// Opens an in-memory database and creates the tables in `schema`.
+    pub fn open(schema: &str) -> rusqlite::Result<Self> {
+        let conn = Connection::open(":memory:")?;
+        conn.execute_batch(schema)?;
+
+        Ok(Self { conn })
+    }
+
+    /// Opens an in-memory database like [`TestContext::open`], returning it behind an `Arc`.
+    pub fn shared(schema: &str) -> rusqlite::Result<Arc<Self>> {
+        Self::open(schema).map(shared)
+    }
+
+    /// Returns the count selected by `sql`.
+    pub fn count(&self, sql: &str) -> rusqlite::Result<usize> {
+        self.conn.query_row(sql, [], |row| row.get(0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Author {
+    pub id: i64,
+    pub name: String,
+}
+
+impl Manifest for Author {
+    type Context = TestContext;
+    type Overrides = ();
+
+    fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+        (
+            Self {
+                id: 0,
+                name: "N. K. Jemisin".into(),
+            },
+            Associations::new(),
+        )
+    }
+}
+
+impl Persist for Author {
+    type Err = rusqlite::Error;
+
+    async fn persist(ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+        ctx.conn.execute(
+            "insert into author (name) values ($1)",
+            params![author.name],
+        )?;
+
+        Ok(Self {
+            id: ctx.conn.last_insert_rowid(),
+            ..author
+        })
+    }
+}
+
+/// A post, which registers its [`Author`] as an association.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Post {
+    pub id: i64,
+    pub author_name: String,
+    pub title: String,
+}
+
+impl Manifest for Post {
+    type Context = TestContext;
+    type Overrides = ();
+
+    fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+        let mut associations = Associations::new();
+        let author: Author = association(&mut associations);
+
+        (
+            Self {
+                id: 0,
+                author_name: author.name,
+                title: "The Fifth Season".into(),
+            },
+            associations,
+        )
+    }
+}
+
+impl Persist for Post {
+    type Err = rusqlite::Error;
+
+    async fn persist(ctx: &Self::Context, post: Self) -> Result<Self, Self::Err> {
+        ctx.conn.execute(
+            "insert into post (author_name, title) values ($1, $2)",
+            params![post.author_name, post.title],
+        )?;
+
+        Ok(Self {
+            id: ctx.conn.last_insert_rowid(),
+            ..post
+        })
+    }
+}
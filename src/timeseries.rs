@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    configured_options, persist_roots_at, with_clock, FixedClock, MaligniusError, Persist,
+};
+
+/// Persists `count` entities with timestamps spaced `interval` apart, starting at `start`.
+///
+/// Each entity is manifested with its timestamp as the current time, so factory defaults using
+/// [`now`](crate::now) pick it up. `overrides` receives the index of the entity and its
+/// timestamp and returns the overrides to manifest it with. If `T` is configured as
+/// [`batched`](crate::PersistConfig::batched) the entities are persisted in a single batch.
+///
+/// Each entity is the root of its own graph and is persisted with the options configured for `T`,
+/// the same as with [`persist_many_with`](crate::persist_many_with).
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use std::time::{Duration, SystemTime};
+/// # use malignius::*;
+/// # #[derive(Default)]
+/// # struct MetricOverrides {
+/// #     value: Option<f64>,
+/// # }
+/// # struct Metric {
+/// #     value: f64,
+/// #     recorded_at: SystemTime,
+/// # }
+/// # impl Manifest for Metric {
+/// #     type Context = ();
+/// #     type Overrides = MetricOverrides;
+/// #     fn manifest(overrides: MetricOverrides) -> (Self, Associations<()>) {
+/// #         let metric = Metric {
+/// #             value: overrides.value.unwrap_or(0.0),
+/// #             recorded_at: now(),
+/// #         };
+/// #         (metric, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Metric {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), metric: Self) -> Result<Self, Self::Err> {
+/// #         Ok(metric)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// # let start = SystemTime::UNIX_EPOCH;
+/// let metrics: Vec<Metric> = persist_timeseries(
+///     ctx,
+///     start,
+///     Duration::from_secs(60 * 60),
+///     24,
+///     |hour, _| MetricOverrides { value: Some(hour as f64), ..Default::default() },
+/// )
+/// .await?;
+/// # assert_eq!(metrics[23].recorded_at, start + Duration::from_secs(23 * 60 * 60));
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_timeseries<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    start: SystemTime,
+    interval: Duration,
+    count: usize,
+    mut overrides: impl FnMut(usize, SystemTime) -> T::Overrides,
+) -> Result<Vec<T>, MaligniusError<T::Err>> {
+    persist_roots_at::<T>(
+        &ctx,
+        0,
+        count,
+        |index| {
+            let timestamp = start + interval * index as u32;
+            let overrides = overrides(index, timestamp);
+
+            with_clock(FixedClock::new(timestamp), || T::manifest(overrides))
+        },
+        configured_options::<T>(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+
+    use crate::test_support::shared;
+    use crate::{configure, now, Associations, Manifest, PersistConfig};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MetricsContext {
+        pub metrics: RefCell<Vec<Metric>>,
+        pub batches: Cell<usize>,
+    }
+
+    #[derive(Debug, Default)]
+    struct MetricOverrides {
+        pub value: Option<u32>,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Metric {
+        pub recorded_at: SystemTime,
+        pub value: u32,
+    }
+
+    impl Manifest for Metric {
+        type Context = MetricsContext;
+        type Overrides = MetricOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    recorded_at: now(),
+                    value: overrides.value.unwrap_or_default(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Metric {
+        type Err = std::convert::Infallible;
+
+        async fn persist(ctx: &Self::Context, metric: Self) -> Result<Self, Self::Err> {
+            ctx.metrics.borrow_mut().push(metric.clone());
+            Ok(metric)
+        }
+
+        async fn persist_batch(
+            ctx: &Self::Context,
+            metrics: Vec<Self>,
+        ) -> Result<Vec<Self>, Self::Err> {
+            ctx.batches.set(ctx.batches.get() + 1);
+            ctx.metrics.borrow_mut().extend(metrics.iter().cloned());
+            Ok(metrics)
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_timeseries_spaces_the_timestamps_by_the_interval(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        configure::<Metric>(PersistConfig {
+            batched: true,
+            ..Default::default()
+        });
+
+        let ctx = shared(MetricsContext::default());
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let hour = Duration::from_secs(60 * 60);
+
+        let metrics: Vec<Metric> =
+            persist_timeseries(ctx.clone(), start, hour, 24, |index, _| MetricOverrides {
+                value: Some(index as u32 * 10),
+            })
+            .await?;
+
+        assert_eq!(metrics.len(), 24);
+        assert_eq!(*ctx.metrics.borrow(), metrics);
+        assert_eq!(ctx.batches.get(), 1);
+
+        for (index, metric) in metrics.iter().enumerate() {
+            assert_eq!(metric.recorded_at, start + hour * index as u32);
+            assert_eq!(metric.value, index as u32 * 10);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use crate::bulk::persist_level;
+use crate::{InitialOverrides, MaligniusError, Persist};
+
+/// An entity that refers to another entity of the same type as its parent, such as a comment
+/// and its replies.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Default)]
+/// # struct CommentOverrides {
+/// #     parent_id: Option<Option<u64>>,
+/// # }
+/// # struct Comment {
+/// #     id: u64,
+/// #     parent_id: Option<u64>,
+/// # }
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = CommentOverrides;
+/// #     fn manifest(overrides: CommentOverrides) -> (Self, Associations<()>) {
+/// #         let comment = Comment {
+/// #             id: sequences::next_named("comment", |n| n as u64),
+/// #             parent_id: overrides.parent_id.unwrap_or(None),
+/// #         };
+/// #         (comment, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), comment: Self) -> Result<Self, Self::Err> {
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// impl TreeNode for Comment {
+///     fn child_of(parent: &Self) -> Self::Overrides {
+///         CommentOverrides { parent_id: Some(Some(parent.id)), ..Default::default() }
+///     }
+/// }
+/// ```
+pub trait TreeNode: Persist {
+    /// Returns the overrides to manifest a child of `parent` with.
+    fn child_of(parent: &Self) -> Self::Overrides;
+}
+
+/// Persists a tree of entities, where every node has `branching` children until the tree is
+/// `depth` levels below its root.
+///
+/// The root is manifested with the default overrides and every other node with
+/// [`TreeNode::child_of`] its parent. A level can only be manifested once the level above it has
+/// been persisted, since the children need their parents' keys, so the tree is persisted a level
+/// at a time, the same way as [`persist_bulk_hierarchy`](crate::persist_bulk_hierarchy).
+///
+/// The nodes are returned breadth-first, starting with the root, so the children of the node at
+/// `index` are at `index * branching + 1..=index * branching + branching`.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Default)]
+/// # struct CommentOverrides {
+/// #     parent_id: Option<Option<u64>>,
+/// # }
+/// # struct Comment {
+/// #     id: u64,
+/// #     parent_id: Option<u64>,
+/// # }
+/// # impl Manifest for Comment {
+/// #     type Context = ();
+/// #     type Overrides = CommentOverrides;
+/// #     fn manifest(overrides: CommentOverrides) -> (Self, Associations<()>) {
+/// #         let comment = Comment {
+/// #             id: sequences::next_named("comment", |n| n as u64),
+/// #             parent_id: overrides.parent_id.unwrap_or(None),
+/// #         };
+/// #         (comment, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Comment {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &(), comment: Self) -> Result<Self, Self::Err> {
+/// #         Ok(comment)
+/// #     }
+/// # }
+/// # impl TreeNode for Comment {
+/// #     fn child_of(parent: &Self) -> Self::Overrides {
+/// #         CommentOverrides { parent_id: Some(Some(parent.id)), ..Default::default() }
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// // A root with two replies, each of which has two replies of its own.
+/// let comments = persist_tree::<Comment>(ctx, 2, 2).await?;
+/// assert_eq!(comments.len(), 7);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn persist_tree<Node>(
+    ctx: Arc<Node::Context>,
+    depth: usize,
+    branching: usize,
+) -> Result<Vec<Node>, MaligniusError<Node::Err>>
+where
+    Node: TreeNode + 'static,
+{
+    let mut nodes =
+        persist_level::<(), Node>(&ctx, &[()], 1, 0, |_| Node::Overrides::initial()).await?;
+
+    let mut level_start = 0;
+    for _ in 0..depth {
+        let level = persist_level::<Node, Node>(
+            &ctx,
+            &nodes[level_start..],
+            branching,
+            nodes.len(),
+            Node::child_of,
+        )
+        .await?;
+
+        level_start = nodes.len();
+        nodes.extend(level);
+    }
+
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::params;
+
+    use crate::test_support::TestContext;
+    use crate::{Associations, Manifest};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Comment {
+        pub id: i64,
+        pub parent_id: Option<i64>,
+    }
+
+    impl Manifest for Comment {
+        type Context = TestContext;
+        type Overrides = Option<i64>;
+
+        fn manifest(parent_id: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { id: 0, parent_id }, Associations::new())
+        }
+    }
+
+    impl Persist for Comment {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, comment: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into comment (parent_id) values ($1)",
+                params![comment.parent_id],
+            )?;
+
+            Ok(Self {
+                id: ctx.conn.last_insert_rowid(),
+                ..comment
+            })
+        }
+    }
+
+    impl TreeNode for Comment {
+        fn child_of(parent: &Self) -> Self::Overrides {
+            Some(parent.id)
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_tree_wires_each_node_to_its_parent() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let ctx = TestContext::shared(
+            r#"
+                pragma foreign_keys = on;
+                create table comment (
+                    id integer primary key,
+                    parent_id integer references comment (id)
+                );
+            "#,
+        )?;
+
+        let comments = persist_tree::<Comment>(ctx.clone(), 2, 2).await?;
+
+        assert_eq!(comments.len(), 7);
+        let count: usize = ctx
+            .conn
+            .query_row("select count(*) from comment", [], |row| row.get(0))?;
+        assert_eq!(count, 7);
+
+        assert_eq!(comments[0].parent_id, None);
+        for (index, comment) in comments.iter().enumerate().skip(1) {
+            assert_eq!(comment.parent_id, Some(comments[(index - 1) / 2].id));
+        }
+
+        let leaves: usize = ctx.conn.query_row(
+            "select count(*) from comment where id not in (select parent_id from comment where parent_id is not null)",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(leaves, 4);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+
+use crate::panic::catch_panic;
+use crate::{Manifest, PanicError};
+
+/// A cross-field constraint that a manifested entity violates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintViolation {
+    /// The name of the violated constraint.
+    pub constraint: &'static str,
+    /// A description of how the constraint was violated.
+    pub message: String,
+}
+
+impl ConstraintViolation {
+    pub fn new(constraint: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            constraint,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "constraint `{}` violated: {}",
+            self.constraint, self.message
+        )
+    }
+}
+
+impl std::error::Error for ConstraintViolation {}
+
+/// An error that occurred while manifesting an entity with [`try_manifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    /// The factory panicked.
+    Panicked(PanicError),
+    /// The manifested entity violates one of its constraints.
+    ConstraintViolated {
+        entity_type: &'static str,
+        violation: ConstraintViolation,
+    },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panicked(err) => write!(f, "{err}"),
+            Self::ConstraintViolated {
+                entity_type,
+                violation,
+            } => write!(f, "invalid `{entity_type}`: {violation}"),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Panicked(err) => Some(err),
+            Self::ConstraintViolated { violation, .. } => Some(violation),
+        }
+    }
+}
+
+/// Manifests an entity, returning an error instead of panicking if the factory panics.
+///
+/// The entity's [`Manifest::check_constraints`] are checked after the overrides have been
+/// applied.
+pub fn try_manifest<T: Manifest>(overrides: T::Overrides) -> Result<T, ManifestError> {
+    let (entity, _) = catch_panic(
+        || format!("manifesting `{}`", std::any::type_name::<T>()),
+        || crate::graph_path::with_root::<T, _>(|| T::manifest(overrides)),
+    )
+    .map_err(ManifestError::Panicked)?;
+
+    T::check_constraints(&entity).map_err(|violation| ManifestError::ConstraintViolated {
+        entity_type: std::any::type_name::<T>(),
+        violation,
+    })?;
+
+    Ok(entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Associations;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Color {
+        pub name: String,
+    }
+
+    impl Manifest for Color {
+        type Context = ();
+        type Overrides = Option<usize>;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let names = ["red", "green", "blue"];
+
+            (
+                Self {
+                    name: names[overrides.unwrap_or(0)].into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    #[test]
+    fn try_manifest_returns_the_entity() {
+        assert_eq!(
+            try_manifest::<Color>(Some(2)),
+            Ok(Color {
+                name: "blue".into()
+            })
+        );
+    }
+
+    #[test]
+    fn try_manifest_converts_a_panic_into_an_error() {
+        let err = try_manifest::<Color>(Some(5)).unwrap_err();
+
+        let ManifestError::Panicked(panic) = &err else {
+            panic!("expected a panic, got {err:?}");
+        };
+
+        assert_eq!(
+            panic.context,
+            format!("manifesting `{}`", std::any::type_name::<Color>())
+        );
+        assert!(panic.message.contains("index out of bounds"));
+        assert!(err
+            .to_string()
+            .contains("Color` panicked: index out of bounds"));
+    }
+
+    #[derive(Debug, Default)]
+    struct BookingOverrides {
+        pub start: Option<u32>,
+        pub end: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Booking {
+        pub start: u32,
+        pub end: u32,
+    }
+
+    impl Manifest for Booking {
+        type Context = ();
+        type Overrides = BookingOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    start: overrides.start.unwrap_or(10),
+                    end: overrides.end.unwrap_or(20),
+                },
+                Associations::new(),
+            )
+        }
+
+        fn check_constraints(booking: &Self) -> Result<(), ConstraintViolation> {
+            if booking.start >= booking.end {
+                return Err(ConstraintViolation::new(
+                    "start_before_end",
+                    format!(
+                        "start ({}) must be before end ({})",
+                        booking.start, booking.end
+                    ),
+                ));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn try_manifest_accepts_overrides_that_satisfy_the_constraints() {
+        assert_eq!(
+            try_manifest::<Booking>(BookingOverrides {
+                start: Some(15),
+                ..Default::default()
+            }),
+            Ok(Booking { start: 15, end: 20 })
+        );
+    }
+
+    #[test]
+    fn try_manifest_rejects_overrides_that_violate_a_constraint() {
+        let err = try_manifest::<Booking>(BookingOverrides {
+            start: Some(25),
+            ..Default::default()
+        })
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            ManifestError::ConstraintViolated {
+                entity_type: std::any::type_name::<Booking>(),
+                violation: ConstraintViolation::new(
+                    "start_before_end",
+                    "start (25) must be before end (20)"
+                ),
+            }
+        );
+        assert!(err.to_string().ends_with(
+            "constraint `start_before_end` violated: start (25) must be before end (20)"
+        ));
+    }
+}
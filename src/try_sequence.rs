@@ -0,0 +1,114 @@
+/// A [`Sequence`](crate::Sequence) whose producer can fail.
+///
+/// This is useful for values that have to be parsed or validated, such as an `Email` newtype,
+/// without panicking inside the producer.
+///
+/// ```
+/// # use malignius::TrySequence;
+/// # struct Email(String);
+/// #
+/// # impl Email {
+/// #     fn parse(value: String) -> Result<Self, String> {
+/// #         if value.contains('@') { Ok(Self(value)) } else { Err(value) }
+/// #     }
+/// # }
+/// let mut emails = TrySequence::new(|n| Email::parse(format!("user{n}@example.com")));
+/// let email = emails.next()?;
+/// # assert_eq!(email.0, "user1@example.com");
+/// # Ok::<(), String>(())
+/// ```
+pub struct TrySequence<T, E> {
+    counter: usize,
+    produce: Box<dyn Fn(usize) -> Result<T, E>>,
+}
+
+impl<T, E> TrySequence<T, E> {
+    pub fn new(produce: impl Fn(usize) -> Result<T, E> + 'static) -> Self {
+        Self {
+            counter: 1,
+            produce: Box::new(produce),
+        }
+    }
+
+    /// Returns the next value in the sequence.
+    ///
+    /// The sequence only advances when a value is produced successfully, so after an error the
+    /// next call tries the same index again.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<T, E> {
+        let value = (self.produce)(self.counter)?;
+        self.counter += 1;
+
+        Ok(value)
+    }
+
+    /// Returns the next *n* values in the sequence, stopping at the first error.
+    ///
+    /// The values produced before the error are still consumed.
+    pub fn take(&mut self, n: usize) -> Result<Vec<T>, E> {
+        (0..n).map(|_| self.next()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn email(n: usize) -> Result<String, String> {
+        match n {
+            3 => Err(format!("user{n} is reserved")),
+            n => Ok(format!("user{n}@example.com")),
+        }
+    }
+
+    #[test]
+    fn take_produces_values_until_an_error() {
+        let mut emails = TrySequence::new(email);
+
+        assert_eq!(
+            emails.take(2),
+            Ok(vec![
+                "user1@example.com".to_string(),
+                "user2@example.com".to_string()
+            ])
+        );
+        assert_eq!(emails.take(2), Err("user3 is reserved".to_string()));
+    }
+
+    #[test]
+    fn errors_do_not_advance_the_sequence() {
+        let attempts = Rc::new(Cell::new(0));
+        let mut ids = TrySequence::new({
+            let attempts = attempts.clone();
+            move |n| {
+                attempts.set(attempts.get() + 1);
+                match attempts.get() {
+                    2 => Err("flaky"),
+                    _ => Ok(n),
+                }
+            }
+        });
+
+        assert_eq!(ids.next(), Ok(1));
+        assert_eq!(ids.next(), Err("flaky"));
+        assert_eq!(ids.next(), Ok(2));
+    }
+
+    #[test]
+    fn take_stops_producing_after_the_first_error() {
+        let produced = Rc::new(Cell::new(0));
+        let mut emails = TrySequence::new({
+            let produced = produced.clone();
+            move |n| {
+                produced.set(produced.get() + 1);
+                email(n)
+            }
+        });
+
+        assert!(emails.take(10).is_err());
+        assert_eq!(produced.get(), 3);
+    }
+}
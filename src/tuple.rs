@@ -0,0 +1,94 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use crate::{persist_in, Persist};
+
+/// A tuple of entities that can be persisted together with [`persist_tuple`].
+///
+/// This is implemented for tuples of up to eight entities that share the same context.
+pub trait PersistTuple: Sized {
+    type Context;
+
+    /// Persists each entity in the tuple, in order.
+    #[allow(async_fn_in_trait)]
+    async fn persist_tuple(ctx: &Self::Context) -> Result<Self, Box<dyn Error>>;
+}
+
+macro_rules! impl_persist_tuple {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<$first, $($rest),*> PersistTuple for ($first, $($rest,)*)
+        where
+            $first: Persist + 'static,
+            <$first as Persist>::Err: Error + 'static,
+            $(
+                $rest: Persist<Context = <$first as crate::Manifest>::Context> + 'static,
+                <$rest as Persist>::Err: Error + 'static,
+            )*
+        {
+            type Context = <$first as crate::Manifest>::Context;
+
+            async fn persist_tuple(ctx: &Self::Context) -> Result<Self, Box<dyn Error>> {
+                Ok((
+                    persist_in::<$first>(ctx).await?,
+                    $(persist_in::<$rest>(ctx).await?,)*
+                ))
+            }
+        }
+    };
+}
+
+impl_persist_tuple!(A);
+impl_persist_tuple!(A, B);
+impl_persist_tuple!(A, B, C);
+impl_persist_tuple!(A, B, C, D);
+impl_persist_tuple!(A, B, C, D, E);
+impl_persist_tuple!(A, B, C, D, E, F);
+impl_persist_tuple!(A, B, C, D, E, F, G);
+impl_persist_tuple!(A, B, C, D, E, F, G, H);
+
+/// Persists several unrelated entities with a shared context, returning them as a tuple.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use malignius::*;
+/// # #[derive(Clone)]
+/// # struct Movie;
+/// # impl Manifest for Movie {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Movie {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, movie: Self) -> Result<Self, Self::Err> {
+/// #         Ok(movie)
+/// #     }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Author;
+/// # impl Manifest for Author {
+/// #     type Context = ();
+/// #     type Overrides = ();
+/// #     fn manifest(_overrides: Self::Overrides) -> (Self, Associations<()>) {
+/// #         (Self, Associations::new())
+/// #     }
+/// # }
+/// # impl Persist for Author {
+/// #     type Err = std::convert::Infallible;
+/// #     async fn persist(_ctx: &Self::Context, author: Self) -> Result<Self, Self::Err> {
+/// #         Ok(author)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let ctx = Arc::new(());
+/// let (movie, author) = persist_tuple::<(Movie, Author)>(ctx).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[inline(always)]
+pub async fn persist_tuple<T: PersistTuple>(ctx: Arc<T::Context>) -> Result<T, Box<dyn Error>> {
+    T::persist_tuple(&ctx).await
+}
@@ -0,0 +1,138 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The suffix set with [`set_unique_suffix`], or `None` if it was never set.
+static CONFIGURED_SUFFIX: Mutex<Option<Option<UniqueSuffix>>> = Mutex::new(None);
+
+/// A suffix appended to values that must not collide across test runs.
+///
+/// When tests persist into a shared database, defaults such as `"Author 1"` will collide with
+/// the rows left behind by previous runs. Appending a suffix that is unique to the run avoids
+/// this, either with [`unique`] or with [`Sequence::unique`](crate::Sequence::unique). The suffix
+/// they append is configured with [`set_unique_suffix`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniqueSuffix(String);
+
+impl UniqueSuffix {
+    pub fn new(suffix: impl Into<String>) -> Self {
+        Self(suffix.into())
+    }
+
+    /// Returns the suffix for the current process.
+    ///
+    /// The suffix is generated once per process from the process id and the current time.
+    pub fn for_process() -> &'static UniqueSuffix {
+        static SUFFIX: OnceLock<UniqueSuffix> = OnceLock::new();
+
+        SUFFIX.get_or_init(|| {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos());
+
+            // Hashing spreads both inputs over the bits that are kept, so processes started at
+            // the same time still get different suffixes.
+            let mut hasher = DefaultHasher::new();
+            std::process::id().hash(&mut hasher);
+            nanos.hash(&mut hasher);
+
+            Self(format!("{:06x}", hasher.finish() & 0xff_ffff))
+        })
+    }
+
+    /// Appends the suffix to the value.
+    pub fn apply(&self, value: impl fmt::Display) -> String {
+        format!("{value}-{}", self.0)
+    }
+}
+
+impl fmt::Display for UniqueSuffix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Sets the [`UniqueSuffix`] that [`unique`] appends, process-wide.
+///
+/// Passing `None` disables the suffix, so values are left as they are, which is useful when
+/// every run gets a fresh database. Until this is called, [`UniqueSuffix::for_process`] is used.
+///
+/// ```
+/// # use malignius::{set_unique_suffix, UniqueSuffix};
+/// set_unique_suffix(std::env::var("CI_JOB_ID").ok().map(UniqueSuffix::new));
+/// ```
+pub fn set_unique_suffix(suffix: Option<UniqueSuffix>) {
+    *CONFIGURED_SUFFIX.lock().unwrap() = Some(suffix);
+}
+
+/// Returns the [`UniqueSuffix`] that [`unique`] appends, or `None` if it has been disabled.
+pub fn unique_suffix() -> Option<UniqueSuffix> {
+    match &*CONFIGURED_SUFFIX.lock().unwrap() {
+        Some(suffix) => suffix.clone(),
+        None => Some(UniqueSuffix::for_process().clone()),
+    }
+}
+
+/// Appends the configured [`unique_suffix`] to the value, for fields that must be unique.
+///
+/// ```
+/// # use malignius::{unique, Sequence};
+/// let mut names = Sequence::new(|n| unique(format!("Author {n}")));
+/// # assert!(names.next().starts_with("Author 1"));
+/// ```
+pub fn unique(value: impl fmt::Display) -> String {
+    match unique_suffix() {
+        Some(suffix) => suffix.apply(value),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Sequence;
+
+    use super::*;
+
+    #[test]
+    fn different_suffixes_produce_non_colliding_values() {
+        let first_run = UniqueSuffix::new("abc123");
+        let second_run = UniqueSuffix::new("def456");
+
+        let mut first_names = Sequence::new(move |n| first_run.apply(format!("Author {n}")));
+        let mut second_names = Sequence::new(move |n| second_run.apply(format!("Author {n}")));
+
+        let first_names = first_names.take_vec(3);
+        let second_names = second_names.take_vec(3);
+
+        assert_eq!(
+            first_names,
+            vec!["Author 1-abc123", "Author 2-abc123", "Author 3-abc123"]
+        );
+        assert!(first_names.iter().all(|name| !second_names.contains(name)));
+    }
+
+    #[test]
+    fn unique_uses_the_configured_suffix() {
+        assert_eq!(
+            unique("Author 1"),
+            format!("Author 1-{}", UniqueSuffix::for_process())
+        );
+        assert_eq!(UniqueSuffix::for_process().to_string().len(), 6);
+
+        set_unique_suffix(Some(UniqueSuffix::new("abc123")));
+        let mut names = Sequence::new(|n| format!("Author {n}")).unique();
+        assert_eq!(
+            names.take_vec(2),
+            vec!["Author 1-abc123", "Author 2-abc123"]
+        );
+
+        // The suffix is read as values are produced, so existing sequences pick up changes.
+        set_unique_suffix(None);
+        assert_eq!(names.next(), "Author 3");
+        assert_eq!(unique("Author 1"), "Author 1");
+
+        set_unique_suffix(Some(UniqueSuffix::for_process().clone()));
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt;
+
+use crate::{manifest_with, Manifest};
+
+/// A [`Manifest`] with named variants, each of which is a preset set of overrides.
+///
+/// A common use is a `"deleted"` variant for entities with a soft-delete column, so that the
+/// default manifest produces a live entity and the variant produces one with `deleted_at` set.
+/// Keep in mind that soft-deleted rows still count towards unique constraints unless the
+/// constraint is a partial index that excludes them (e.g. `where deleted_at is null`), so
+/// soft-deleted variants still need unique values.
+pub trait ManifestVariant: Manifest {
+    /// Returns the overrides for the named variant, or `None` if no such variant exists.
+    fn variant(name: &str) -> Option<Self::Overrides>;
+}
+
+/// Manifests the named variant of an entity.
+pub fn manifest_variant<T: ManifestVariant>(name: &str) -> Result<T, UnknownVariantError> {
+    let overrides = T::variant(name).ok_or_else(|| UnknownVariantError {
+        entity_type: std::any::type_name::<T>(),
+        variant: name.to_owned(),
+    })?;
+
+    Ok(manifest_with::<T>(overrides))
+}
+
+/// The error returned by [`manifest_variant`] when the variant does not exist.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownVariantError {
+    pub entity_type: &'static str,
+    pub variant: String,
+}
+
+impl fmt::Display for UnknownVariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` has no variant named `{}`",
+            self.entity_type, self.variant
+        )
+    }
+}
+
+impl std::error::Error for UnknownVariantError {}
@@ -0,0 +1,337 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{persist_with, MaligniusError, Persist};
+
+/// An error returned by [`persist_verified`].
+#[derive(Debug)]
+pub enum VerificationError<E> {
+    /// The entity failed to persist.
+    Persist(E),
+    /// The entity was persisted, but the verification did not find it.
+    Unverified { entity_type: &'static str },
+}
+
+impl<E: fmt::Display> fmt::Display for VerificationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Persist(err) => write!(f, "failed to persist: {err}"),
+            Self::Unverified { entity_type } => {
+                write!(f, "persisted `{entity_type}` could not be verified")
+            }
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for VerificationError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Persist(err) => Some(err),
+            Self::Unverified { .. } => None,
+        }
+    }
+}
+
+/// Persists an entity and then runs `verify` to confirm that it was actually persisted.
+///
+/// The `verify` closure receives the context and the persisted entity, and should return whether
+/// the entity could be found. This catches `Persist` implementations that silently do nothing.
+pub async fn persist_verified<T: Persist + 'static>(
+    ctx: Arc<T::Context>,
+    overrides: T::Overrides,
+    verify: impl FnOnce(&T::Context, &T) -> bool,
+) -> Result<T, VerificationError<MaligniusError<T::Err>>> {
+    let entity = persist_with::<T>(ctx.clone(), overrides)
+        .await
+        .map_err(VerificationError::Persist)?;
+
+    if !verify(&ctx, &entity) {
+        return Err(VerificationError::Unverified {
+            entity_type: std::any::type_name::<T>(),
+        });
+    }
+
+    Ok(entity)
+}
+
+/// A check that an association was actually persisted, run before the entity that registered it
+/// is persisted.
+///
+/// The check receives the context and the persisted association, as [`Any`] so that a single
+/// check can handle every association type. It should return whether the association could be
+/// found, and return `true` for any types it does not know about.
+///
+/// ```
+/// # use malignius::{AssociationCheck, PersistOptions};
+/// # use rusqlite::Connection;
+/// # struct Post {
+/// #     id: i64,
+/// # }
+/// # struct TestContext {
+/// #     conn: Connection,
+/// # }
+/// # impl TestContext {
+/// #     fn post_exists(&self, id: i64) -> bool {
+/// #         let sql = "select exists(select 1 from post where id = $1)";
+/// #         self.conn.query_row(sql, [id], |row| row.get(0)).unwrap()
+/// #     }
+/// # }
+/// let options = PersistOptions {
+///     verify_associations: Some(AssociationCheck::new(|ctx, association| {
+///         let ctx = ctx.downcast_ref::<TestContext>().unwrap();
+///         match association.downcast_ref::<Post>() {
+///             Some(post) => ctx.post_exists(post.id),
+///             None => true,
+///         }
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct AssociationCheck(Arc<AssociationCheckFn>);
+
+type AssociationCheckFn = dyn Fn(&dyn Any, &dyn Any) -> bool + Send + Sync;
+
+impl AssociationCheck {
+    pub fn new(check: impl Fn(&dyn Any, &dyn Any) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(check))
+    }
+
+    pub(crate) fn check(&self, ctx: &dyn Any, association: &dyn Any) -> bool {
+        (self.0)(ctx, association)
+    }
+}
+
+/// The error returned when an association fails the
+/// [`verify_associations`](crate::PersistOptions::verify_associations) check.
+///
+/// It is returned as a [`MaligniusError::Association`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnverifiedAssociation {
+    pub(crate) entity_type: &'static str,
+}
+
+impl UnverifiedAssociation {
+    /// Returns the type name of the association that could not be verified.
+    pub fn entity_type(&self) -> &'static str {
+        self.entity_type
+    }
+}
+
+impl fmt::Display for UnverifiedAssociation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "association `{}` was persisted but could not be verified",
+            self.entity_type
+        )
+    }
+}
+
+impl std::error::Error for UnverifiedAssociation {}
+
+impl fmt::Debug for AssociationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssociationCheck").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::params;
+
+    use crate::test_support::TestContext;
+    use crate::{association, persist_with_options, Associations, Manifest, PersistOptions};
+
+    use super::*;
+
+    const SCHEMA: &str = r#"
+                    create table if not exists movie (
+                        id integer primary key,
+                        title text not null
+                    );
+                "#;
+
+    fn movie_exists(ctx: &TestContext, id: u32) -> bool {
+        ctx.conn
+            .query_row("select count(*) from movie where id = $1", [id], |row| {
+                row.get::<_, u32>(0)
+            })
+            .is_ok_and(|count| count == 1)
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Movie {
+        pub id: u32,
+        pub title: String,
+    }
+
+    impl Manifest for Movie {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: 1,
+                    title: "Inception".into(),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Movie {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, movie: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "
+                    insert into movie (id, title) values ($1, $2)
+                ",
+                params![movie.id, movie.title],
+            )?;
+
+            Ok(movie)
+        }
+    }
+
+    /// A movie whose `Persist` implementation forgets to insert anything.
+    #[derive(Debug)]
+    struct UnsavedMovie {
+        pub id: u32,
+    }
+
+    impl Manifest for UnsavedMovie {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (Self { id: 1 }, Associations::new())
+        }
+    }
+
+    impl Persist for UnsavedMovie {
+        type Err = rusqlite::Error;
+
+        async fn persist(_ctx: &Self::Context, movie: Self) -> Result<Self, Self::Err> {
+            Ok(movie)
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_verified_succeeds_when_the_row_exists(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let movie: Movie =
+            persist_verified(ctx, (), |ctx, movie: &Movie| movie_exists(ctx, movie.id)).await?;
+
+        assert_eq!(movie.title, "Inception");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn persist_verified_fails_when_the_row_is_missing(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        let result = persist_verified(ctx, (), |ctx, movie: &UnsavedMovie| {
+            movie_exists(ctx, movie.id)
+        })
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(VerificationError::Unverified { entity_type })
+                if entity_type == std::any::type_name::<UnsavedMovie>()
+        ));
+
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct Review {
+        pub movie_id: u32,
+    }
+
+    impl Manifest for Review {
+        type Context = TestContext;
+        type Overrides = ();
+
+        fn manifest(_overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            let mut associations = Associations::new();
+            let movie: UnsavedMovie = association(&mut associations);
+
+            (Self { movie_id: movie.id }, associations)
+        }
+    }
+
+    impl Persist for Review {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, review: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "insert into review (movie_id) values ($1)",
+                [review.movie_id],
+            )?;
+
+            Ok(review)
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_associations_catches_an_association_that_was_not_written(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(SCHEMA)?;
+
+        ctx.conn.execute(
+            r#"
+                create table if not exists review (
+                    id integer primary key,
+                    movie_id integer not null references movie (id)
+                );
+            "#,
+            (),
+        )?;
+
+        let check = AssociationCheck::new(|ctx, association| {
+            let ctx = ctx.downcast_ref::<TestContext>().unwrap();
+
+            match association.downcast_ref::<UnsavedMovie>() {
+                Some(movie) => movie_exists(ctx, movie.id),
+                None => true,
+            }
+        });
+
+        let result = persist_with_options::<Review>(
+            ctx.clone(),
+            (),
+            PersistOptions {
+                verify_associations: Some(check),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let Err(MaligniusError::Association(err)) = result else {
+            panic!("expected the association to fail verification");
+        };
+        let err = err
+            .downcast_ref::<UnverifiedAssociation>()
+            .expect("expected an UnverifiedAssociation");
+        assert!(err.entity_type().ends_with("UnsavedMovie"));
+        assert!(err
+            .to_string()
+            .ends_with("UnsavedMovie` was persisted but could not be verified"));
+
+        let reviews: usize = ctx
+            .conn
+            .query_row("select count(*) from review", [], |row| row.get(0))?;
+        assert_eq!(reviews, 0, "the review should not have been persisted");
+
+        Ok(())
+    }
+}
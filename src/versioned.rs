@@ -0,0 +1,128 @@
+/// An entity with an optimistic-lock version column.
+///
+/// Factories should default the version to [`Versioned::INITIAL_VERSION`] using
+/// [`initial_version`], so that persisted entities start out at the same version the
+/// application would insert them with.
+pub trait Versioned {
+    /// The version a newly-inserted entity starts at.
+    const INITIAL_VERSION: u64 = 1;
+
+    fn version(&self) -> u64;
+
+    fn set_version(&mut self, version: u64);
+}
+
+/// Returns the version a newly-inserted `T` starts at.
+pub fn initial_version<T: Versioned>() -> u64 {
+    T::INITIAL_VERSION
+}
+
+/// Increments the version of the entity, returning the new version.
+///
+/// Keeping a copy of the entity from before the bump gives you a stale entity that can be used
+/// to exercise optimistic-lock conflicts.
+pub fn bump_version<T: Versioned>(entity: &mut T) -> u64 {
+    let version = entity.version() + 1;
+    entity.set_version(version);
+
+    version
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rusqlite::params;
+
+    use crate::test_support::TestContext;
+    use crate::{persist, Associations, Manifest, Persist};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct DocumentOverrides {
+        pub version: Option<u64>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    struct Document {
+        pub id: u32,
+        pub version: u64,
+    }
+
+    impl Versioned for Document {
+        fn version(&self) -> u64 {
+            self.version
+        }
+
+        fn set_version(&mut self, version: u64) {
+            self.version = version;
+        }
+    }
+
+    impl Manifest for Document {
+        type Context = TestContext;
+        type Overrides = DocumentOverrides;
+
+        fn manifest(overrides: Self::Overrides) -> (Self, Associations<Self::Context>) {
+            (
+                Self {
+                    id: 1,
+                    version: overrides.version.unwrap_or(initial_version::<Self>()),
+                },
+                Associations::new(),
+            )
+        }
+    }
+
+    impl Persist for Document {
+        type Err = rusqlite::Error;
+
+        async fn persist(ctx: &Self::Context, document: Self) -> Result<Self, Self::Err> {
+            ctx.conn.execute(
+                "
+                    insert into document (id, version) values ($1, $2)
+                ",
+                params![document.id, document.version],
+            )?;
+
+            Ok(document)
+        }
+    }
+
+    #[tokio::test]
+    async fn versioned_entities_are_persisted_with_the_initial_version(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ctx = TestContext::shared(
+            r#"
+                create table if not exists document (
+                    id integer primary key,
+                    version integer not null
+                );
+            "#,
+        )?;
+
+        let document: Document = persist(ctx.clone()).await?;
+
+        assert_eq!(document.version, 1);
+
+        let persisted_version: u64 = ctx.conn.query_row(
+            "select version from document where id = $1",
+            [document.id],
+            |row| row.get(0),
+        )?;
+
+        assert_eq!(persisted_version, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_version_leaves_earlier_copies_stale() {
+        let mut document = Document { id: 1, version: 1 };
+        let stale = document.clone();
+
+        assert_eq!(bump_version(&mut document), 2);
+        assert_eq!(document.version(), 2);
+        assert_eq!(stale.version(), 1);
+    }
+}